@@ -0,0 +1,126 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use aes::cbc::{AesCbcCipher, AesCbcIv};
+use aes::ctr::{AesCtrCipher, AesCtrIv, AesCtrMode};
+use aes::ecb::AesEcbCipher;
+use aes::key::AesKey;
+
+const SIZES: [usize; 3] = [16, 1024, 64 * 1024];
+
+fn keys() -> Vec<(&'static str, AesKey)> {
+    vec![
+        ("aes-128", AesKey::aes_128_key([0x2b; 16])),
+        ("aes-192", AesKey::aes_192_key([0x8e; 24])),
+        ("aes-256", AesKey::aes_256_key([0x60; 32])),
+    ]
+}
+
+fn bench_ecb(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ecb");
+    for (name, key) in keys() {
+        let cipher = AesEcbCipher::new(&key);
+        for size in SIZES {
+            let input = vec![0x5a_u8; size];
+            group.throughput(Throughput::Bytes(size as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}/encrypt", name), size),
+                &input,
+                |b, input| b.iter(|| cipher.encrypt(black_box(input)).unwrap()),
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}/decrypt", name), size),
+                &input,
+                |b, input| b.iter(|| cipher.decrypt(black_box(input)).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_cbc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cbc");
+    let iv = AesCbcIv::aes_cbc_iv([0x00; 16]);
+    for (name, key) in keys() {
+        let cipher = AesCbcCipher::new(&key, &iv);
+        for size in SIZES {
+            let input = vec![0x5a_u8; size];
+            group.throughput(Throughput::Bytes(size as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}/encrypt", name), size),
+                &input,
+                |b, input| b.iter(|| cipher.encrypt(black_box(input)).unwrap()),
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}/decrypt", name), size),
+                &input,
+                |b, input| b.iter(|| cipher.decrypt(black_box(input)).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_ctr(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ctr");
+    let iv = AesCtrIv::aes_ctr_iv([0x00; 16]);
+    for (name, key) in keys() {
+        for (mode_name, mode) in [
+            ("nist_sp800_38a", AesCtrMode::NIST_SP800_38A),
+            ("cryptopals", AesCtrMode::CRYPTOPALS),
+        ] {
+            let cipher = AesCtrCipher::new(&key, &iv, mode);
+            for size in SIZES {
+                let input = vec![0x5a_u8; size];
+                group.throughput(Throughput::Bytes(size as u64));
+                group.bench_with_input(
+                    BenchmarkId::new(format!("{}/{}", name, mode_name), size),
+                    &input,
+                    |b, input| b.iter(|| cipher.encrypt(black_box(input)).unwrap()),
+                );
+            }
+        }
+        group.bench_function(
+            BenchmarkId::new(format!("{}/free_function", name), 1024),
+            |b| {
+                let input = vec![0x5a_u8; 1024];
+                b.iter(|| {
+                    aes::ctr::encrypt(key.as_slice(), iv.as_slice(), black_box(&input)).unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+#[cfg(feature = "parallel")]
+fn bench_parallel(c: &mut Criterion) {
+    use aes::parallel::DEFAULT_CHUNK_SIZE;
+    let mut group = c.benchmark_group("parallel");
+    let key = AesKey::aes_128_key([0x2b; 16]);
+    let iv = AesCtrIv::aes_ctr_iv([0x00; 16]);
+    let ecb = AesEcbCipher::new(&key);
+    let ctr = AesCtrCipher::new(&key, &iv, AesCtrMode::NIST_SP800_38A);
+    for size in [64 * 1024, 16 * 1024 * 1024] {
+        let input = vec![0x5a_u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("ecb/encrypt", size), &input, |b, input| {
+            b.iter(|| {
+                ecb.par_encrypt(black_box(input), DEFAULT_CHUNK_SIZE)
+                    .unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("ctr/encrypt", size), &input, |b, input| {
+            b.iter(|| {
+                ctr.par_encrypt(black_box(input), DEFAULT_CHUNK_SIZE)
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+#[cfg(not(feature = "parallel"))]
+criterion_group!(benches, bench_ecb, bench_cbc, bench_ctr);
+#[cfg(feature = "parallel")]
+criterion_group!(benches, bench_ecb, bench_cbc, bench_ctr, bench_parallel);
+criterion_main!(benches);
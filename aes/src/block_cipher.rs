@@ -0,0 +1,43 @@
+use crate::error::AesError;
+
+// A keyed block cipher. The ECB, CBC, and CTR modes (and the oracles built on
+// them) are generic over this, so any cipher implementing it gets them for free.
+pub trait BlockCipher: Clone {
+    type Key;
+
+    // What `key_from_slice` fails with.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    fn block_size() -> usize;
+
+    fn from_key(key: &Self::Key) -> Self;
+
+    // Parses raw key bytes, for callers that only carry a `Vec<u8>` key.
+    fn key_from_slice(bytes: &[u8]) -> Result<Self::Key, Self::Error>;
+
+    // Encrypts exactly one block in place.
+    fn encrypt_block_mut(&self, block: &mut [u8]);
+
+    // Decrypts exactly one block in place.
+    fn decrypt_block_mut(&self, block: &mut [u8]);
+
+    // Encrypts every block of `blocks` in place; implementations may override
+    // this to process several blocks at once.
+    fn encrypt_blocks_mut(&self, blocks: &mut [u8]) {
+        for block in blocks.chunks_exact_mut(Self::block_size()) {
+            self.encrypt_block_mut(block);
+        }
+    }
+
+    // Decrypts every block of `blocks` in place.
+    fn decrypt_blocks_mut(&self, blocks: &mut [u8]) {
+        for block in blocks.chunks_exact_mut(Self::block_size()) {
+            self.decrypt_block_mut(block);
+        }
+    }
+}
+
+// PKCS#7 can only describe padding for block sizes that fit in a byte.
+pub(crate) fn pkcs7_block_size<C: BlockCipher>() -> Result<u8, AesError> {
+    u8::try_from(C::block_size()).map_err(|_| AesError::InvalidBlockSize(C::block_size()))
+}
@@ -1,7 +1,9 @@
+use pkcs7::Pkcs7Mut;
 use quickcheck::{single_shrinker, Arbitrary, Gen};
 use rand::prelude::*;
-use xor::exor::{exor, exor_mut};
+use xor::exor::exor_mut;
 
+use crate::block_cipher::{pkcs7_block_size, BlockCipher};
 use crate::ecb::AesEcbBlockCipher;
 use crate::error::AesError;
 use crate::key::AesKey;
@@ -38,6 +40,12 @@ impl AesCbcIv {
     }
 }
 
+impl AsRef<[u8]> for AesCbcIv {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 impl Distribution<AesCbcIv> for rand::distributions::Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AesCbcIv {
         AesCbcIv(rng.gen())
@@ -71,61 +79,307 @@ impl Arbitrary for AesCbcIv {
     }
 }
 
-pub struct AesCbcCipher<'k, 'iv> {
-    key: &'k AesKey,
-    iv: &'iv AesCbcIv,
+// See: https://csrc.nist.gov/publications/detail/sp/800-38a/addendum/final
+//
+// The ciphertext stealing variants accept any input of at least 16 bytes and
+// produce output of the same length. They only differ in the order of the
+// final two ciphertext blocks, where the penultimate one is truncated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum AesCbcMode {
+    STANDARD, // input must be a multiple of 16 bytes
+    CS1,      // ... || C[n-1]* || C[n]
+    CS2,      // as CS1 when block aligned, otherwise as CS3
+    CS3,      // ... || C[n] || C[n-1]*, swapped even when block aligned (Kerberos)
+}
+
+impl AesCbcMode {
+    pub fn decrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        self,
+        key: &K,
+        iv: &IV,
+        ciphertext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let key = AesKey::try_copy_from_slice(key)?;
+        let iv = AesCbcIv::try_copy_from_slice(iv)?;
+        let cipher = AesCbcCipher::with_mode(&key, &iv, self);
+        cipher.decrypt(ciphertext)
+    }
+
+    pub fn encrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        self,
+        key: &K,
+        iv: &IV,
+        plaintext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let key = AesKey::try_copy_from_slice(key)?;
+        let iv = AesCbcIv::try_copy_from_slice(iv)?;
+        let cipher = AesCbcCipher::with_mode(&key, &iv, self);
+        cipher.encrypt(plaintext)
+    }
+
+    // Whether the final two blocks are stored as `C[n] || C[n-1]*`.
+    fn swaps_final_blocks(self, length: usize, block_size: usize) -> bool {
+        match self {
+            AesCbcMode::STANDARD | AesCbcMode::CS1 => false,
+            AesCbcMode::CS2 => !length.is_multiple_of(block_size),
+            AesCbcMode::CS3 => true,
+        }
+    }
+}
+
+impl Distribution<AesCbcMode> for rand::distributions::Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AesCbcMode {
+        match rng.gen_range(0..4) {
+            0 => AesCbcMode::STANDARD,
+            1 => AesCbcMode::CS1,
+            2 => AesCbcMode::CS2,
+            _ => AesCbcMode::CS3,
+        }
+    }
+}
+
+impl Arbitrary for AesCbcMode {
+    fn arbitrary(g: &mut Gen) -> AesCbcMode {
+        *g.choose(&[
+            AesCbcMode::STANDARD,
+            AesCbcMode::CS1,
+            AesCbcMode::CS2,
+            AesCbcMode::CS3,
+        ])
+        .unwrap()
+    }
+}
+
+// The IV must be exactly one block long, which is checked once when the
+// cipher is built. The AES constructors take a typed IV, which always is.
+pub struct CbcCipher<'k, 'iv, C: BlockCipher> {
+    block_cipher: C,
+    key: std::marker::PhantomData<&'k C::Key>,
+    iv: &'iv [u8],
+    mode: AesCbcMode,
 }
 
+pub type AesCbcCipher<'k, 'iv> = CbcCipher<'k, 'iv, AesEcbBlockCipher>;
+
 impl<'k, 'iv> AesCbcCipher<'k, 'iv> {
     pub fn new(key: &'k AesKey, iv: &'iv AesCbcIv) -> AesCbcCipher<'k, 'iv> {
-        AesCbcCipher { key, iv }
+        Self::with_mode(key, iv, AesCbcMode::STANDARD)
+    }
+
+    pub fn with_mode(
+        key: &'k AesKey,
+        iv: &'iv AesCbcIv,
+        mode: AesCbcMode,
+    ) -> AesCbcCipher<'k, 'iv> {
+        CbcCipher {
+            block_cipher: AesEcbBlockCipher::new(key),
+            key: std::marker::PhantomData,
+            iv: iv.as_slice(),
+            mode,
+        }
+    }
+}
+
+impl<'k, 'iv, C: BlockCipher> CbcCipher<'k, 'iv, C> {
+    pub fn try_new<IV: ?Sized + AsRef<[u8]>>(
+        key: &'k C::Key,
+        iv: &'iv IV,
+    ) -> Result<CbcCipher<'k, 'iv, C>, AesError> {
+        Self::try_with_mode(key, iv, AesCbcMode::STANDARD)
+    }
+
+    pub fn try_with_mode<IV: ?Sized + AsRef<[u8]>>(
+        key: &'k C::Key,
+        iv: &'iv IV,
+        mode: AesCbcMode,
+    ) -> Result<CbcCipher<'k, 'iv, C>, AesError> {
+        let iv = iv.as_ref();
+        if iv.len() != C::block_size() {
+            return Err(AesError::InvalidInitializationVectorSize {
+                was: iv.len(),
+                explanation: "must be one block",
+            });
+        }
+        Ok(CbcCipher {
+            block_cipher: C::from_key(key),
+            key: std::marker::PhantomData,
+            iv,
+            mode,
+        })
+    }
+
+    pub fn mode(&self) -> AesCbcMode {
+        self.mode
     }
 
     pub fn decrypt<T: ?Sized + AsRef<[u8]>>(&self, ciphertext: &T) -> Result<Vec<u8>, AesError> {
-        let ciphertext = ciphertext.as_ref();
-        match ciphertext.len() {
-            0 => Ok(vec![]),
-            x => {
-                if x % 16 == 0 {
-                    let block_cipher = AesEcbBlockCipher::new(&self.key);
-                    let mut iv = [0u8; 16];
-                    iv.copy_from_slice(&self.iv.0[..]);
-                    Ok(ciphertext
-                        .chunks(16_usize)
-                        .flat_map(|encrypted_block| {
-                            let mut decrypted_block =
-                                block_cipher.decrypt_block(&encrypted_block).unwrap();
-                            exor_mut(&mut decrypted_block, &iv).unwrap();
-                            iv.copy_from_slice(&encrypted_block[..]);
-                            decrypted_block.into_iter()
-                        })
-                        .collect())
-                } else {
-                    Err(AesError::InvalidBlockSize(x))
-                }
+        match self.mode {
+            AesCbcMode::STANDARD => self.decrypt_blocks(ciphertext.as_ref()),
+            _ => {
+                let mut plaintext = ciphertext.as_ref().to_vec();
+                self.decrypt_in_place(&mut plaintext)?;
+                Ok(plaintext)
             }
         }
     }
 
     pub fn encrypt<T: ?Sized + AsRef<[u8]>>(&self, plaintext: &T) -> Result<Vec<u8>, AesError> {
-        let plaintext = plaintext.as_ref();
-        match plaintext.len() {
+        let mut ciphertext = plaintext.as_ref().to_vec();
+        self.encrypt_in_place(&mut ciphertext)?;
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt_in_place(&self, buffer: &mut [u8]) -> Result<(), AesError> {
+        match self.mode {
+            AesCbcMode::STANDARD => self.decrypt_blocks_in_place(buffer),
+            mode => self.decrypt_stealing_in_place(buffer, mode),
+        }
+    }
+
+    pub fn encrypt_in_place(&self, buffer: &mut [u8]) -> Result<(), AesError> {
+        match self.mode {
+            AesCbcMode::STANDARD => self.encrypt_blocks_in_place(buffer),
+            mode => self.encrypt_stealing_in_place(buffer, mode),
+        }
+    }
+
+    // Decrypts and then strips the PKCS#7 padding.
+    pub fn decrypt_padded_in_place(&self, buffer: &mut Vec<u8>) -> Result<(), AesError> {
+        let block_size = pkcs7_block_size::<C>()?;
+        self.decrypt_in_place(buffer)?;
+        buffer.pkcs7_unpad_mut(block_size)?;
+        Ok(())
+    }
+
+    // Applies PKCS#7 padding and then encrypts. Like `pkcs7::pad`, an empty
+    // buffer gets no padding block and stays empty.
+    pub fn encrypt_padded_in_place(&self, buffer: &mut Vec<u8>) -> Result<(), AesError> {
+        buffer.pkcs7_pad_mut(pkcs7_block_size::<C>()?)?;
+        self.encrypt_in_place(buffer)
+    }
+
+    fn decrypt_stealing_in_place(
+        &self,
+        buffer: &mut [u8],
+        mode: AesCbcMode,
+    ) -> Result<(), AesError> {
+        let bs = C::block_size();
+        let length = buffer.len();
+        if length < bs {
+            return Err(AesError::InvalidBlockSize(length));
+        }
+        if length == bs {
+            return self.decrypt_blocks_in_place(buffer);
+        }
+        let partial = match length % bs {
+            0 => bs,
+            x => x,
+        };
+        let tail = &mut buffer[length - bs - partial..];
+        if !mode.swaps_final_blocks(length, bs) {
+            tail.rotate_right(bs);
+        }
+        // The tail is now `C[n] || C[n-1]*`. Decrypting `C[n]` yields
+        // `(P[n]* || 0) ^ C[n-1]`, which recovers both the final partial
+        // plaintext and the stolen tail of `C[n-1]`.
+        self.block_cipher.decrypt_block_mut(&mut tail[..bs]);
+        for i in 0..partial {
+            let stolen = tail[bs + i];
+            tail[bs + i] = tail[i] ^ stolen;
+            tail[i] = stolen;
+        }
+        self.decrypt_blocks_in_place(&mut buffer[..length - partial])
+    }
+
+    fn encrypt_stealing_in_place(
+        &self,
+        buffer: &mut [u8],
+        mode: AesCbcMode,
+    ) -> Result<(), AesError> {
+        let bs = C::block_size();
+        let length = buffer.len();
+        if length < bs {
+            return Err(AesError::InvalidBlockSize(length));
+        }
+        if length == bs {
+            return self.encrypt_blocks_in_place(buffer);
+        }
+        let partial = match length % bs {
+            0 => bs,
+            x => x,
+        };
+        self.encrypt_blocks_in_place(&mut buffer[..length - partial])?;
+        // The tail is `C[n-1] || P[n]*`. XOR-swapping the first `partial`
+        // bytes leaves `(P[n]* || 0) ^ C[n-1]` followed by the stolen bytes
+        // of `C[n-1]`, so encrypting the first block gives `C[n] || C[n-1]*`.
+        let tail = &mut buffer[length - bs - partial..];
+        for i in 0..partial {
+            tail[i] ^= tail[bs + i];
+            tail[bs + i] ^= tail[i];
+        }
+        self.block_cipher.encrypt_block_mut(&mut tail[..bs]);
+        if !mode.swaps_final_blocks(length, bs) {
+            tail.rotate_left(bs);
+        }
+        Ok(())
+    }
+
+    // Walks backwards so that each preceding ciphertext block is still intact
+    // when it is needed to un-chain the current one.
+    fn decrypt_blocks_in_place(&self, buffer: &mut [u8]) -> Result<(), AesError> {
+        let bs = C::block_size();
+        if !buffer.len().is_multiple_of(bs) {
+            return Err(AesError::InvalidBlockSize(buffer.len()));
+        }
+        for offset in (0..buffer.len()).step_by(bs).rev() {
+            let (previous_blocks, blocks) = buffer.split_at_mut(offset);
+            let block = &mut blocks[..bs];
+            self.block_cipher.decrypt_block_mut(block);
+            let previous_block = match offset {
+                0 => self.iv,
+                _ => &previous_blocks[offset - bs..],
+            };
+            exor_mut(block, previous_block).unwrap();
+        }
+        Ok(())
+    }
+
+    fn encrypt_blocks_in_place(&self, buffer: &mut [u8]) -> Result<(), AesError> {
+        let bs = C::block_size();
+        if !buffer.len().is_multiple_of(bs) {
+            return Err(AesError::InvalidBlockSize(buffer.len()));
+        }
+        for offset in (0..buffer.len()).step_by(bs) {
+            let (previous_blocks, blocks) = buffer.split_at_mut(offset);
+            let block = &mut blocks[..bs];
+            let previous_block = match offset {
+                0 => self.iv,
+                _ => &previous_blocks[offset - bs..],
+            };
+            exor_mut(block, previous_block).unwrap();
+            self.block_cipher.encrypt_block_mut(block);
+        }
+        Ok(())
+    }
+
+    fn decrypt_blocks(&self, ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        let bs = C::block_size();
+        match ciphertext.len() {
             0 => Ok(vec![]),
             x => {
-                if x % 16 == 0 {
-                    let block_cipher = AesEcbBlockCipher::new(&self.key);
-                    let mut iv = [0u8; 16];
-                    iv.copy_from_slice(&self.iv.0[..]);
-                    Ok(plaintext
-                        .chunks(16_usize)
-                        .flat_map(|decrypted_block| {
-                            let decrypted_block: Vec<u8> = exor(&decrypted_block, &iv).unwrap();
-                            let encrypted_block =
-                                block_cipher.encrypt_block(&decrypted_block).unwrap();
-                            iv.copy_from_slice(&encrypted_block[..]);
-                            encrypted_block.into_iter()
-                        })
-                        .collect())
+                if x % bs == 0 {
+                    // Block decryption does not depend on the chain, so every
+                    // block is decrypted in one batch before un-chaining.
+                    let mut plaintext = ciphertext.to_vec();
+                    self.block_cipher.decrypt_blocks_mut(&mut plaintext);
+                    let previous_blocks = std::iter::once(self.iv).chain(ciphertext.chunks(bs));
+                    for (decrypted_block, previous_block) in
+                        plaintext.chunks_mut(bs).zip(previous_blocks)
+                    {
+                        exor_mut(decrypted_block, &previous_block).unwrap();
+                    }
+                    Ok(plaintext)
                 } else {
                     Err(AesError::InvalidBlockSize(x))
                 }
@@ -155,3 +409,184 @@ pub fn encrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + As
     let cipher = AesCbcCipher::new(&key, &iv);
     cipher.encrypt(plaintext)
 }
+
+pub fn decrypt_in_place<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    buffer: &mut [u8],
+) -> Result<(), AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesCbcCipher::new(&key, &iv);
+    cipher.decrypt_in_place(buffer)
+}
+
+pub fn encrypt_in_place<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    buffer: &mut [u8],
+) -> Result<(), AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesCbcCipher::new(&key, &iv);
+    cipher.encrypt_in_place(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cbc::{AesCbcCipher, AesCbcIv, AesCbcMode};
+    use crate::ecb::AesEcbBlockCipher;
+    use crate::key::AesKey;
+
+    #[quickcheck]
+    fn encrypt_matches_manual_chaining(key: AesKey, iv: AesCbcIv, blocks: Vec<u128>) -> bool {
+        let plaintext: Vec<u8> = blocks.iter().flat_map(|b| b.to_be_bytes()).collect();
+        let block_cipher = AesEcbBlockCipher::new(&key);
+        let mut previous_block: Vec<u8> = iv.to_vec();
+        let mut expected: Vec<u8> = Vec::new();
+        for block in plaintext.chunks(16) {
+            let block: Vec<u8> = xor::exor::exor(&block, &previous_block).unwrap();
+            previous_block = block_cipher.encrypt_block(&block).unwrap();
+            expected.extend_from_slice(&previous_block);
+        }
+        let cipher = AesCbcCipher::new(&key, &iv);
+        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        expected == ciphertext && plaintext == cipher.decrypt(&ciphertext).unwrap()
+    }
+
+    // RFC 3962, Appendix B. Kerberos uses CBC-CS3 with a zero IV.
+    #[test]
+    fn rfc_3962_vectors() {
+        let key = AesKey::aes_128_key(*b"chicken teriyaki");
+        let iv = AesCbcIv::aes_cbc_iv([0_u8; 16]);
+        let cipher = AesCbcCipher::with_mode(&key, &iv, AesCbcMode::CS3);
+        let plaintext = b"I would like the General Gau's Chicken, please, and wonton soup.";
+        for (length, ciphertext) in [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (
+                31,
+                "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+            ),
+            (
+                32,
+                "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+            ),
+            (
+                47,
+                "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e\
+                 39312523a78662d5be7fcbcc98ebf5",
+            ),
+            (
+                48,
+                "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd8\
+                 39312523a78662d5be7fcbcc98ebf5a8",
+            ),
+            (
+                64,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+                 4807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8",
+            ),
+        ] {
+            let ciphertext = hex::decode(ciphertext).unwrap();
+            assert_eq!(ciphertext, cipher.encrypt(&plaintext[..length]).unwrap());
+            assert_eq!(
+                &plaintext[..length],
+                &cipher.decrypt(&ciphertext).unwrap()[..]
+            );
+        }
+    }
+
+    #[quickcheck]
+    fn stealing_preserves_length(
+        key: AesKey,
+        iv: AesCbcIv,
+        mode: AesCbcMode,
+        plaintext: Vec<u8>,
+    ) -> bool {
+        let cipher = AesCbcCipher::with_mode(&key, &iv, mode);
+        match cipher.encrypt(&plaintext) {
+            Ok(ciphertext) => {
+                ciphertext.len() == plaintext.len()
+                    && plaintext == cipher.decrypt(&ciphertext).unwrap()
+            }
+            Err(_) => match mode {
+                AesCbcMode::STANDARD => !plaintext.len().is_multiple_of(16),
+                _ => plaintext.len() < 16,
+            },
+        }
+    }
+
+    #[quickcheck]
+    fn in_place_matches_allocating(
+        key: AesKey,
+        iv: AesCbcIv,
+        mode: AesCbcMode,
+        plaintext: Vec<u8>,
+    ) -> bool {
+        let cipher = AesCbcCipher::with_mode(&key, &iv, mode);
+        let mut buffer = plaintext.clone();
+        match cipher.encrypt(&plaintext) {
+            Ok(expected) => {
+                cipher.encrypt_in_place(&mut buffer).unwrap();
+                let encrypted = expected == buffer;
+                cipher.decrypt_in_place(&mut buffer).unwrap();
+                encrypted && plaintext == buffer
+            }
+            Err(error) => Err(error) == cipher.encrypt_in_place(&mut buffer),
+        }
+    }
+
+    #[quickcheck]
+    fn padded_in_place_round_trip(key: AesKey, iv: AesCbcIv, plaintext: Vec<u8>) -> bool {
+        let cipher = AesCbcCipher::new(&key, &iv);
+        let mut buffer = plaintext.clone();
+        cipher.encrypt_padded_in_place(&mut buffer).unwrap();
+        let padded: Vec<u8> = pkcs7::pad(&plaintext, 16).unwrap();
+        let encrypted = cipher.encrypt(&padded).unwrap() == buffer;
+        cipher.decrypt_padded_in_place(&mut buffer).unwrap();
+        encrypted && plaintext == buffer
+    }
+
+    #[test]
+    fn padded_in_place_leaves_empty_input_empty() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let iv = AesCbcIv::aes_cbc_iv([0_u8; 16]);
+        let cipher = AesCbcCipher::new(&key, &iv);
+        let mut buffer: Vec<u8> = vec![];
+        cipher.encrypt_padded_in_place(&mut buffer).unwrap();
+        assert!(buffer.is_empty());
+        cipher.decrypt_padded_in_place(&mut buffer).unwrap();
+        assert!(buffer.is_empty());
+    }
+
+    #[quickcheck]
+    fn stealing_variants_only_reorder_final_blocks(
+        key: AesKey,
+        iv: AesCbcIv,
+        plaintext: Vec<u8>,
+    ) -> bool {
+        if plaintext.len() <= 16 {
+            return true;
+        }
+        let encrypt = |mode| {
+            AesCbcCipher::with_mode(&key, &iv, mode)
+                .encrypt(&plaintext)
+                .unwrap()
+        };
+        let (cs1, cs2, cs3) = (
+            encrypt(AesCbcMode::CS1),
+            encrypt(AesCbcMode::CS2),
+            encrypt(AesCbcMode::CS3),
+        );
+        let partial = match plaintext.len() % 16 {
+            0 => 16,
+            x => x,
+        };
+        let mut swapped = cs1.clone();
+        swapped[plaintext.len() - 16 - partial..].rotate_right(16);
+        let aligned = plaintext.len().is_multiple_of(16);
+        cs3 == swapped
+            && cs2 == if aligned { cs1.clone() } else { swapped }
+            && (!aligned || cs1 == AesCbcCipher::new(&key, &iv).encrypt(&plaintext).unwrap())
+    }
+}
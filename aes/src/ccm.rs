@@ -0,0 +1,473 @@
+// See: https://tools.ietf.org/html/rfc3610
+// See: https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38c.pdf
+
+use quickcheck::{Arbitrary, Gen};
+use rand::prelude::*;
+use xor::ct::ct_eq;
+
+use crate::ctr::{AesCtrIv, AesCtrKeystream, AesCtrMode};
+use crate::ecb::AesEcbBlockCipher;
+use crate::error::AesError;
+use crate::key::AesKey;
+
+#[derive(Clone, Debug)]
+pub struct AesCcmNonce(Vec<u8>);
+
+impl AesCcmNonce {
+    pub fn aes_ccm_nonce(value: [u8; 13]) -> AesCcmNonce {
+        AesCcmNonce(value.to_vec())
+    }
+
+    // The nonce length `N` fixes the size of the length field to `15 - N`
+    // bytes, so shorter nonces allow longer messages.
+    pub fn try_copy_from_slice<N: ?Sized + AsRef<[u8]>>(
+        bytes: &N,
+    ) -> Result<AesCcmNonce, AesError> {
+        let bytes = bytes.as_ref();
+        match bytes.len() {
+            7..=13 => Ok(AesCcmNonce(bytes.to_vec())),
+            x => Err(AesError::InvalidInitializationVectorSize {
+                was: x,
+                explanation: "must be between 7 and 13 bytes",
+            }),
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    // Size in bytes of the message length field (`L` in RFC 3610).
+    fn length_size(&self) -> usize {
+        15 - self.0.len()
+    }
+}
+
+impl Distribution<AesCcmNonce> for rand::distributions::Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AesCcmNonce {
+        AesCcmNonce::aes_ccm_nonce(rng.gen())
+    }
+}
+
+impl Arbitrary for AesCcmNonce {
+    fn arbitrary(g: &mut Gen) -> AesCcmNonce {
+        let length = *g.choose(&[7, 8, 9, 10, 11, 12, 13]).unwrap();
+        AesCcmNonce((0..length).map(|_| u8::arbitrary(g)).collect())
+    }
+}
+
+pub struct AesCcmCipher<'k, 'n> {
+    key: &'k AesKey,
+    nonce: &'n AesCcmNonce,
+    tag_size: usize,
+}
+
+impl<'k, 'n> AesCcmCipher<'k, 'n> {
+    pub fn new(key: &'k AesKey, nonce: &'n AesCcmNonce) -> AesCcmCipher<'k, 'n> {
+        AesCcmCipher {
+            key,
+            nonce,
+            tag_size: 16,
+        }
+    }
+
+    // The tag size must be one of 4, 6, 8, 10, 12, 14, or 16.
+    pub fn with_tag_size(
+        key: &'k AesKey,
+        nonce: &'n AesCcmNonce,
+        tag_size: usize,
+    ) -> Result<AesCcmCipher<'k, 'n>, AesError> {
+        if !(4..=16).contains(&tag_size) || !tag_size.is_multiple_of(2) {
+            return Err(AesError::InvalidTagSize(tag_size));
+        }
+        Ok(AesCcmCipher {
+            key,
+            nonce,
+            tag_size,
+        })
+    }
+
+    pub fn tag_size(&self) -> usize {
+        self.tag_size
+    }
+
+    // The largest message that fits in the length field.
+    pub fn max_message_size(&self) -> u64 {
+        match self.nonce.length_size() {
+            8 => u64::MAX,
+            l => (1_u64 << (8 * l)) - 1,
+        }
+    }
+
+    // Counter block A_0. The counter blocks share the nonce with B_0 and
+    // count upwards in the trailing length-field bytes.
+    fn initial_counter_block(&self) -> AesCtrIv {
+        let nonce = self.nonce.as_slice();
+        let mut a0 = [0_u8; 16];
+        a0[0] = (self.nonce.length_size() - 1) as u8;
+        a0[1..1 + nonce.len()].copy_from_slice(nonce);
+        AesCtrIv::aes_ctr_iv(a0)
+    }
+
+    fn compute_tag(
+        &self,
+        block_cipher: &AesEcbBlockCipher,
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, AesError> {
+        if plaintext.len() as u64 > self.max_message_size() {
+            return Err(AesError::MessageTooLong(plaintext.len()));
+        }
+        let nonce = self.nonce.as_slice();
+        let length_size = self.nonce.length_size();
+        let mut b0 = [0_u8; 16];
+        b0[0] = if aad.is_empty() { 0 } else { 0x40 }
+            | (((self.tag_size - 2) / 2) as u8) << 3
+            | (length_size - 1) as u8;
+        b0[1..1 + nonce.len()].copy_from_slice(nonce);
+        b0[16 - length_size..]
+            .copy_from_slice(&(plaintext.len() as u64).to_be_bytes()[8 - length_size..]);
+        let mut mac = CbcMac::new(block_cipher, b0);
+        if !aad.is_empty() {
+            let aad_length = aad.len() as u64;
+            if aad_length < 0xff00 {
+                mac.update(&(aad_length as u16).to_be_bytes());
+            } else if aad_length <= u32::MAX as u64 {
+                mac.update(&[0xff, 0xfe]);
+                mac.update(&(aad_length as u32).to_be_bytes());
+            } else {
+                mac.update(&[0xff, 0xff]);
+                mac.update(&aad_length.to_be_bytes());
+            }
+            mac.update(aad);
+            mac.pad();
+        }
+        mac.update(plaintext);
+        mac.pad();
+        let mut s0 = [0_u8; 16];
+        s0.copy_from_slice(self.initial_counter_block().as_slice());
+        block_cipher.encrypt_blocks_mut(&mut s0);
+        Ok(mac
+            .state
+            .iter()
+            .zip(s0.iter())
+            .take(self.tag_size)
+            .map(|(a, b)| a ^ b)
+            .collect())
+    }
+
+    fn crypt(&self, block_cipher: &AesEcbBlockCipher, input: &[u8]) -> Result<Vec<u8>, AesError> {
+        // The counter never carries out of the length field for a message that
+        // fits in it, so a full 128-bit increment gives the same blocks.
+        let mut counter = self.initial_counter_block();
+        counter.increment(AesCtrMode::NIST_SP800_38A)?;
+        let mut output = input.to_vec();
+        AesCtrKeystream::from_block_cipher(block_cipher, &counter, AesCtrMode::NIST_SP800_38A)?
+            .apply(&mut output)?;
+        Ok(output)
+    }
+
+    pub fn encrypt_detached<A: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        aad: &A,
+        plaintext: &T,
+    ) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+        let plaintext = plaintext.as_ref();
+        let block_cipher = AesEcbBlockCipher::new(self.key);
+        let tag = self.compute_tag(&block_cipher, aad.as_ref(), plaintext)?;
+        let ciphertext = self.crypt(&block_cipher, plaintext)?;
+        Ok((ciphertext, tag))
+    }
+
+    // The plaintext is only returned once the tag has been verified.
+    pub fn decrypt_detached<
+        A: ?Sized + AsRef<[u8]>,
+        T: ?Sized + AsRef<[u8]>,
+        G: ?Sized + AsRef<[u8]>,
+    >(
+        &self,
+        aad: &A,
+        ciphertext: &T,
+        tag: &G,
+    ) -> Result<Vec<u8>, AesError> {
+        let tag = tag.as_ref();
+        if tag.len() != self.tag_size {
+            return Err(AesError::InvalidTagSize(tag.len()));
+        }
+        let block_cipher = AesEcbBlockCipher::new(self.key);
+        let mut plaintext = self.crypt(&block_cipher, ciphertext.as_ref())?;
+        let challenge = self.compute_tag(&block_cipher, aad.as_ref(), &plaintext)?;
+        if !ct_eq(&challenge, tag) {
+            plaintext.iter_mut().for_each(|b| *b = 0);
+            return Err(AesError::AuthenticationFailed);
+        }
+        Ok(plaintext)
+    }
+
+    // Returns the ciphertext with the tag appended.
+    pub fn encrypt<A: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        aad: &A,
+        plaintext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let (mut ciphertext, tag) = self.encrypt_detached(aad, plaintext)?;
+        ciphertext.extend(tag);
+        Ok(ciphertext)
+    }
+
+    // Expects the ciphertext with the tag appended.
+    pub fn decrypt<A: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        aad: &A,
+        ciphertext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let ciphertext = ciphertext.as_ref();
+        if ciphertext.len() < self.tag_size {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - self.tag_size);
+        self.decrypt_detached(aad, ciphertext, tag)
+    }
+}
+
+// Raw CBC-MAC with a zero IV, used for the authentication part of CCM.
+struct CbcMac<'b> {
+    block_cipher: &'b AesEcbBlockCipher,
+    state: [u8; 16],
+    offset: usize,
+}
+
+impl<'b> CbcMac<'b> {
+    fn new(block_cipher: &'b AesEcbBlockCipher, b0: [u8; 16]) -> CbcMac<'b> {
+        let mut state = b0;
+        block_cipher.encrypt_blocks_mut(&mut state);
+        CbcMac {
+            block_cipher,
+            state,
+            offset: 0,
+        }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        for &byte in input {
+            self.state[self.offset] ^= byte;
+            self.offset += 1;
+            if self.offset == 16 {
+                self.block_cipher.encrypt_blocks_mut(&mut self.state);
+                self.offset = 0;
+            }
+        }
+    }
+
+    // Completes a partial block with zero bytes.
+    fn pad(&mut self) {
+        if self.offset != 0 {
+            self.block_cipher.encrypt_blocks_mut(&mut self.state);
+            self.offset = 0;
+        }
+    }
+}
+
+pub fn decrypt<
+    K: ?Sized + AsRef<[u8]>,
+    N: ?Sized + AsRef<[u8]>,
+    A: ?Sized + AsRef<[u8]>,
+    T: ?Sized + AsRef<[u8]>,
+>(
+    key: &K,
+    nonce: &N,
+    aad: &A,
+    ciphertext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let nonce = AesCcmNonce::try_copy_from_slice(nonce)?;
+    let cipher = AesCcmCipher::new(&key, &nonce);
+    cipher.decrypt(aad, ciphertext)
+}
+
+pub fn encrypt<
+    K: ?Sized + AsRef<[u8]>,
+    N: ?Sized + AsRef<[u8]>,
+    A: ?Sized + AsRef<[u8]>,
+    T: ?Sized + AsRef<[u8]>,
+>(
+    key: &K,
+    nonce: &N,
+    aad: &A,
+    plaintext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let nonce = AesCcmNonce::try_copy_from_slice(nonce)?;
+    let cipher = AesCcmCipher::new(&key, &nonce);
+    cipher.encrypt(aad, plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ccm::{AesCcmCipher, AesCcmNonce};
+    use crate::error::AesError;
+    use crate::key::AesKey;
+
+    // (nonce, packet length, header length, tag size, output) from RFC 3610
+    // packet vectors #1 through #4, #7, and #10. The packet is the bytes
+    // 0x00, 0x01, ... with the leading header bytes used as associated data.
+    const TEST_VECTORS: [(&str, usize, usize, usize, &str); 6] = [
+        (
+            "00000003020100a0a1a2a3a4a5",
+            31,
+            8,
+            8,
+            "588c979a61c663d2f066d0c2c0f989806d5f6b61dac38417e8d12cfdf926e0",
+        ),
+        (
+            "00000004030201a0a1a2a3a4a5",
+            32,
+            8,
+            8,
+            "72c91a36e135f8cf291ca894085c87e3cc15c439c9e43a3ba091d56e10400916",
+        ),
+        (
+            "00000005040302a0a1a2a3a4a5",
+            33,
+            8,
+            8,
+            "51b1e5f44a197d1da46b0f8e2d282ae871e838bb64da8596574adaa76fbd9fb0c5",
+        ),
+        (
+            "00000006050403a0a1a2a3a4a5",
+            31,
+            12,
+            8,
+            "a28c6865939a9a79faaa5c4c2a9d4a91cdac8c96c861b9c9e61ef1",
+        ),
+        (
+            "00000009080706a0a1a2a3a4a5",
+            31,
+            8,
+            10,
+            "0135d1b2c95f41d5d1d4fec185d166b8094e999dfed96c048c56602c97acbb7490",
+        ),
+        (
+            "0000000c0b0a09a0a1a2a3a4a5",
+            31,
+            12,
+            10,
+            "07342594157785152b074098330abb141b947b566aa9406b4d999988dd",
+        ),
+    ];
+
+    #[test]
+    fn rfc_3610_vectors() {
+        let key =
+            AesKey::try_copy_from_slice(&hex::decode("c0c1c2c3c4c5c6c7c8c9cacbcccdcecf").unwrap())
+                .unwrap();
+        for (nonce, packet_length, header_length, tag_size, output) in TEST_VECTORS {
+            let nonce = AesCcmNonce::try_copy_from_slice(&hex::decode(nonce).unwrap()).unwrap();
+            let packet: Vec<u8> = (0..packet_length as u8).collect();
+            let (aad, plaintext) = packet.split_at(header_length);
+            let output = hex::decode(output).unwrap();
+            let cipher = AesCcmCipher::with_tag_size(&key, &nonce, tag_size).unwrap();
+            assert_eq!(output, cipher.encrypt(aad, plaintext).unwrap());
+            assert_eq!(plaintext, &cipher.decrypt(aad, &output).unwrap()[..]);
+        }
+    }
+
+    // (nonce, associated data length, payload length, tag size, output) from
+    // NIST SP 800-38C appendix C, examples 1 through 3. The associated data is
+    // the bytes 0x00, 0x01, ... and the payload the bytes 0x20, 0x21, ...
+    const SP_800_38C_EXAMPLES: [(&str, usize, usize, usize, &str); 3] = [
+        ("10111213141516", 8, 4, 4, "7162015b4dac255d"),
+        (
+            "1011121314151617",
+            16,
+            16,
+            6,
+            "d2a1f0e051ea5f62081a7792073d593d1fc64fbfaccd",
+        ),
+        (
+            "101112131415161718191a1b",
+            20,
+            24,
+            8,
+            "e3b201a9f5b71a7a9b1ceaeccd97e70b6176aad9a4428aa5484392fbc1b09951",
+        ),
+    ];
+
+    #[test]
+    fn sp_800_38c_examples() {
+        let key =
+            AesKey::try_copy_from_slice(&hex::decode("404142434445464748494a4b4c4d4e4f").unwrap())
+                .unwrap();
+        for (nonce, aad_length, payload_length, tag_size, output) in SP_800_38C_EXAMPLES {
+            let nonce = AesCcmNonce::try_copy_from_slice(&hex::decode(nonce).unwrap()).unwrap();
+            let aad: Vec<u8> = (0..aad_length as u8).collect();
+            let plaintext: Vec<u8> = (0x20..0x20 + payload_length as u8).collect();
+            let output = hex::decode(output).unwrap();
+            let cipher = AesCcmCipher::with_tag_size(&key, &nonce, tag_size).unwrap();
+            assert_eq!(output, cipher.encrypt(&aad, &plaintext).unwrap());
+            assert_eq!(plaintext, cipher.decrypt(&aad, &output).unwrap());
+        }
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let key = AesKey::aes_128_key([0_u8; 16]);
+        let nonce = AesCcmNonce::aes_ccm_nonce([0_u8; 13]);
+        for tag_size in [0, 2, 5, 15, 17, 18] {
+            assert_eq!(
+                AesError::InvalidTagSize(tag_size),
+                AesCcmCipher::with_tag_size(&key, &nonce, tag_size)
+                    .err()
+                    .unwrap()
+            );
+        }
+        for nonce_size in [0, 6, 14, 16] {
+            assert!(AesCcmNonce::try_copy_from_slice(&vec![0_u8; nonce_size]).is_err());
+        }
+        // A 13-byte nonce leaves a 2-byte length field.
+        let cipher = AesCcmCipher::new(&key, &nonce);
+        assert_eq!(
+            AesError::MessageTooLong(0x10000),
+            cipher.encrypt(&[], &vec![0_u8; 0x10000]).err().unwrap()
+        );
+    }
+
+    #[quickcheck]
+    fn encrypt_and_decrypt_is_identity(
+        key: AesKey,
+        nonce: AesCcmNonce,
+        aad: Vec<u8>,
+        plaintext: Vec<u8>,
+        tag_size: u8,
+    ) -> bool {
+        let tag_size = 4 + 2 * (tag_size as usize % 7);
+        let cipher = AesCcmCipher::with_tag_size(&key, &nonce, tag_size).unwrap();
+        let ciphertext: Vec<u8> = cipher.encrypt(&aad, &plaintext).unwrap();
+        ciphertext.len() == plaintext.len() + tag_size
+            && plaintext == cipher.decrypt(&aad, &ciphertext).unwrap()
+    }
+
+    #[quickcheck]
+    fn tampering_is_detected(
+        key: AesKey,
+        nonce: AesCcmNonce,
+        aad: Vec<u8>,
+        plaintext: Vec<u8>,
+        index: usize,
+    ) -> bool {
+        let cipher = AesCcmCipher::new(&key, &nonce);
+        let mut ciphertext: Vec<u8> = cipher.encrypt(&aad, &plaintext).unwrap();
+        let index = index % ciphertext.len();
+        ciphertext[index] ^= 0x01;
+        let tampered = cipher.decrypt(&aad, &ciphertext);
+        ciphertext[index] ^= 0x01;
+        let mut aad = aad;
+        aad.push(0x00);
+        tampered == Err(AesError::AuthenticationFailed)
+            && cipher.decrypt(&aad, &ciphertext) == Err(AesError::AuthenticationFailed)
+    }
+}
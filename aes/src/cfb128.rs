@@ -0,0 +1,136 @@
+use crate::cbc::AesCbcIv;
+use crate::ecb::AesEcbBlockCipher;
+use crate::error::AesError;
+use crate::key::AesKey;
+
+// Full-block cipher feedback (CFB-128). Works on plaintexts of any length: a
+// short final segment only uses the leading bytes of its keystream block.
+pub struct AesCfb128Cipher<'k, 'iv> {
+    block_cipher: AesEcbBlockCipher,
+    key: std::marker::PhantomData<&'k AesKey>,
+    iv: &'iv AesCbcIv,
+}
+
+impl<'k, 'iv> AesCfb128Cipher<'k, 'iv> {
+    pub fn new(key: &'k AesKey, iv: &'iv AesCbcIv) -> AesCfb128Cipher<'k, 'iv> {
+        AesCfb128Cipher {
+            block_cipher: AesEcbBlockCipher::new(key),
+            key: std::marker::PhantomData,
+            iv,
+        }
+    }
+
+    pub fn decrypt<T: ?Sized + AsRef<[u8]>>(&self, ciphertext: &T) -> Result<Vec<u8>, AesError> {
+        let ciphertext = ciphertext.as_ref();
+        // Every keystream block is the encryption of a known block (the IV
+        // or a previous ciphertext block), so they are encrypted in one batch.
+        let blocks = ciphertext.len().div_ceil(16);
+        let mut keystream: Vec<u8> = Vec::with_capacity(blocks * 16);
+        for previous_block in std::iter::once(self.iv.as_slice())
+            .chain(ciphertext.chunks_exact(16))
+            .take(blocks)
+        {
+            keystream.extend_from_slice(previous_block);
+        }
+        self.block_cipher.encrypt_blocks_mut(&mut keystream);
+        Ok(ciphertext
+            .iter()
+            .zip(keystream.iter())
+            .map(|(c, k)| c ^ k)
+            .collect())
+    }
+
+    pub fn encrypt<T: ?Sized + AsRef<[u8]>>(&self, plaintext: &T) -> Result<Vec<u8>, AesError> {
+        let plaintext = plaintext.as_ref();
+        let mut ciphertext = plaintext.to_vec();
+        let mut register = [0u8; 16];
+        register.copy_from_slice(self.iv.as_slice());
+        for segment in ciphertext.chunks_mut(16) {
+            self.block_cipher.encrypt_blocks_mut(&mut register);
+            segment
+                .iter_mut()
+                .zip(register.iter())
+                .for_each(|(p, k)| *p ^= k);
+            register[..segment.len()].copy_from_slice(segment);
+        }
+        Ok(ciphertext)
+    }
+}
+
+pub fn decrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    ciphertext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesCfb128Cipher::new(&key, &iv);
+    cipher.decrypt(ciphertext)
+}
+
+pub fn encrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    plaintext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesCfb128Cipher::new(&key, &iv);
+    cipher.encrypt(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cbc::AesCbcIv;
+    use crate::cfb128::AesCfb128Cipher;
+    use crate::key::AesKey;
+
+    // NIST SP 800-38A, F.3.13 through F.3.18.
+    #[test]
+    fn nist_sp800_38a_vectors() {
+        let iv = "000102030405060708090a0b0c0d0e0f";
+        let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+        for (key, ciphertext) in [
+            (
+                "2b7e151628aed2a6abf7158809cf4f3c",
+                "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+                 26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6",
+            ),
+            (
+                "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+                "cdc80d6fddf18cab34c25909c99a417467ce7f7f81173621961a2b70171d3d7a\
+                 2e1e8a1dd59b88b1c8e60fed1efac4c9c05f9f9ca9834fa042ae8fba584b09ff",
+            ),
+            (
+                "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+                "dc7e84bfda79164b7ecd8486985d386039ffed143b28b1c832113c6331e5407b\
+                 df10132415e54b92a13ed0a8267ae2f975a385741ab9cef82031623d55b1e471",
+            ),
+        ] {
+            let plaintext = hex::decode(plaintext).unwrap();
+            let ciphertext = hex::decode(ciphertext).unwrap();
+            let challenge: Vec<u8> = crate::cfb128::encrypt(
+                &hex::decode(key).unwrap(),
+                &hex::decode(iv).unwrap(),
+                &plaintext,
+            )
+            .unwrap();
+            assert_eq!(ciphertext, challenge);
+            let challenge: Vec<u8> = crate::cfb128::decrypt(
+                &hex::decode(key).unwrap(),
+                &hex::decode(iv).unwrap(),
+                &ciphertext,
+            )
+            .unwrap();
+            assert_eq!(plaintext, challenge);
+        }
+    }
+
+    #[quickcheck]
+    fn encrypt_and_decrypt_is_identity(key: AesKey, iv: AesCbcIv, plaintext: Vec<u8>) -> bool {
+        let cipher = AesCfb128Cipher::new(&key, &iv);
+        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        plaintext.len() == ciphertext.len() && plaintext == cipher.decrypt(&ciphertext).unwrap()
+    }
+}
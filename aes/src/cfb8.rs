@@ -0,0 +1,129 @@
+use crate::cbc::AesCbcIv;
+use crate::ecb::AesEcbBlockCipher;
+use crate::error::AesError;
+use crate::key::AesKey;
+
+// 8-bit cipher feedback (CFB-8). Each byte costs a full block encryption of
+// the shift register, which is then advanced by one ciphertext byte.
+pub struct AesCfb8Cipher<'k, 'iv> {
+    block_cipher: AesEcbBlockCipher,
+    key: std::marker::PhantomData<&'k AesKey>,
+    iv: &'iv AesCbcIv,
+}
+
+impl<'k, 'iv> AesCfb8Cipher<'k, 'iv> {
+    pub fn new(key: &'k AesKey, iv: &'iv AesCbcIv) -> AesCfb8Cipher<'k, 'iv> {
+        AesCfb8Cipher {
+            block_cipher: AesEcbBlockCipher::new(key),
+            key: std::marker::PhantomData,
+            iv,
+        }
+    }
+
+    pub fn decrypt<T: ?Sized + AsRef<[u8]>>(&self, ciphertext: &T) -> Result<Vec<u8>, AesError> {
+        let ciphertext = ciphertext.as_ref();
+        let mut plaintext = ciphertext.to_vec();
+        let mut register = [0u8; 16];
+        register.copy_from_slice(self.iv.as_slice());
+        for byte in plaintext.iter_mut() {
+            let c = *byte;
+            *byte ^= self.keystream_byte(&register);
+            register.rotate_left(1);
+            register[15] = c;
+        }
+        Ok(plaintext)
+    }
+
+    pub fn encrypt<T: ?Sized + AsRef<[u8]>>(&self, plaintext: &T) -> Result<Vec<u8>, AesError> {
+        let plaintext = plaintext.as_ref();
+        let mut ciphertext = plaintext.to_vec();
+        let mut register = [0u8; 16];
+        register.copy_from_slice(self.iv.as_slice());
+        for byte in ciphertext.iter_mut() {
+            *byte ^= self.keystream_byte(&register);
+            register.rotate_left(1);
+            register[15] = *byte;
+        }
+        Ok(ciphertext)
+    }
+
+    fn keystream_byte(&self, register: &[u8; 16]) -> u8 {
+        let mut block = *register;
+        self.block_cipher.encrypt_blocks_mut(&mut block);
+        block[0]
+    }
+}
+
+pub fn decrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    ciphertext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesCfb8Cipher::new(&key, &iv);
+    cipher.decrypt(ciphertext)
+}
+
+pub fn encrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    plaintext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesCfb8Cipher::new(&key, &iv);
+    cipher.encrypt(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cbc::AesCbcIv;
+    use crate::cfb8::AesCfb8Cipher;
+    use crate::key::AesKey;
+
+    // NIST SP 800-38A, F.3.7 through F.3.12.
+    #[test]
+    fn nist_sp800_38a_vectors() {
+        let iv = "000102030405060708090a0b0c0d0e0f";
+        let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d";
+        for (key, ciphertext) in [
+            (
+                "2b7e151628aed2a6abf7158809cf4f3c",
+                "3b79424c9c0dd436bace9e0ed4586a4f32b9",
+            ),
+            (
+                "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+                "cda2521ef0a905ca44cd057cbf0d47a0678a",
+            ),
+            (
+                "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+                "dc1f1a8520a64db55fcc8ac554844e889700",
+            ),
+        ] {
+            let plaintext = hex::decode(plaintext).unwrap();
+            let ciphertext = hex::decode(ciphertext).unwrap();
+            let challenge: Vec<u8> = crate::cfb8::encrypt(
+                &hex::decode(key).unwrap(),
+                &hex::decode(iv).unwrap(),
+                &plaintext,
+            )
+            .unwrap();
+            assert_eq!(ciphertext, challenge);
+            let challenge: Vec<u8> = crate::cfb8::decrypt(
+                &hex::decode(key).unwrap(),
+                &hex::decode(iv).unwrap(),
+                &ciphertext,
+            )
+            .unwrap();
+            assert_eq!(plaintext, challenge);
+        }
+    }
+
+    #[quickcheck]
+    fn encrypt_and_decrypt_is_identity(key: AesKey, iv: AesCbcIv, plaintext: Vec<u8>) -> bool {
+        let cipher = AesCfb8Cipher::new(&key, &iv);
+        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        plaintext.len() == ciphertext.len() && plaintext == cipher.decrypt(&ciphertext).unwrap()
+    }
+}
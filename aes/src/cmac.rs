@@ -0,0 +1,168 @@
+// See: https://tools.ietf.org/html/rfc4493
+//
+// The CMAC core lives here so that AES-based modes can build on it; the `mac`
+// crate wraps it as a `FixedHashContext`.
+
+use zeroize::Zeroize;
+
+use crate::ecb::AesEcbBlockCipher;
+use crate::key::AesKey;
+
+// Doubling in GF(2^128) with the big-endian convention of SP 800-38B.
+pub fn dbl(block: [u8; 16]) -> [u8; 16] {
+    let value = u128::from_be_bytes(block);
+    let carry = (value >> 127) as u8;
+    ((value << 1) ^ (0x87 * carry as u128)).to_be_bytes()
+}
+
+// Returns the subkeys `(K1, K2)` derived from `L = AES-K(0^128)`.
+pub fn generate_subkeys(block_cipher: &AesEcbBlockCipher) -> ([u8; 16], [u8; 16]) {
+    let mut l = [0_u8; 16];
+    block_cipher.encrypt_blocks_mut(&mut l);
+    let k1 = dbl(l);
+    let k2 = dbl(k1);
+    (k1, k2)
+}
+
+#[derive(Clone)]
+pub struct AesCmac {
+    block_cipher: AesEcbBlockCipher,
+    k1: [u8; 16],
+    k2: [u8; 16],
+    state: [u8; 16],
+    // The final block is treated specially, so up to a full block is held
+    // back until more input shows that it was not the last one.
+    buffer: [u8; 16],
+    buffer_len: usize,
+}
+
+// The subkeys and chaining state are key material: they are wiped on drop and
+// left out of `Debug`.
+impl std::fmt::Debug for AesCmac {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AesCmac")
+            .field("block_cipher", &self.block_cipher)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for AesCmac {
+    fn drop(&mut self) {
+        self.k1.zeroize();
+        self.k2.zeroize();
+        self.state.zeroize();
+        self.buffer.zeroize();
+    }
+}
+
+impl AesCmac {
+    pub fn new(key: &AesKey) -> AesCmac {
+        let block_cipher = AesEcbBlockCipher::new(key);
+        let (k1, k2) = generate_subkeys(&block_cipher);
+        AesCmac {
+            block_cipher,
+            k1,
+            k2,
+            state: [0_u8; 16],
+            buffer: [0_u8; 16],
+            buffer_len: 0,
+        }
+    }
+
+    pub fn update<T: ?Sized + AsRef<[u8]>>(&mut self, input: &T) {
+        for &byte in input.as_ref() {
+            if self.buffer_len == 16 {
+                self.process_buffer();
+            }
+            self.buffer[self.buffer_len] = byte;
+            self.buffer_len += 1;
+        }
+    }
+
+    // The tag for the input so far. More input may follow.
+    pub fn tag(&self) -> [u8; 16] {
+        let mut last_block = self.buffer;
+        let subkey = if self.buffer_len == 16 {
+            &self.k1
+        } else {
+            last_block[self.buffer_len] = 0x80;
+            last_block[self.buffer_len + 1..].fill(0);
+            &self.k2
+        };
+        for ((b, k), s) in last_block
+            .iter_mut()
+            .zip(subkey.iter())
+            .zip(self.state.iter())
+        {
+            *b ^= k ^ s;
+        }
+        self.block_cipher.encrypt_blocks_mut(&mut last_block);
+        last_block
+    }
+
+    // Keeps the key and clears the message.
+    pub fn reset(&mut self) {
+        self.state = [0_u8; 16];
+        self.buffer = [0_u8; 16];
+        self.buffer_len = 0;
+    }
+
+    fn process_buffer(&mut self) {
+        self.state
+            .iter_mut()
+            .zip(self.buffer.iter())
+            .for_each(|(s, b)| *s ^= b);
+        self.block_cipher.encrypt_blocks_mut(&mut self.state);
+        self.buffer_len = 0;
+    }
+}
+
+// One-shot AES-CMAC of `input`.
+pub fn aes_cmac<T: ?Sized + AsRef<[u8]>>(key: &AesKey, input: &T) -> [u8; 16] {
+    let mut mac = AesCmac::new(key);
+    mac.update(input);
+    mac.tag()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmac::*;
+
+    #[test]
+    fn rfc_4493_subkeys() {
+        let key =
+            AesKey::try_copy_from_slice(&hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap())
+                .unwrap();
+        let (k1, k2) = generate_subkeys(&AesEcbBlockCipher::new(&key));
+        assert_eq!("fbeed618357133667c85e08f7236a8de", hex::encode(k1));
+        assert_eq!("f7ddac306ae266ccf90bc11ee46d513b", hex::encode(k2));
+    }
+
+    // RFC 4493 section 4, examples 1 and 2.
+    #[test]
+    fn rfc_4493_vectors() {
+        let key =
+            AesKey::try_copy_from_slice(&hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap())
+                .unwrap();
+        assert_eq!(
+            "bb1d6929e95937287fa37d129b756746",
+            hex::encode(aes_cmac(&key, b""))
+        );
+        assert_eq!(
+            "070a16b46b4d4144f79bdd9dd04a287c",
+            hex::encode(aes_cmac(
+                &key,
+                &hex::decode("6bc1bee22e409f96e93d7e117393172a").unwrap()
+            ))
+        );
+    }
+
+    #[quickcheck]
+    fn reset_forgets_the_message(key: AesKey, first: Vec<u8>, second: Vec<u8>) -> bool {
+        let mut mac = AesCmac::new(&key);
+        mac.update(&first);
+        mac.reset();
+        mac.update(&second);
+        mac.tag() == aes_cmac(&key, &second)
+    }
+}
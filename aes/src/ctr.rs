@@ -1,15 +1,21 @@
 use quickcheck::{single_shrinker, Arbitrary, Gen};
 use rand::prelude::*;
+use std::borrow::Cow;
 
+use crate::block_cipher::BlockCipher;
 use crate::ecb::AesEcbBlockCipher;
 use crate::error::AesError;
 use crate::key::AesKey;
+#[cfg(feature = "parallel")]
+use crate::parallel::chunk_len;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum AesCtrMode {
-    NIST_SP800_38A, // NIST SP 800-38A mode (128-bit BE nonce + 128-bit BE counter)
-    CRYPTOPALS,     // cryptopals mode (64-bit LE nonce + 64-bit LE counter)
+    NIST_SP800_38A,       // NIST SP 800-38A mode (128-bit BE nonce + 128-bit BE counter)
+    NIST_SP800_38D,       // NIST SP 800-38D mode (96-bit nonce + 32-bit BE counter)
+    CRYPTOPALS,           // cryptopals mode (64-bit LE nonce + 64-bit LE counter)
+    Custom(AesCtrLayout), // any other `nonce || counter` layout
 }
 
 impl AesCtrMode {
@@ -72,6 +78,224 @@ impl AesCtrMode {
     }
 }
 
+impl AesCtrMode {
+    // The counter layout for a block of `block_size` bytes. The 38D counter is
+    // always the last four bytes and the cryptopals counter is always the
+    // second half of the block; all three built-in modes wrap on overflow.
+    pub fn layout(self, block_size: usize) -> AesCtrLayout {
+        let (nonce_size, endianness) = match self {
+            AesCtrMode::NIST_SP800_38A => (0, AesCtrEndianness::Big),
+            AesCtrMode::NIST_SP800_38D => (block_size.saturating_sub(4), AesCtrEndianness::Big),
+            AesCtrMode::CRYPTOPALS => (block_size / 2, AesCtrEndianness::Little),
+            AesCtrMode::Custom(layout) => return layout,
+        };
+        AesCtrLayout {
+            nonce_size,
+            counter_size: block_size - nonce_size,
+            endianness,
+            initial_value: 0,
+            overflow: AesCtrOverflow::Wrap,
+        }
+    }
+
+    // Adds `blocks` to the counter portion of a counter block of any size,
+    // always wrapping within that portion. `CtrKeystream` honors
+    // `AesCtrOverflow::Error` by tracking where its counter runs out instead.
+    pub(crate) fn increment_counter(self, block: &mut [u8], blocks: u128) {
+        self.layout(block.len()).wrapping_increment(block, blocks)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AesCtrEndianness {
+    Big,
+    Little,
+}
+
+// What happens when incrementing would carry out of the counter field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AesCtrOverflow {
+    Wrap,  // the counter wraps to zero and the nonce is left untouched
+    Error, // `AesError::CounterOverflow` and the counter is left untouched
+}
+
+// A counter block laid out as `nonce || counter || fixed`, where the counter
+// is `counter_size` bytes and any bytes after it never change.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AesCtrLayout {
+    nonce_size: usize,
+    counter_size: usize,
+    endianness: AesCtrEndianness,
+    initial_value: u128,
+    overflow: AesCtrOverflow,
+}
+
+impl AesCtrLayout {
+    // RFC 3686: 32-bit nonce and 64-bit IV, then a 32-bit BE counter from 1.
+    pub const RFC_3686: AesCtrLayout = AesCtrLayout {
+        nonce_size: 12,
+        counter_size: 4,
+        endianness: AesCtrEndianness::Big,
+        initial_value: 1,
+        overflow: AesCtrOverflow::Error,
+    };
+
+    // Starts counting at zero and wraps on overflow.
+    pub fn new(
+        nonce_size: usize,
+        counter_size: usize,
+        endianness: AesCtrEndianness,
+    ) -> Result<AesCtrLayout, AesError> {
+        if counter_size == 0 || nonce_size + counter_size > 16 {
+            return Err(AesError::InvalidCounterLayout {
+                nonce_size,
+                counter_size,
+            });
+        }
+        Ok(AesCtrLayout {
+            nonce_size,
+            counter_size,
+            endianness,
+            initial_value: 0,
+            overflow: AesCtrOverflow::Wrap,
+        })
+    }
+
+    pub fn with_initial_value(self, initial_value: u128) -> Result<AesCtrLayout, AesError> {
+        if initial_value > self.max_value() {
+            return Err(AesError::CounterOverflow);
+        }
+        Ok(AesCtrLayout {
+            initial_value,
+            ..self
+        })
+    }
+
+    pub fn with_overflow(self, overflow: AesCtrOverflow) -> AesCtrLayout {
+        AesCtrLayout { overflow, ..self }
+    }
+
+    pub fn nonce_size(&self) -> usize {
+        self.nonce_size
+    }
+
+    pub fn counter_size(&self) -> usize {
+        self.counter_size
+    }
+
+    pub fn endianness(&self) -> AesCtrEndianness {
+        self.endianness
+    }
+
+    pub fn initial_value(&self) -> u128 {
+        self.initial_value
+    }
+
+    pub fn overflow(&self) -> AesCtrOverflow {
+        self.overflow
+    }
+
+    pub fn max_value(&self) -> u128 {
+        u128::MAX >> (128 - 8 * self.counter_size)
+    }
+
+    // Builds the first counter block, `nonce || initial value || 0*`.
+    pub fn initial_counter_block<N: ?Sized + AsRef<[u8]>>(
+        &self,
+        nonce: &N,
+    ) -> Result<AesCtrIv, AesError> {
+        let nonce = nonce.as_ref();
+        if nonce.len() != self.nonce_size {
+            return Err(AesError::InvalidInitializationVectorSize {
+                was: nonce.len(),
+                explanation: "must match the nonce size of the counter layout",
+            });
+        }
+        let mut block = [0_u8; 16];
+        block[..self.nonce_size].copy_from_slice(nonce);
+        self.wrapping_increment(&mut block, self.initial_value);
+        Ok(AesCtrIv::aes_ctr_iv(block))
+    }
+
+    pub(crate) fn check_block_size(&self, block_size: usize) -> Result<(), AesError> {
+        if self.nonce_size + self.counter_size > block_size {
+            return Err(AesError::InvalidCounterLayout {
+                nonce_size: self.nonce_size,
+                counter_size: self.counter_size,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn counter_value(&self, block: &[u8]) -> u128 {
+        let counter = &block[self.nonce_size..self.nonce_size + self.counter_size];
+        match self.endianness {
+            AesCtrEndianness::Big => counter
+                .iter()
+                .fold(0_u128, |value, &c| (value << 8) | c as u128),
+            AesCtrEndianness::Little => counter
+                .iter()
+                .rev()
+                .fold(0_u128, |value, &c| (value << 8) | c as u128),
+        }
+    }
+
+    // Adds `blocks` to the counter, honoring `overflow`.
+    pub fn increment(&self, block: &mut [u8], blocks: u128) -> Result<(), AesError> {
+        self.check_block_size(block.len())?;
+        if self.overflow == AesCtrOverflow::Error
+            && blocks > self.max_value() - self.counter_value(block)
+        {
+            return Err(AesError::CounterOverflow);
+        }
+        self.wrapping_increment(block, blocks);
+        Ok(())
+    }
+
+    fn wrapping_increment(&self, block: &mut [u8], blocks: u128) {
+        let counter = &mut block[self.nonce_size..self.nonce_size + self.counter_size];
+        match self.endianness {
+            AesCtrEndianness::Big => add_be(counter, blocks),
+            AesCtrEndianness::Little => add_le(counter, blocks),
+        }
+    }
+}
+
+impl Arbitrary for AesCtrLayout {
+    fn arbitrary(g: &mut Gen) -> AesCtrLayout {
+        let counter_size = 1 + usize::arbitrary(g) % 16;
+        let nonce_size = usize::arbitrary(g) % (17 - counter_size);
+        let endianness = *g
+            .choose(&[AesCtrEndianness::Big, AesCtrEndianness::Little])
+            .unwrap();
+        let layout = AesCtrLayout::new(nonce_size, counter_size, endianness).unwrap();
+        let initial_value = u128::arbitrary(g) & layout.max_value();
+        layout.with_initial_value(initial_value).unwrap()
+    }
+}
+
+fn add_be(counter: &mut [u8], mut blocks: u128) {
+    for c in counter.iter_mut().rev() {
+        if blocks == 0 {
+            break;
+        }
+        let sum = *c as u128 + (blocks & 0xff);
+        *c = sum as u8;
+        blocks = (blocks >> 8) + (sum >> 8);
+    }
+}
+
+fn add_le(counter: &mut [u8], mut blocks: u128) {
+    for c in counter.iter_mut() {
+        if blocks == 0 {
+            break;
+        }
+        let sum = *c as u128 + (blocks & 0xff);
+        *c = sum as u8;
+        blocks = (blocks >> 8) + (sum >> 8);
+    }
+}
+
 impl Distribution<AesCtrMode> for rand::distributions::Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AesCtrMode {
         match rng.gen_range(0..2) {
@@ -120,25 +344,20 @@ impl AesCtrIv {
         self.0.to_vec()
     }
 
-    pub fn increment(&mut self, mode: AesCtrMode) {
-        match mode {
-            AesCtrMode::NIST_SP800_38A => {
-                for c in self.0[..].iter_mut().rev() {
-                    *c = c.wrapping_add(1);
-                    if *c != 0 {
-                        break;
-                    }
-                }
-            }
-            AesCtrMode::CRYPTOPALS => {
-                for c in self.0[8..].iter_mut() {
-                    *c = c.wrapping_add(1);
-                    if *c != 0 {
-                        break;
-                    }
-                }
-            }
-        }
+    // Fails only for a layout with `AesCtrOverflow::Error`.
+    pub fn increment(&mut self, mode: AesCtrMode) -> Result<(), AesError> {
+        self.increment_by(1, mode)
+    }
+
+    // Equivalent to calling `increment` `blocks` times, but in O(1).
+    pub fn increment_by(&mut self, blocks: u128, mode: AesCtrMode) -> Result<(), AesError> {
+        mode.layout(16).increment(&mut self.0, blocks)
+    }
+}
+
+impl AsRef<[u8]> for AesCtrIv {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
     }
 }
 
@@ -175,63 +394,241 @@ impl Arbitrary for AesCtrIv {
     }
 }
 
+// Number of counter blocks encrypted per refill of the keystream buffer.
+const KEYSTREAM_BLOCKS: usize = 8;
+
 #[derive(Clone, Debug)]
-pub struct AesCtrKeystream<'k> {
-    key: &'k AesKey,
-    iv: AesCtrIv,
+pub struct CtrKeystream<'k, C: BlockCipher> {
+    block_cipher: Cow<'k, C>,
+    origin: Vec<u8>,
+    counter: Vec<u8>,
     mode: AesCtrMode,
-    bytes: [u8; 16],
+    bytes: Vec<u8>,
     offset: usize,
+    position: u64,
+    // The position at which a counter that must not wrap runs out, if that
+    // is within reach of a `u64`.
+    end: Option<u64>,
 }
 
-impl<'k> AesCtrKeystream<'k> {
-    pub fn new(key: &'k AesKey, iv: &AesCtrIv, mode: AesCtrMode) -> AesCtrKeystream<'k> {
-        AesCtrKeystream {
-            key,
-            iv: iv.clone(),
+pub type AesCtrKeystream<'k> = CtrKeystream<'k, AesEcbBlockCipher>;
+
+impl<'k, C: BlockCipher> CtrKeystream<'k, C> {
+    // Fails if `iv` is not exactly one block long, or if the counter layout
+    // does not fit in one block.
+    // The keystream owns its expanded key, so `'k` is not tied to `key`.
+    pub fn new<IV: ?Sized + AsRef<[u8]>>(
+        key: &C::Key,
+        iv: &IV,
+        mode: AesCtrMode,
+    ) -> Result<CtrKeystream<'k, C>, AesError> {
+        Self::from_cow(Cow::Owned(C::from_key(key)), iv.as_ref(), mode)
+    }
+
+    pub fn from_block_cipher<IV: ?Sized + AsRef<[u8]>>(
+        block_cipher: &'k C,
+        iv: &IV,
+        mode: AesCtrMode,
+    ) -> Result<CtrKeystream<'k, C>, AesError> {
+        Self::from_cow(Cow::Borrowed(block_cipher), iv.as_ref(), mode)
+    }
+
+    fn from_cow(
+        block_cipher: Cow<'k, C>,
+        iv: &[u8],
+        mode: AesCtrMode,
+    ) -> Result<CtrKeystream<'k, C>, AesError> {
+        if iv.len() != C::block_size() {
+            return Err(AesError::InvalidInitializationVectorSize {
+                was: iv.len(),
+                explanation: "must be one block",
+            });
+        }
+        let layout = mode.layout(C::block_size());
+        layout.check_block_size(C::block_size())?;
+        let end = match layout.overflow() {
+            AesCtrOverflow::Wrap => None,
+            AesCtrOverflow::Error => (layout.max_value() - layout.counter_value(iv))
+                .checked_add(1)
+                .and_then(|blocks| blocks.checked_mul(C::block_size() as u128))
+                .and_then(|end| u64::try_from(end).ok()),
+        };
+        let size = C::block_size() * KEYSTREAM_BLOCKS;
+        Ok(CtrKeystream {
+            block_cipher,
+            origin: iv.to_vec(),
+            counter: iv.to_vec(),
             mode,
-            bytes: [0_u8; 16],
-            offset: 16_usize,
+            bytes: vec![0_u8; size],
+            offset: size,
+            position: 0_u64,
+            end,
+        })
+    }
+
+    pub fn at_offset<IV: ?Sized + AsRef<[u8]>>(
+        key: &C::Key,
+        iv: &IV,
+        mode: AesCtrMode,
+        position: u64,
+    ) -> Result<CtrKeystream<'k, C>, AesError> {
+        let mut keystream = Self::new(key, iv, mode)?;
+        keystream.seek(position)?;
+        Ok(keystream)
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    // Moves to an absolute byte position of the keystream without generating
+    // any of the skipped blocks. Fails with `AesError::CounterOverflow` past
+    // the end of a counter that must not wrap.
+    pub fn seek(&mut self, position: u64) -> Result<(), AesError> {
+        if self.end.is_some_and(|end| position > end) {
+            return Err(AesError::CounterOverflow);
+        }
+        let block_size = C::block_size() as u64;
+        self.counter.copy_from_slice(&self.origin);
+        self.mode
+            .increment_counter(&mut self.counter, (position / block_size) as u128);
+        self.offset = self.bytes.len();
+        self.position = position;
+        let offset = (position % block_size) as usize;
+        if offset != 0 {
+            self.refill();
+            self.offset = offset;
+        }
+        Ok(())
+    }
+
+    // XORs the next `input.len()` keystream bytes into `input`. Fails with
+    // `AesError::CounterOverflow`, leaving `input` untouched, if that would
+    // run past the end of a counter that must not wrap.
+    pub fn apply(&mut self, input: &mut [u8]) -> Result<(), AesError> {
+        if self
+            .end
+            .is_some_and(|end| self.position.saturating_add(input.len() as u64) > end)
+        {
+            return Err(AesError::CounterOverflow);
+        }
+        let mut done = 0_usize;
+        while done < input.len() {
+            if self.offset >= self.bytes.len() {
+                self.refill();
+            }
+            let n = std::cmp::min(input.len() - done, self.bytes.len() - self.offset);
+            xor::kernel::xor_in_place(
+                &mut input[done..(done + n)],
+                &self.bytes[self.offset..(self.offset + n)],
+            );
+            self.offset += n;
+            self.position += n as u64;
+            done += n;
+        }
+        Ok(())
+    }
+
+    // Blocks past the end of a counter that must not wrap are generated with
+    // a wrapped counter, but `apply` and `next` never hand them out.
+    fn refill(&mut self) {
+        for block in self.bytes.chunks_mut(C::block_size()) {
+            block.copy_from_slice(&self.counter);
+            self.mode.increment_counter(&mut self.counter, 1);
         }
+        self.block_cipher.encrypt_blocks_mut(&mut self.bytes);
+        self.offset = 0_usize;
     }
 }
 
-impl<'k> Iterator for AesCtrKeystream<'k> {
+// Ends where a counter that must not wrap runs out.
+impl<'k, C: BlockCipher> Iterator for CtrKeystream<'k, C> {
     type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.end.is_some_and(|end| self.position >= end) {
+            return None;
+        }
         if self.offset >= self.bytes.len() {
-            let block_cipher = AesEcbBlockCipher::new(&self.key);
-            let encrypted_block = block_cipher.encrypt_block(&self.iv.0[..]).unwrap();
-            self.bytes.copy_from_slice(&encrypted_block[..]);
-            self.offset = 0_usize;
-            self.iv.increment(self.mode);
+            self.refill();
         }
         let byte = self.bytes[self.offset];
         self.offset += 1;
+        self.position += 1;
         Some(byte)
     }
 }
 
-pub struct AesCtrCipher<'k, 'iv> {
-    key: &'k AesKey,
-    iv: &'iv AesCtrIv,
+impl<'k, C: BlockCipher> xor::keystream::Keystream for CtrKeystream<'k, C> {
+    fn apply(&mut self, input: &mut [u8]) -> xor::Result<()> {
+        Ok(CtrKeystream::apply(self, input)?)
+    }
+
+    fn seek(&mut self, position: u64) -> xor::Result<()> {
+        Ok(CtrKeystream::seek(self, position)?)
+    }
+}
+
+// The IV must be exactly one block long and the counter layout must fit in
+// one block, which is checked once when the cipher is built. The AES
+// constructor takes a typed IV, which always satisfies both.
+pub struct CtrCipher<'k, 'iv, C: BlockCipher> {
+    block_cipher: C,
+    key: std::marker::PhantomData<&'k C::Key>,
+    iv: &'iv [u8],
     mode: AesCtrMode,
 }
 
+pub type AesCtrCipher<'k, 'iv> = CtrCipher<'k, 'iv, AesEcbBlockCipher>;
+
 impl<'k, 'iv> AesCtrCipher<'k, 'iv> {
     pub fn new(key: &'k AesKey, iv: &'iv AesCtrIv, mode: AesCtrMode) -> AesCtrCipher<'k, 'iv> {
-        AesCtrCipher { key, iv, mode }
+        CtrCipher {
+            block_cipher: AesEcbBlockCipher::new(key),
+            key: std::marker::PhantomData,
+            iv: iv.as_slice(),
+            mode,
+        }
     }
+}
 
-    fn crypt<T: ?Sized + AsRef<[u8]>>(&self, input: &T) -> Result<Vec<u8>, AesError> {
-        let input = input.as_ref();
-        if input.is_empty() {
-            Ok(vec![])
-        } else {
-            let keystream = AesCtrKeystream::new(&self.key, &self.iv, self.mode);
-            Ok(input.iter().zip(keystream).map(|(a, b)| a ^ b).collect())
+impl<'k, 'iv, C: BlockCipher> CtrCipher<'k, 'iv, C> {
+    pub fn try_new<IV: ?Sized + AsRef<[u8]>>(
+        key: &'k C::Key,
+        iv: &'iv IV,
+        mode: AesCtrMode,
+    ) -> Result<CtrCipher<'k, 'iv, C>, AesError> {
+        let iv = iv.as_ref();
+        if iv.len() != C::block_size() {
+            return Err(AesError::InvalidInitializationVectorSize {
+                was: iv.len(),
+                explanation: "must be one block",
+            });
         }
+        mode.layout(C::block_size())
+            .check_block_size(C::block_size())?;
+        Ok(CtrCipher {
+            block_cipher: C::from_key(key),
+            key: std::marker::PhantomData,
+            iv,
+            mode,
+        })
+    }
+
+    // Fails if the IV is not exactly one block long, which `try_new` and the
+    // typed AES IV already rule out.
+    pub fn keystream(&self) -> Result<CtrKeystream<'_, C>, AesError> {
+        CtrKeystream::from_block_cipher(&self.block_cipher, self.iv, self.mode)
+    }
+
+    fn crypt<T: ?Sized + AsRef<[u8]>>(&self, input: &T) -> Result<Vec<u8>, AesError> {
+        let mut output = input.as_ref().to_vec();
+        self.crypt_in_place(&mut output)?;
+        Ok(output)
+    }
+
+    fn crypt_in_place(&self, buffer: &mut [u8]) -> Result<(), AesError> {
+        self.keystream()?.apply(buffer)
     }
 
     fn crypt_at_offset<T: ?Sized + AsRef<[u8]>>(
@@ -239,17 +636,11 @@ impl<'k, 'iv> AesCtrCipher<'k, 'iv> {
         input: &T,
         offset: usize,
     ) -> Result<Vec<u8>, AesError> {
-        let input = input.as_ref();
-        if input.is_empty() {
-            Ok(vec![])
-        } else {
-            let keystream = AesCtrKeystream::new(&self.key, &self.iv, self.mode);
-            Ok(input
-                .iter()
-                .zip(keystream.skip(offset))
-                .map(|(a, b)| a ^ b)
-                .collect())
-        }
+        let mut output = input.as_ref().to_vec();
+        let mut keystream = self.keystream()?;
+        keystream.seek(offset as u64)?;
+        keystream.apply(&mut output)?;
+        Ok(output)
     }
 
     pub fn decrypt<T: ?Sized + AsRef<[u8]>>(&self, ciphertext: &T) -> Result<Vec<u8>, AesError> {
@@ -260,6 +651,14 @@ impl<'k, 'iv> AesCtrCipher<'k, 'iv> {
         self.crypt(plaintext)
     }
 
+    pub fn decrypt_in_place(&self, buffer: &mut [u8]) -> Result<(), AesError> {
+        self.crypt_in_place(buffer)
+    }
+
+    pub fn encrypt_in_place(&self, buffer: &mut [u8]) -> Result<(), AesError> {
+        self.crypt_in_place(buffer)
+    }
+
     pub fn decrypt_at_offset<T: ?Sized + AsRef<[u8]>>(
         &self,
         ciphertext: &T,
@@ -277,6 +676,63 @@ impl<'k, 'iv> AesCtrCipher<'k, 'iv> {
     }
 }
 
+#[cfg(feature = "parallel")]
+impl<'k, 'iv, C: BlockCipher + Sync> CtrCipher<'k, 'iv, C> {
+    // Each chunk seeks its own copy of the keystream to the chunk's offset,
+    // so no counter state is shared between threads. Seeking to the end first
+    // makes a counter overflow fail before any chunk is touched.
+    fn par_crypt_in_place(&self, buffer: &mut [u8], chunk_size: usize) -> Result<(), AesError> {
+        use rayon::prelude::*;
+        let mut keystream = self.keystream()?;
+        keystream.seek(buffer.len() as u64)?;
+        let chunk_len = chunk_len(chunk_size, C::block_size());
+        buffer
+            .par_chunks_mut(chunk_len)
+            .enumerate()
+            .try_for_each(|(index, chunk)| {
+                let mut keystream = keystream.clone();
+                keystream.seek((index * chunk_len) as u64)?;
+                keystream.apply(chunk)
+            })
+    }
+
+    pub fn par_decrypt<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        ciphertext: &T,
+        chunk_size: usize,
+    ) -> Result<Vec<u8>, AesError> {
+        let mut plaintext = ciphertext.as_ref().to_vec();
+        self.par_crypt_in_place(&mut plaintext, chunk_size)?;
+        Ok(plaintext)
+    }
+
+    pub fn par_encrypt<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        plaintext: &T,
+        chunk_size: usize,
+    ) -> Result<Vec<u8>, AesError> {
+        let mut ciphertext = plaintext.as_ref().to_vec();
+        self.par_crypt_in_place(&mut ciphertext, chunk_size)?;
+        Ok(ciphertext)
+    }
+
+    pub fn par_decrypt_in_place(
+        &self,
+        buffer: &mut [u8],
+        chunk_size: usize,
+    ) -> Result<(), AesError> {
+        self.par_crypt_in_place(buffer, chunk_size)
+    }
+
+    pub fn par_encrypt_in_place(
+        &self,
+        buffer: &mut [u8],
+        chunk_size: usize,
+    ) -> Result<(), AesError> {
+        self.par_crypt_in_place(buffer, chunk_size)
+    }
+}
+
 pub fn decrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
     key: &K,
     iv: &IV,
@@ -293,6 +749,28 @@ pub fn encrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + As
     AesCtrMode::CRYPTOPALS.encrypt(key, iv, plaintext)
 }
 
+pub fn decrypt_in_place<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    buffer: &mut [u8],
+) -> Result<(), AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCtrIv::try_copy_from_slice(iv)?;
+    let cipher = AesCtrCipher::new(&key, &iv, AesCtrMode::CRYPTOPALS);
+    cipher.decrypt_in_place(buffer)
+}
+
+pub fn encrypt_in_place<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    buffer: &mut [u8],
+) -> Result<(), AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCtrIv::try_copy_from_slice(iv)?;
+    let cipher = AesCtrCipher::new(&key, &iv, AesCtrMode::CRYPTOPALS);
+    cipher.encrypt_in_place(buffer)
+}
+
 pub fn decrypt_cryptopals<
     K: ?Sized + AsRef<[u8]>,
     IV: ?Sized + AsRef<[u8]>,
@@ -421,9 +899,276 @@ pub fn encrypt_at_offset_nist_sp800_38a<
 
 #[cfg(test)]
 mod tests {
-    use crate::ctr::{AesCtrCipher, AesCtrIv, AesCtrMode};
+    use crate::ctr::{
+        AesCtrCipher, AesCtrEndianness, AesCtrIv, AesCtrKeystream, AesCtrLayout, AesCtrMode,
+        AesCtrOverflow,
+    };
+    use crate::error::AesError;
     use crate::key::AesKey;
 
+    #[quickcheck]
+    fn increment_by_matches_repeated_increment(
+        iv: AesCtrIv,
+        mode: AesCtrMode,
+        blocks: u16,
+    ) -> bool {
+        let mut expected = iv.clone();
+        for _ in 0..blocks {
+            expected.increment(mode).unwrap();
+        }
+        let mut challenge = iv;
+        challenge.increment_by(blocks as u128, mode).unwrap();
+        expected.as_slice() == challenge.as_slice()
+    }
+
+    #[quickcheck]
+    fn built_in_modes_match_their_layouts(
+        key: AesKey,
+        iv: AesCtrIv,
+        mode: AesCtrMode,
+        plaintext: Vec<u8>,
+    ) -> bool {
+        let custom = AesCtrMode::Custom(mode.layout(16));
+        let expected: Vec<u8> = AesCtrCipher::new(&key, &iv, mode)
+            .encrypt(&plaintext)
+            .unwrap();
+        expected
+            == AesCtrCipher::new(&key, &iv, custom)
+                .encrypt(&plaintext)
+                .unwrap()
+    }
+
+    #[quickcheck]
+    fn increment_by_matches_repeated_increment_for_any_layout(
+        iv: AesCtrIv,
+        layout: AesCtrLayout,
+        blocks: u8,
+    ) -> bool {
+        let mode = AesCtrMode::Custom(layout);
+        let mut expected = iv.clone();
+        for _ in 0..blocks {
+            expected.increment(mode).unwrap();
+        }
+        let mut challenge = iv.clone();
+        challenge.increment_by(blocks as u128, mode).unwrap();
+        // Only the counter field may change.
+        let fixed = layout.nonce_size()..layout.nonce_size() + layout.counter_size();
+        expected.as_slice() == challenge.as_slice()
+            && (0..16)
+                .filter(|i| !fixed.contains(i))
+                .all(|i| iv.as_slice()[i] == challenge.as_slice()[i])
+            && layout.counter_value(challenge.as_slice())
+                == layout
+                    .counter_value(iv.as_slice())
+                    .wrapping_add(blocks as u128)
+                    & layout.max_value()
+    }
+
+    // RFC 3686, section 6, test vector #1.
+    #[test]
+    fn rfc_3686_vector() {
+        let key =
+            AesKey::try_copy_from_slice(&hex::decode("ae6852f8121067cc4bf7a5765577f39e").unwrap())
+                .unwrap();
+        let nonce = hex::decode("00000030").unwrap();
+        let iv = hex::decode("0000000000000000").unwrap();
+        let layout = AesCtrLayout::RFC_3686;
+        let counter = layout.initial_counter_block(&[nonce, iv].concat()).unwrap();
+        assert_eq!(
+            "00000030000000000000000000000001",
+            hex::encode(counter.as_slice())
+        );
+        let cipher = AesCtrCipher::new(&key, &counter, AesCtrMode::Custom(layout));
+        assert_eq!(
+            "e4095d4fb7a7b3792d6175a3261311b8",
+            hex::encode(cipher.encrypt(b"Single block msg").unwrap())
+        );
+    }
+
+    #[test]
+    fn big_endian_64_bit_counter() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let layout = AesCtrLayout::new(8, 8, AesCtrEndianness::Big)
+            .unwrap()
+            .with_initial_value(u64::MAX as u128)
+            .unwrap();
+        let counter = layout.initial_counter_block(&[0xab; 8]).unwrap();
+        let cipher = AesCtrCipher::new(&key, &counter, AesCtrMode::Custom(layout));
+        let keystream: Vec<u8> = cipher.encrypt(&[0_u8; 32]).unwrap();
+        let mut wrapped = [0xab_u8; 16];
+        wrapped[8..].fill(0);
+        let expected: Vec<u8> =
+            crate::ecb::encrypt(key.as_slice(), &[counter.as_slice(), &wrapped[..]].concat())
+                .unwrap();
+        assert_eq!(expected, keystream);
+    }
+
+    #[test]
+    fn counter_overflow_is_an_error_when_requested() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let layout = AesCtrLayout::new(15, 1, AesCtrEndianness::Big)
+            .unwrap()
+            .with_initial_value(0xfe)
+            .unwrap()
+            .with_overflow(AesCtrOverflow::Error);
+        let mode = AesCtrMode::Custom(layout);
+        let counter = layout.initial_counter_block(&[0x11; 15]).unwrap();
+        let cipher = AesCtrCipher::new(&key, &counter, mode);
+        assert!(cipher.encrypt(&[0_u8; 32]).is_ok());
+        assert_eq!(Err(AesError::CounterOverflow), cipher.encrypt(&[0_u8; 33]));
+        assert_eq!(
+            Err(AesError::CounterOverflow),
+            cipher.encrypt_at_offset(&[0_u8; 1], 32)
+        );
+        let mut iv = counter.clone();
+        iv.increment(mode).unwrap();
+        assert_eq!(Err(AesError::CounterOverflow), iv.increment(mode));
+        assert_eq!(0xff, iv.as_slice()[15]);
+        // The same layout with wrapping leaves the nonce alone.
+        let mode = AesCtrMode::Custom(layout.with_overflow(AesCtrOverflow::Wrap));
+        iv.increment(mode).unwrap();
+        assert_eq!([&[0x11; 15][..], &[0x00]].concat(), iv.as_slice());
+    }
+
+    #[test]
+    fn keystream_stops_at_the_end_of_a_counter_that_must_not_wrap() {
+        use xor::keystream::Keystream;
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let layout = AesCtrLayout::new(15, 1, AesCtrEndianness::Big)
+            .unwrap()
+            .with_initial_value(0xfe)
+            .unwrap()
+            .with_overflow(AesCtrOverflow::Error);
+        let mode = AesCtrMode::Custom(layout);
+        let counter = layout.initial_counter_block(&[0x11; 15]).unwrap();
+        assert_eq!(
+            32,
+            AesCtrKeystream::new(&key, &counter, mode).unwrap().count()
+        );
+        let mut keystream = AesCtrKeystream::new(&key, &counter, mode).unwrap();
+        let mut buffer = [0_u8; 31];
+        keystream.apply(&mut buffer).unwrap();
+        let mut tail = [0_u8; 2];
+        assert_eq!(Err(AesError::CounterOverflow), keystream.apply(&mut tail));
+        assert_eq!([0_u8; 2], tail);
+        assert_eq!(31, keystream.position());
+        keystream.apply(&mut tail[..1]).unwrap();
+        assert_eq!(None, keystream.next());
+        assert_eq!(Err(AesError::CounterOverflow), keystream.seek(33));
+        assert!(keystream.seek(32).is_ok());
+        assert!(AesCtrKeystream::at_offset(&key, &counter, mode, 33).is_err());
+        let mut keystream: Box<dyn Keystream> =
+            Box::new(AesCtrKeystream::at_offset(&key, &counter, mode, 30).unwrap());
+        assert!(keystream.fill(&mut [0_u8; 3]).is_err());
+        assert!(keystream.seek(40).is_err());
+        assert!(keystream.fill(&mut [0_u8; 2]).is_ok());
+    }
+
+    #[test]
+    fn invalid_layouts_are_rejected() {
+        assert_eq!(
+            Err(AesError::InvalidCounterLayout {
+                nonce_size: 12,
+                counter_size: 8
+            }),
+            AesCtrLayout::new(12, 8, AesCtrEndianness::Big)
+        );
+        assert!(AesCtrLayout::new(16, 0, AesCtrEndianness::Big).is_err());
+        assert_eq!(
+            Err(AesError::CounterOverflow),
+            AesCtrLayout::new(14, 2, AesCtrEndianness::Little)
+                .unwrap()
+                .with_initial_value(0x10000)
+        );
+    }
+
+    #[quickcheck]
+    fn in_place_matches_allocating(
+        key: AesKey,
+        iv: AesCtrIv,
+        mode: AesCtrMode,
+        plaintext: Vec<u8>,
+    ) -> bool {
+        let cipher = AesCtrCipher::new(&key, &iv, mode);
+        let expected: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        let mut buffer = plaintext.clone();
+        cipher.encrypt_in_place(&mut buffer).unwrap();
+        let encrypted = expected == buffer;
+        cipher.decrypt_in_place(&mut buffer).unwrap();
+        encrypted && plaintext == buffer
+    }
+
+    #[cfg(feature = "parallel")]
+    #[quickcheck]
+    fn parallel_matches_sequential(
+        key: AesKey,
+        iv: AesCtrIv,
+        mode: AesCtrMode,
+        plaintext: Vec<u8>,
+        chunk_size: u8,
+    ) -> bool {
+        let cipher = AesCtrCipher::new(&key, &iv, mode);
+        let expected: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        let ciphertext: Vec<u8> = cipher.par_encrypt(&plaintext, chunk_size as usize).unwrap();
+        expected == ciphertext
+            && plaintext
+                == cipher
+                    .par_decrypt(&ciphertext, chunk_size as usize)
+                    .unwrap()
+    }
+
+    #[quickcheck]
+    fn keystream_seek_matches_skip(
+        key: AesKey,
+        iv: AesCtrIv,
+        mode: AesCtrMode,
+        position: u16,
+        length: u8,
+    ) -> bool {
+        let expected: Vec<u8> = AesCtrKeystream::new(&key, &iv, mode)
+            .unwrap()
+            .skip(position as usize)
+            .take(length as usize)
+            .collect();
+        let challenge: Vec<u8> = AesCtrKeystream::at_offset(&key, &iv, mode, position as u64)
+            .unwrap()
+            .take(length as usize)
+            .collect();
+        expected == challenge
+    }
+
+    #[quickcheck]
+    fn keystream_trait_matches_skip(
+        key: AesKey,
+        iv: AesCtrIv,
+        mode: AesCtrMode,
+        position: u16,
+        length: u8,
+    ) -> bool {
+        use xor::keystream::Keystream;
+        let expected: Vec<u8> = AesCtrKeystream::new(&key, &iv, mode)
+            .unwrap()
+            .skip(position as usize)
+            .take(length as usize)
+            .collect();
+        let mut keystream: Box<dyn Keystream> =
+            Box::new(AesCtrKeystream::new(&key, &iv, mode).unwrap());
+        let mut challenge = vec![0xff_u8; length as usize];
+        keystream.seek(position as u64).unwrap();
+        keystream.fill(&mut challenge).unwrap();
+        expected == challenge
+    }
+
+    #[test]
+    fn keystream_rejects_wrong_iv_size() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        assert!(matches!(
+            AesCtrKeystream::new(&key, &[0_u8; 15], AesCtrMode::CRYPTOPALS),
+            Err(AesError::InvalidInitializationVectorSize { was: 15, .. })
+        ));
+        assert!(AesCtrKeystream::at_offset(&key, &[0_u8; 17], AesCtrMode::CRYPTOPALS, 3).is_err());
+    }
+
     #[quickcheck]
     fn encrypt_and_decrypt_is_identity(
         key: AesKey,
@@ -0,0 +1,213 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::ctr::{AesCtrIv, AesCtrKeystream, AesCtrMode};
+use crate::error::AesError;
+use crate::key::AesKey;
+
+const WRITE_CHUNK_SIZE: usize = 8192;
+
+// Wraps a stream so that bytes read from it and bytes written to it are
+// passed through the AES-CTR keystream. The keystream position follows the
+// byte position of the inner stream, so seeking allows random-access reads
+// and edits without processing any of the skipped data.
+pub struct AesCtrStream<'k, S> {
+    inner: S,
+    keystream: AesCtrKeystream<'k>,
+}
+
+impl<'k, S> AesCtrStream<'k, S> {
+    pub fn new(
+        inner: S,
+        key: &'k AesKey,
+        iv: &AesCtrIv,
+        mode: AesCtrMode,
+    ) -> Result<AesCtrStream<'k, S>, AesError> {
+        Self::at_offset(inner, key, iv, mode, 0)
+    }
+
+    // For an inner stream that is already positioned at `position`.
+    pub fn at_offset(
+        inner: S,
+        key: &'k AesKey,
+        iv: &AesCtrIv,
+        mode: AesCtrMode,
+        position: u64,
+    ) -> Result<AesCtrStream<'k, S>, AesError> {
+        Ok(AesCtrStream {
+            inner,
+            keystream: AesCtrKeystream::at_offset(key, iv, mode, position)?,
+        })
+    }
+
+    pub fn position(&self) -> u64 {
+        self.keystream.position()
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<'k, R: Read> Read for AesCtrStream<'k, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.keystream
+            .apply(&mut buf[..n])
+            .map_err(io::Error::other)?;
+        Ok(n)
+    }
+}
+
+impl<'k, W: Write> Write for AesCtrStream<'k, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut chunk = [0_u8; WRITE_CHUNK_SIZE];
+        let len = std::cmp::min(buf.len(), WRITE_CHUNK_SIZE);
+        let chunk = &mut chunk[..len];
+        chunk.copy_from_slice(&buf[..len]);
+        let position = self.keystream.position();
+        self.keystream.apply(chunk).map_err(io::Error::other)?;
+        match self.inner.write(chunk) {
+            Ok(n) => {
+                if n < len {
+                    self.keystream
+                        .seek(position + n as u64)
+                        .map_err(io::Error::other)?;
+                }
+                Ok(n)
+            }
+            Err(error) => {
+                self.keystream.seek(position).map_err(io::Error::other)?;
+                Err(error)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<'k, S: Seek> Seek for AesCtrStream<'k, S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.keystream.seek(position).map_err(io::Error::other)?;
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+    use crate::ctr::{AesCtrCipher, AesCtrIv, AesCtrMode};
+    use crate::ctr_stream::AesCtrStream;
+    use crate::key::AesKey;
+
+    #[quickcheck]
+    fn stream_matches_cipher(
+        key: AesKey,
+        iv: AesCtrIv,
+        mode: AesCtrMode,
+        plaintext: Vec<u8>,
+    ) -> bool {
+        let cipher = AesCtrCipher::new(&key, &iv, mode);
+        let expected: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        let mut writer = AesCtrStream::new(Vec::new(), &key, &iv, mode).unwrap();
+        for chunk in plaintext.chunks(7) {
+            writer.write_all(chunk).unwrap();
+        }
+        let ciphertext = writer.into_inner();
+        let mut reader = AesCtrStream::new(Cursor::new(&ciphertext), &key, &iv, mode).unwrap();
+        let mut challenge: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut challenge).unwrap();
+        expected == ciphertext && plaintext == challenge
+    }
+
+    #[quickcheck]
+    fn seek_and_read_matches_decrypt_at_offset(
+        key: AesKey,
+        iv: AesCtrIv,
+        mode: AesCtrMode,
+        plaintext: Vec<u8>,
+        offset: usize,
+    ) -> bool {
+        let offset = offset % (plaintext.len() + 1);
+        let cipher = AesCtrCipher::new(&key, &iv, mode);
+        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        let mut reader = AesCtrStream::new(Cursor::new(&ciphertext), &key, &iv, mode).unwrap();
+        reader.seek(SeekFrom::Start(offset as u64)).unwrap();
+        let mut challenge: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut challenge).unwrap();
+        plaintext[offset..] == challenge[..]
+    }
+
+    #[quickcheck]
+    fn seek_and_write_edits_in_place(
+        key: AesKey,
+        iv: AesCtrIv,
+        mode: AesCtrMode,
+        plaintext: Vec<u8>,
+        offset: usize,
+        edit: Vec<u8>,
+    ) -> bool {
+        let offset = offset % (plaintext.len() + 1);
+        let cipher = AesCtrCipher::new(&key, &iv, mode);
+        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        let mut stream = AesCtrStream::new(Cursor::new(ciphertext), &key, &iv, mode).unwrap();
+        stream.seek(SeekFrom::Start(offset as u64)).unwrap();
+        stream.write_all(&edit).unwrap();
+        let ciphertext = stream.into_inner().into_inner();
+        let mut expected = plaintext.clone();
+        expected.truncate(offset);
+        expected.extend_from_slice(&edit);
+        if offset + edit.len() < plaintext.len() {
+            expected.extend_from_slice(&plaintext[offset + edit.len()..]);
+        }
+        expected == cipher.decrypt(&ciphertext).unwrap()
+    }
+
+    #[test]
+    fn seek_far_into_the_keystream() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let iv = AesCtrIv::aes_ctr_iv([0xff; 16]);
+        // 32 bytes from 5 bytes into block 2^36 cover blocks 2^36 through
+        // 2^36 + 2. The counter blocks are built by hand for each layout, all
+        // starting from an all-ones IV.
+        let position: u64 = (1 << 40) + 5;
+        let first: u64 = position / 16;
+        let counter_block = |mode: AesCtrMode, n: u64| {
+            let mut block = [0xff_u8; 16];
+            match mode {
+                AesCtrMode::NIST_SP800_38A => {
+                    block = u128::MAX.wrapping_add(n as u128).to_be_bytes();
+                }
+                AesCtrMode::NIST_SP800_38D => {
+                    block[12..].copy_from_slice(&u32::MAX.wrapping_add(n as u32).to_be_bytes());
+                }
+                AesCtrMode::CRYPTOPALS => {
+                    block[8..].copy_from_slice(&u64::MAX.wrapping_add(n).to_le_bytes());
+                }
+                AesCtrMode::Custom(_) => unreachable!(),
+            }
+            block
+        };
+        for mode in [
+            AesCtrMode::NIST_SP800_38A,
+            AesCtrMode::NIST_SP800_38D,
+            AesCtrMode::CRYPTOPALS,
+        ] {
+            let counter_blocks: Vec<u8> = (first..(first + 3))
+                .flat_map(|n| counter_block(mode, n))
+                .collect();
+            let keystream: Vec<u8> = crate::ecb::encrypt(key.as_slice(), &counter_blocks).unwrap();
+            let mut stream =
+                AesCtrStream::at_offset(Vec::new(), &key, &iv, mode, position).unwrap();
+            stream.write_all(&[0_u8; 32]).unwrap();
+            assert_eq!(&keystream[5..37], &stream.into_inner()[..]);
+        }
+    }
+}
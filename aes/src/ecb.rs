@@ -1,12 +1,32 @@
+use pkcs7::Pkcs7Mut;
+
+use crate::block_cipher::{pkcs7_block_size, BlockCipher};
 use crate::error::AesError;
 use crate::key::AesKey;
+#[cfg(feature = "parallel")]
+use crate::parallel::chunk_len;
+
+// Number of blocks handed to the underlying implementation at once, which
+// lets it pipeline several blocks through the AES-NI instructions.
+const BATCH_BLOCKS: usize = 8;
 
+#[derive(Clone)]
 pub enum AesEcbBlockCipher {
     Aes128BlockCipher(Box<aesimpl::Aes128>),
     Aes192BlockCipher(Box<aesimpl::Aes192>),
     Aes256BlockCipher(Box<aesimpl::Aes256>),
 }
 
+impl std::fmt::Debug for AesEcbBlockCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Aes128BlockCipher(_) => write!(f, "Aes128BlockCipher(..)"),
+            Self::Aes192BlockCipher(_) => write!(f, "Aes192BlockCipher(..)"),
+            Self::Aes256BlockCipher(_) => write!(f, "Aes256BlockCipher(..)"),
+        }
+    }
+}
+
 impl AesEcbBlockCipher {
     pub fn new(aes_key: &AesKey) -> Self {
         use aesimpl::cipher::{generic_array::GenericArray, KeyInit};
@@ -60,52 +80,196 @@ impl AesEcbBlockCipher {
             Err(AesError::InvalidBlockSize(plaintext.len()))
         }
     }
+
+    // Encrypts every 16-byte block of `blocks` in place.
+    pub fn encrypt_blocks_mut(&self, blocks: &mut [u8]) {
+        use aesimpl::cipher::BlockEncrypt;
+        self.crypt_blocks_mut(blocks, |batch| match self {
+            Self::Aes128BlockCipher(aes128) => aes128.encrypt_blocks(batch),
+            Self::Aes192BlockCipher(aes192) => aes192.encrypt_blocks(batch),
+            Self::Aes256BlockCipher(aes256) => aes256.encrypt_blocks(batch),
+        })
+    }
+
+    // Decrypts every 16-byte block of `blocks` in place.
+    pub fn decrypt_blocks_mut(&self, blocks: &mut [u8]) {
+        use aesimpl::cipher::BlockDecrypt;
+        self.crypt_blocks_mut(blocks, |batch| match self {
+            Self::Aes128BlockCipher(aes128) => aes128.decrypt_blocks(batch),
+            Self::Aes192BlockCipher(aes192) => aes192.decrypt_blocks(batch),
+            Self::Aes256BlockCipher(aes256) => aes256.decrypt_blocks(batch),
+        })
+    }
+
+    fn crypt_blocks_mut<F: Fn(&mut [aesimpl::Block])>(&self, blocks: &mut [u8], crypt: F) {
+        debug_assert_eq!(blocks.len() % 16, 0);
+        let mut batch = [aesimpl::Block::default(); BATCH_BLOCKS];
+        for chunk in blocks.chunks_mut(16 * BATCH_BLOCKS) {
+            let count = chunk.len() / 16;
+            for (block, input) in batch.iter_mut().zip(chunk.chunks(16)) {
+                block.copy_from_slice(input);
+            }
+            crypt(&mut batch[..count]);
+            for (block, output) in batch.iter().zip(chunk.chunks_mut(16)) {
+                output.copy_from_slice(block);
+            }
+        }
+    }
 }
 
-pub struct AesEcbCipher<'k> {
-    key: &'k AesKey,
+impl BlockCipher for AesEcbBlockCipher {
+    type Key = AesKey;
+    type Error = AesError;
+
+    fn block_size() -> usize {
+        16
+    }
+
+    fn from_key(key: &AesKey) -> Self {
+        AesEcbBlockCipher::new(key)
+    }
+
+    fn key_from_slice(bytes: &[u8]) -> Result<AesKey, AesError> {
+        AesKey::try_copy_from_slice(bytes)
+    }
+
+    fn encrypt_block_mut(&self, block: &mut [u8]) {
+        AesEcbBlockCipher::encrypt_blocks_mut(self, block)
+    }
+
+    fn decrypt_block_mut(&self, block: &mut [u8]) {
+        AesEcbBlockCipher::decrypt_blocks_mut(self, block)
+    }
+
+    fn encrypt_blocks_mut(&self, blocks: &mut [u8]) {
+        AesEcbBlockCipher::encrypt_blocks_mut(self, blocks)
+    }
+
+    fn decrypt_blocks_mut(&self, blocks: &mut [u8]) {
+        AesEcbBlockCipher::decrypt_blocks_mut(self, blocks)
+    }
+}
+
+pub struct EcbCipher<'k, C: BlockCipher> {
+    block_cipher: C,
+    key: std::marker::PhantomData<&'k C::Key>,
 }
 
-impl<'k> AesEcbCipher<'k> {
-    pub fn new(key: &'k AesKey) -> AesEcbCipher<'k> {
-        AesEcbCipher { key }
+pub type AesEcbCipher<'k> = EcbCipher<'k, AesEcbBlockCipher>;
+
+impl<'k, C: BlockCipher> EcbCipher<'k, C> {
+    pub fn new(key: &'k C::Key) -> EcbCipher<'k, C> {
+        Self::from_block_cipher(C::from_key(key))
     }
 
-    pub fn decrypt<T: ?Sized + AsRef<[u8]>>(&self, ciphertext: &T) -> Result<Vec<u8>, AesError> {
-        let ciphertext = ciphertext.as_ref();
-        match ciphertext.len() {
-            0 => Ok(vec![]),
-            x => {
-                if x % 16 == 0 {
-                    let block_cipher = AesEcbBlockCipher::new(&self.key);
-                    Ok(ciphertext
-                        .chunks(16_usize)
-                        .flat_map(|block| block_cipher.decrypt_block(&block).unwrap().into_iter())
-                        .collect())
-                } else {
-                    Err(AesError::InvalidBlockSize(x))
-                }
-            }
+    pub fn from_block_cipher(block_cipher: C) -> EcbCipher<'k, C> {
+        EcbCipher {
+            block_cipher,
+            key: std::marker::PhantomData,
         }
     }
 
+    pub fn decrypt<T: ?Sized + AsRef<[u8]>>(&self, ciphertext: &T) -> Result<Vec<u8>, AesError> {
+        let mut plaintext = ciphertext.as_ref().to_vec();
+        self.decrypt_in_place(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
     pub fn encrypt<T: ?Sized + AsRef<[u8]>>(&self, plaintext: &T) -> Result<Vec<u8>, AesError> {
-        let plaintext = plaintext.as_ref();
-        match plaintext.len() {
-            0 => Ok(vec![]),
-            x => {
-                if x % 16 == 0 {
-                    let block_cipher = AesEcbBlockCipher::new(&self.key);
-                    Ok(plaintext
-                        .chunks(16_usize)
-                        .flat_map(|block| block_cipher.encrypt_block(&block).unwrap().into_iter())
-                        .collect())
-                } else {
-                    Err(AesError::InvalidBlockSize(x))
-                }
-            }
+        let mut ciphertext = plaintext.as_ref().to_vec();
+        self.encrypt_in_place(&mut ciphertext)?;
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt_in_place(&self, buffer: &mut [u8]) -> Result<(), AesError> {
+        if buffer.len().is_multiple_of(C::block_size()) {
+            self.block_cipher.decrypt_blocks_mut(buffer);
+            Ok(())
+        } else {
+            Err(AesError::InvalidBlockSize(buffer.len()))
+        }
+    }
+
+    pub fn encrypt_in_place(&self, buffer: &mut [u8]) -> Result<(), AesError> {
+        if buffer.len().is_multiple_of(C::block_size()) {
+            self.block_cipher.encrypt_blocks_mut(buffer);
+            Ok(())
+        } else {
+            Err(AesError::InvalidBlockSize(buffer.len()))
         }
     }
+
+    // Decrypts and then strips the PKCS#7 padding.
+    pub fn decrypt_padded_in_place(&self, buffer: &mut Vec<u8>) -> Result<(), AesError> {
+        let block_size = pkcs7_block_size::<C>()?;
+        self.decrypt_in_place(buffer)?;
+        buffer.pkcs7_unpad_mut(block_size)?;
+        Ok(())
+    }
+
+    // Applies PKCS#7 padding and then encrypts. Like `pkcs7::pad`, an empty
+    // buffer gets no padding block and stays empty.
+    pub fn encrypt_padded_in_place(&self, buffer: &mut Vec<u8>) -> Result<(), AesError> {
+        buffer.pkcs7_pad_mut(pkcs7_block_size::<C>()?)?;
+        self.encrypt_in_place(buffer)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<'k, C: BlockCipher + Sync> EcbCipher<'k, C> {
+    pub fn par_decrypt<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        ciphertext: &T,
+        chunk_size: usize,
+    ) -> Result<Vec<u8>, AesError> {
+        let mut plaintext = ciphertext.as_ref().to_vec();
+        self.par_decrypt_in_place(&mut plaintext, chunk_size)?;
+        Ok(plaintext)
+    }
+
+    pub fn par_encrypt<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        plaintext: &T,
+        chunk_size: usize,
+    ) -> Result<Vec<u8>, AesError> {
+        let mut ciphertext = plaintext.as_ref().to_vec();
+        self.par_encrypt_in_place(&mut ciphertext, chunk_size)?;
+        Ok(ciphertext)
+    }
+
+    // Splits `buffer` into chunks of about `chunk_size` bytes and decrypts
+    // them on the rayon thread pool.
+    pub fn par_decrypt_in_place(
+        &self,
+        buffer: &mut [u8],
+        chunk_size: usize,
+    ) -> Result<(), AesError> {
+        use rayon::prelude::*;
+        if !buffer.len().is_multiple_of(C::block_size()) {
+            return Err(AesError::InvalidBlockSize(buffer.len()));
+        }
+        let block_cipher = &self.block_cipher;
+        buffer
+            .par_chunks_mut(chunk_len(chunk_size, C::block_size()))
+            .for_each(|chunk| block_cipher.decrypt_blocks_mut(chunk));
+        Ok(())
+    }
+
+    pub fn par_encrypt_in_place(
+        &self,
+        buffer: &mut [u8],
+        chunk_size: usize,
+    ) -> Result<(), AesError> {
+        use rayon::prelude::*;
+        if !buffer.len().is_multiple_of(C::block_size()) {
+            return Err(AesError::InvalidBlockSize(buffer.len()));
+        }
+        let block_cipher = &self.block_cipher;
+        buffer
+            .par_chunks_mut(chunk_len(chunk_size, C::block_size()))
+            .for_each(|chunk| block_cipher.encrypt_blocks_mut(chunk));
+        Ok(())
+    }
 }
 
 pub fn decrypt<K: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
@@ -125,3 +289,103 @@ pub fn encrypt<K: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
     let cipher = AesEcbCipher::new(&key);
     cipher.encrypt(plaintext)
 }
+
+pub fn decrypt_in_place<K: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    buffer: &mut [u8],
+) -> Result<(), AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let cipher = AesEcbCipher::new(&key);
+    cipher.decrypt_in_place(buffer)
+}
+
+pub fn encrypt_in_place<K: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    buffer: &mut [u8],
+) -> Result<(), AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let cipher = AesEcbCipher::new(&key);
+    cipher.encrypt_in_place(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecb::{AesEcbBlockCipher, AesEcbCipher};
+    use crate::error::AesError;
+    use crate::key::AesKey;
+
+    #[quickcheck]
+    fn batched_encrypt_matches_block_by_block(key: AesKey, blocks: Vec<u128>) -> bool {
+        let plaintext: Vec<u8> = blocks.iter().flat_map(|b| b.to_be_bytes()).collect();
+        let block_cipher = AesEcbBlockCipher::new(&key);
+        let expected: Vec<u8> = plaintext
+            .chunks(16)
+            .flat_map(|block| block_cipher.encrypt_block(block).unwrap())
+            .collect();
+        let cipher = AesEcbCipher::new(&key);
+        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        expected == ciphertext && plaintext == cipher.decrypt(&ciphertext).unwrap()
+    }
+
+    #[quickcheck]
+    fn in_place_matches_allocating(key: AesKey, blocks: Vec<u128>) -> bool {
+        let plaintext: Vec<u8> = blocks.iter().flat_map(|b| b.to_be_bytes()).collect();
+        let cipher = AesEcbCipher::new(&key);
+        let mut buffer = plaintext.clone();
+        cipher.encrypt_in_place(&mut buffer).unwrap();
+        let expected: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        let encrypted = expected == buffer;
+        cipher.decrypt_in_place(&mut buffer).unwrap();
+        encrypted && plaintext == buffer
+    }
+
+    #[quickcheck]
+    fn padded_in_place_round_trip(key: AesKey, plaintext: Vec<u8>) -> bool {
+        let cipher = AesEcbCipher::new(&key);
+        let mut buffer = plaintext.clone();
+        cipher.encrypt_padded_in_place(&mut buffer).unwrap();
+        let expected: Vec<u8> = cipher
+            .encrypt(&pkcs7::pad::<Vec<u8>, _>(&plaintext, 16).unwrap())
+            .unwrap();
+        let encrypted = expected == buffer;
+        cipher.decrypt_padded_in_place(&mut buffer).unwrap();
+        encrypted && plaintext == buffer
+    }
+
+    #[test]
+    fn padded_in_place_leaves_empty_input_empty() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let cipher = AesEcbCipher::new(&key);
+        let mut buffer: Vec<u8> = vec![];
+        cipher.encrypt_padded_in_place(&mut buffer).unwrap();
+        assert!(buffer.is_empty());
+        cipher.decrypt_padded_in_place(&mut buffer).unwrap();
+        assert!(buffer.is_empty());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[quickcheck]
+    fn parallel_matches_sequential(key: AesKey, blocks: Vec<u128>, chunk_size: u8) -> bool {
+        let plaintext: Vec<u8> = blocks.iter().flat_map(|b| b.to_be_bytes()).collect();
+        let cipher = AesEcbCipher::new(&key);
+        let expected: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        let ciphertext: Vec<u8> = cipher.par_encrypt(&plaintext, chunk_size as usize).unwrap();
+        expected == ciphertext
+            && plaintext
+                == cipher
+                    .par_decrypt(&ciphertext, chunk_size as usize)
+                    .unwrap()
+    }
+
+    #[test]
+    fn in_place_rejects_partial_blocks() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let cipher = AesEcbCipher::new(&key);
+        let mut buffer = [0_u8; 17];
+        assert_eq!(
+            Err(AesError::InvalidBlockSize(17)),
+            cipher.encrypt_in_place(&mut buffer)
+        );
+        assert_eq!([0_u8; 17], buffer);
+    }
+}
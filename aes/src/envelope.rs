@@ -0,0 +1,508 @@
+// A self-describing container for ciphertext, so that whatever is needed to
+// decrypt it (besides the key) travels with it.
+//
+// Version 1 layout, all lengths in bytes:
+//
+//   magic       2   "AE"
+//   version     1   1
+//   algorithm   1   see `EnvelopeAlgorithm`
+//   mode        1   see `EnvelopeMode`
+//   padding     1   see `EnvelopePadding`
+//   key size    1   16, 24, or 32
+//   iv length   1
+//   iv          iv length
+//   tag length  1   0 when there is no tag
+//   tag         tag length
+//   payload     the rest
+//
+// For GCM everything before the tag is authenticated as associated data.
+
+use pkcs7::padding::{
+    AnsiX923Padding, Iso10126Padding, Iso7816Padding, Padding, Pkcs7Padding, Sslv3Padding,
+    ZeroPadding,
+};
+
+use crate::cbc::AesCbcMode;
+use crate::ctr::AesCtrMode;
+use crate::ecb;
+use crate::error::AesError;
+use crate::gcm::{AesGcmCipher, AesGcmIv};
+use crate::key::AesKey;
+
+pub const ENVELOPE_MAGIC: [u8; 2] = *b"AE";
+pub const ENVELOPE_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvelopeAlgorithm {
+    Aes,
+}
+
+impl EnvelopeAlgorithm {
+    fn id(self) -> u8 {
+        match self {
+            Self::Aes => 0x01,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, AesError> {
+        match id {
+            0x01 => Ok(Self::Aes),
+            _ => Err(AesError::InvalidEnvelope("unknown algorithm")),
+        }
+    }
+}
+
+// Only the built-in counter layouts have an id; `AesCtrMode::Custom` cannot
+// be described by the header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvelopeMode {
+    Ecb,
+    Cbc,
+    Ctr(AesCtrMode),
+    Gcm,
+}
+
+impl EnvelopeMode {
+    fn id(self) -> Result<u8, AesError> {
+        match self {
+            Self::Ecb => Ok(0x01),
+            Self::Cbc => Ok(0x02),
+            Self::Ctr(AesCtrMode::NIST_SP800_38A) => Ok(0x03),
+            Self::Ctr(AesCtrMode::NIST_SP800_38D) => Ok(0x04),
+            Self::Ctr(AesCtrMode::CRYPTOPALS) => Ok(0x05),
+            Self::Ctr(AesCtrMode::Custom(_)) => Err(AesError::InvalidEnvelope(
+                "custom counter layouts cannot be encoded",
+            )),
+            Self::Gcm => Ok(0x06),
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, AesError> {
+        match id {
+            0x01 => Ok(Self::Ecb),
+            0x02 => Ok(Self::Cbc),
+            0x03 => Ok(Self::Ctr(AesCtrMode::NIST_SP800_38A)),
+            0x04 => Ok(Self::Ctr(AesCtrMode::NIST_SP800_38D)),
+            0x05 => Ok(Self::Ctr(AesCtrMode::CRYPTOPALS)),
+            0x06 => Ok(Self::Gcm),
+            _ => Err(AesError::InvalidEnvelope("unknown mode")),
+        }
+    }
+
+    // Checks the iv and tag lengths that go with the mode.
+    fn check(self, iv_len: usize, tag_len: Option<usize>) -> Result<(), AesError> {
+        let (iv_ok, explanation) = match self {
+            Self::Ecb => (iv_len == 0, "must be empty for ECB"),
+            Self::Cbc | Self::Ctr(_) => (iv_len == 16, "must be one block"),
+            Self::Gcm => (iv_len > 0, "must not be empty for GCM"),
+        };
+        if !iv_ok {
+            return Err(AesError::InvalidInitializationVectorSize {
+                explanation,
+                was: iv_len,
+            });
+        }
+        match (self, tag_len) {
+            (Self::Gcm, Some(tag_len)) if (4..=16).contains(&tag_len) => Ok(()),
+            (Self::Gcm, Some(tag_len)) => Err(AesError::InvalidTagSize(tag_len)),
+            (Self::Gcm, None) => Err(AesError::InvalidEnvelope("missing tag")),
+            (_, Some(_)) => Err(AesError::InvalidEnvelope("unexpected tag")),
+            (_, None) => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvelopePadding {
+    Nothing,
+    Pkcs7,
+    AnsiX923,
+    Iso10126,
+    Iso7816,
+    Zero,
+    Sslv3,
+}
+
+impl EnvelopePadding {
+    pub fn padding(self) -> Option<&'static dyn Padding> {
+        match self {
+            Self::Nothing => None,
+            Self::Pkcs7 => Some(&Pkcs7Padding),
+            Self::AnsiX923 => Some(&AnsiX923Padding),
+            Self::Iso10126 => Some(&Iso10126Padding),
+            Self::Iso7816 => Some(&Iso7816Padding),
+            Self::Zero => Some(&ZeroPadding),
+            Self::Sslv3 => Some(&Sslv3Padding),
+        }
+    }
+
+    fn id(self) -> u8 {
+        match self {
+            Self::Nothing => 0x00,
+            Self::Pkcs7 => 0x01,
+            Self::AnsiX923 => 0x02,
+            Self::Iso10126 => 0x03,
+            Self::Iso7816 => 0x04,
+            Self::Zero => 0x05,
+            Self::Sslv3 => 0x06,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, AesError> {
+        match id {
+            0x00 => Ok(Self::Nothing),
+            0x01 => Ok(Self::Pkcs7),
+            0x02 => Ok(Self::AnsiX923),
+            0x03 => Ok(Self::Iso10126),
+            0x04 => Ok(Self::Iso7816),
+            0x05 => Ok(Self::Zero),
+            0x06 => Ok(Self::Sslv3),
+            _ => Err(AesError::InvalidEnvelope("unknown padding")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Envelope {
+    algorithm: EnvelopeAlgorithm,
+    mode: EnvelopeMode,
+    padding: EnvelopePadding,
+    key_size: usize,
+    iv: Vec<u8>,
+    tag: Option<Vec<u8>>,
+    payload: Vec<u8>,
+}
+
+impl Envelope {
+    // Encrypts `plaintext` under `mode`, padding it first if asked to. GCM
+    // always produces a 16-byte tag.
+    pub fn seal<IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        key: &AesKey,
+        mode: EnvelopeMode,
+        padding: EnvelopePadding,
+        iv: &IV,
+        plaintext: &T,
+    ) -> Result<Envelope, AesError> {
+        let iv = iv.as_ref();
+        let tag_len = match mode {
+            EnvelopeMode::Gcm => Some(16),
+            _ => None,
+        };
+        mode.check(iv.len(), tag_len)?;
+        let mut envelope = Envelope {
+            algorithm: EnvelopeAlgorithm::Aes,
+            mode,
+            padding,
+            key_size: key.as_slice().len(),
+            iv: iv.to_vec(),
+            tag: None,
+            payload: Vec::new(),
+        };
+        let mut plaintext = plaintext.as_ref().to_vec();
+        if let Some(padding) = padding.padding() {
+            padding.pad_mut(&mut plaintext, 16)?;
+        }
+        envelope.payload = match mode {
+            EnvelopeMode::Ecb => ecb::encrypt(key.as_slice(), &plaintext)?,
+            EnvelopeMode::Cbc => AesCbcMode::STANDARD.encrypt(key.as_slice(), iv, &plaintext)?,
+            EnvelopeMode::Ctr(ctr_mode) => ctr_mode.encrypt(key.as_slice(), iv, &plaintext)?,
+            EnvelopeMode::Gcm => {
+                let iv = AesGcmIv::try_copy_from_slice(iv)?;
+                let aad = envelope.header(16)?;
+                let (ciphertext, tag) =
+                    AesGcmCipher::new(key, &iv).encrypt_detached(&aad, &plaintext)?;
+                envelope.tag = Some(tag);
+                ciphertext
+            }
+        };
+        Ok(envelope)
+    }
+
+    // Decrypts the payload with whatever the header describes.
+    pub fn open(&self, key: &AesKey) -> Result<Vec<u8>, AesError> {
+        if key.as_slice().len() != self.key_size {
+            return Err(AesError::InvalidKeySize(key.as_slice().len()));
+        }
+        let iv = self.iv.as_slice();
+        let mut plaintext = match self.mode {
+            EnvelopeMode::Ecb => ecb::decrypt(key.as_slice(), &self.payload)?,
+            EnvelopeMode::Cbc => AesCbcMode::STANDARD.decrypt(key.as_slice(), iv, &self.payload)?,
+            EnvelopeMode::Ctr(ctr_mode) => ctr_mode.decrypt(key.as_slice(), iv, &self.payload)?,
+            EnvelopeMode::Gcm => {
+                let tag = self
+                    .tag
+                    .as_ref()
+                    .ok_or(AesError::InvalidEnvelope("missing tag"))?;
+                let iv = AesGcmIv::try_copy_from_slice(iv)?;
+                let aad = self.header(tag.len())?;
+                AesGcmCipher::with_tag_size(key, &iv, tag.len())?.decrypt_detached(
+                    &aad,
+                    &self.payload,
+                    tag,
+                )?
+            }
+        };
+        if let Some(padding) = self.padding.padding() {
+            padding.unpad_mut(&mut plaintext, 16)?;
+        }
+        Ok(plaintext)
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, AesError> {
+        let tag = self.tag.as_deref().unwrap_or(&[]);
+        let mut output = self.header(tag.len())?;
+        output.reserve(tag.len() + self.payload.len());
+        output.extend_from_slice(tag);
+        output.extend_from_slice(&self.payload);
+        Ok(output)
+    }
+
+    pub fn decode<T: ?Sized + AsRef<[u8]>>(input: &T) -> Result<Envelope, AesError> {
+        let mut reader = Reader(input.as_ref());
+        if reader.take(2)? != ENVELOPE_MAGIC {
+            return Err(AesError::InvalidEnvelope("bad magic"));
+        }
+        let version = reader.byte()?;
+        if version != ENVELOPE_VERSION {
+            return Err(AesError::UnsupportedEnvelopeVersion(version));
+        }
+        let algorithm = EnvelopeAlgorithm::from_id(reader.byte()?)?;
+        let mode = EnvelopeMode::from_id(reader.byte()?)?;
+        let padding = EnvelopePadding::from_id(reader.byte()?)?;
+        let key_size = reader.byte()? as usize;
+        if ![16, 24, 32].contains(&key_size) {
+            return Err(AesError::InvalidKeySize(key_size));
+        }
+        let iv_len = reader.byte()? as usize;
+        let iv = reader.take(iv_len)?.to_vec();
+        let tag_len = reader.byte()? as usize;
+        let tag = match tag_len {
+            0 => None,
+            _ => Some(reader.take(tag_len)?.to_vec()),
+        };
+        mode.check(iv.len(), tag.as_ref().map(Vec::len))?;
+        Ok(Envelope {
+            algorithm,
+            mode,
+            padding,
+            key_size,
+            iv,
+            tag,
+            payload: reader.0.to_vec(),
+        })
+    }
+
+    pub fn algorithm(&self) -> EnvelopeAlgorithm {
+        self.algorithm
+    }
+
+    pub fn mode(&self) -> EnvelopeMode {
+        self.mode
+    }
+
+    pub fn padding(&self) -> EnvelopePadding {
+        self.padding
+    }
+
+    pub fn key_size(&self) -> usize {
+        self.key_size
+    }
+
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+
+    pub fn tag(&self) -> Option<&[u8]> {
+        self.tag.as_deref()
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    // Everything up to and including the tag length.
+    fn header(&self, tag_len: usize) -> Result<Vec<u8>, AesError> {
+        let iv_len =
+            u8::try_from(self.iv.len()).map_err(|_| AesError::InvalidInitializationVectorSize {
+                explanation: "must be at most 255 bytes",
+                was: self.iv.len(),
+            })?;
+        let mut header = Vec::with_capacity(9 + self.iv.len());
+        header.extend_from_slice(&ENVELOPE_MAGIC);
+        header.push(ENVELOPE_VERSION);
+        header.push(self.algorithm.id());
+        header.push(self.mode.id()?);
+        header.push(self.padding.id());
+        header.push(self.key_size as u8);
+        header.push(iv_len);
+        header.extend_from_slice(&self.iv);
+        header.push(tag_len as u8);
+        Ok(header)
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], AesError> {
+        if self.0.len() < length {
+            return Err(AesError::InvalidEnvelope("truncated header"));
+        }
+        let (head, tail) = self.0.split_at(length);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8, AesError> {
+        Ok(self.take(1)?[0])
+    }
+}
+
+pub fn decrypt<K: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    envelope: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    Envelope::decode(envelope)?.open(&key)
+}
+
+pub fn encrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    mode: EnvelopeMode,
+    padding: EnvelopePadding,
+    iv: &IV,
+    plaintext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    Envelope::seal(&key, mode, padding, iv, plaintext)?.encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cbc::AesCbcIv;
+    use crate::ctr::AesCtrIv;
+    use quickcheck::TestResult;
+
+    const MODES: [EnvelopeMode; 6] = [
+        EnvelopeMode::Ecb,
+        EnvelopeMode::Cbc,
+        EnvelopeMode::Ctr(AesCtrMode::NIST_SP800_38A),
+        EnvelopeMode::Ctr(AesCtrMode::NIST_SP800_38D),
+        EnvelopeMode::Ctr(AesCtrMode::CRYPTOPALS),
+        EnvelopeMode::Gcm,
+    ];
+
+    fn iv_for(mode: EnvelopeMode, iv: &[u8; 16]) -> &[u8] {
+        match mode {
+            EnvelopeMode::Ecb => &[],
+            EnvelopeMode::Gcm => &iv[..12],
+            _ => &iv[..],
+        }
+    }
+
+    #[quickcheck]
+    fn round_trip(key: AesKey, iv: AesCbcIv, plaintext: Vec<u8>) -> bool {
+        let iv: [u8; 16] = iv.as_slice().try_into().unwrap();
+        MODES.iter().all(|&mode| {
+            let iv = iv_for(mode, &iv);
+            let encoded =
+                encrypt(key.as_slice(), mode, EnvelopePadding::Pkcs7, iv, &plaintext).unwrap();
+            let envelope = Envelope::decode(&encoded).unwrap();
+            envelope.mode() == mode
+                && envelope.iv() == iv
+                && envelope.encode().unwrap() == encoded
+                && decrypt(key.as_slice(), &encoded).unwrap() == plaintext
+        })
+    }
+
+    #[quickcheck]
+    fn round_trip_with_every_padding(key: AesKey, iv: AesCbcIv, plaintext: Vec<u8>) -> bool {
+        [
+            EnvelopePadding::Pkcs7,
+            EnvelopePadding::AnsiX923,
+            EnvelopePadding::Iso10126,
+            EnvelopePadding::Iso7816,
+            EnvelopePadding::Sslv3,
+        ]
+        .iter()
+        .all(|&padding| {
+            let envelope =
+                Envelope::seal(&key, EnvelopeMode::Cbc, padding, &iv, &plaintext).unwrap();
+            Envelope::decode(&envelope.encode().unwrap())
+                .unwrap()
+                .padding()
+                == padding
+                && envelope.open(&key).unwrap() == plaintext
+        })
+    }
+
+    #[quickcheck]
+    fn unpadded_stream_modes_keep_length(key: AesKey, iv: AesCtrIv, plaintext: Vec<u8>) -> bool {
+        let mode = EnvelopeMode::Ctr(AesCtrMode::CRYPTOPALS);
+        let envelope =
+            Envelope::seal(&key, mode, EnvelopePadding::Nothing, &iv, &plaintext).unwrap();
+        envelope.payload().len() == plaintext.len() && envelope.open(&key).unwrap() == plaintext
+    }
+
+    #[quickcheck]
+    fn gcm_authenticates_header(key: AesKey, plaintext: Vec<u8>, index: usize) -> TestResult {
+        let mut encoded = encrypt(
+            key.as_slice(),
+            EnvelopeMode::Gcm,
+            EnvelopePadding::Nothing,
+            &[7_u8; 12],
+            &plaintext,
+        )
+        .unwrap();
+        // Flip a bit in the padding byte or anywhere from the iv length on.
+        let index = match index % (encoded.len() - 6) {
+            0 => 5,
+            i => 6 + i,
+        };
+        encoded[index] ^= 1;
+        TestResult::from_bool(decrypt(key.as_slice(), &encoded).is_err())
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let encoded = encrypt(
+            key.as_slice(),
+            EnvelopeMode::Cbc,
+            EnvelopePadding::Pkcs7,
+            &[0_u8; 16],
+            b"hello",
+        )
+        .unwrap();
+        assert_eq!(b"AE\x01\x01\x02\x01\x10\x10", &encoded[..8]);
+        assert_eq!(
+            Err(AesError::InvalidEnvelope("bad magic")),
+            Envelope::decode(&encoded[1..])
+        );
+        let mut future = encoded.clone();
+        future[2] = 2;
+        assert_eq!(
+            Err(AesError::UnsupportedEnvelopeVersion(2)),
+            Envelope::decode(&future)
+        );
+        assert_eq!(
+            Err(AesError::InvalidEnvelope("truncated header")),
+            Envelope::decode(&encoded[..20])
+        );
+        let mut tagged = encoded.clone();
+        tagged[24] = 1;
+        assert_eq!(
+            Err(AesError::InvalidEnvelope("unexpected tag")),
+            Envelope::decode(&tagged)
+        );
+        assert_eq!(
+            Err(AesError::InvalidKeySize(32)),
+            decrypt(&[0_u8; 32], &encoded)
+        );
+        assert_eq!(
+            b"hello".to_vec(),
+            decrypt(key.as_slice(), &encoded).unwrap()
+        );
+    }
+}
@@ -1,7 +1,10 @@
+use pkcs7::errors::{PadError, Pkcs7PadError, Pkcs7UnpadError, UnpadError};
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AesError {
     InvalidBlockSize(usize),
     InvalidKeySize(usize),
+    InvalidKey(&'static str),
     InvalidInitializationVectorSize {
         explanation: &'static str,
         was: usize,
@@ -10,6 +13,23 @@ pub enum AesError {
         length: usize,
         offset: usize,
     },
+    InvalidTagSize(usize),
+    InvalidRoundCount(usize),
+    InvalidDataUnitSize(usize),
+    MessageTooLong(usize),
+    TooManyComponents(usize),
+    InvalidKeyDataSize(usize),
+    KeyUnwrapFailed,
+    AuthenticationFailed,
+    PaddingFailed(PadError),
+    InvalidPadding(UnpadError),
+    InvalidCounterLayout {
+        nonce_size: usize,
+        counter_size: usize,
+    },
+    CounterOverflow,
+    InvalidEnvelope(&'static str),
+    UnsupportedEnvelopeVersion(u8),
 }
 
 impl std::error::Error for AesError {
@@ -18,8 +38,23 @@ impl std::error::Error for AesError {
         match *self {
             Self::InvalidBlockSize(_) => "invalid block size",
             Self::InvalidKeySize(_) => "invalid key size",
+            Self::InvalidKey(_) => "invalid key",
             Self::InvalidInitializationVectorSize { .. } => "invalid initialization vector size",
             Self::InvalidOffset { .. } => "invalid offset for length of input",
+            Self::InvalidTagSize(_) => "invalid tag size",
+            Self::InvalidRoundCount(_) => "invalid round count",
+            Self::InvalidDataUnitSize(_) => "invalid data unit size",
+            Self::MessageTooLong(_) => "message too long",
+            Self::TooManyComponents(_) => "too many associated data components",
+            Self::InvalidKeyDataSize(_) => "invalid key data size",
+            Self::KeyUnwrapFailed => "key unwrap failed",
+            Self::AuthenticationFailed => "authentication failed",
+            Self::PaddingFailed(_) => "padding failed",
+            Self::InvalidPadding(_) => "invalid padding",
+            Self::InvalidCounterLayout { .. } => "invalid counter layout",
+            Self::CounterOverflow => "counter overflow",
+            Self::InvalidEnvelope(_) => "invalid envelope",
+            Self::UnsupportedEnvelopeVersion(_) => "unsupported envelope version",
         }
     }
 }
@@ -29,12 +64,13 @@ impl ::core::fmt::Display for AesError {
         match *self {
             Self::InvalidBlockSize(size) => write!(
                 f,
-                "Invalid block size of '{}' must be divisible by 16",
+                "Invalid block size of '{}' must be a multiple of the block size",
                 size
             ),
             Self::InvalidKeySize(size) => {
                 write!(f, "Invalid key size of '{}' must be 16, 24, or 32", size)
             }
+            Self::InvalidKey(explanation) => write!(f, "Invalid key: {}", explanation),
             Self::InvalidInitializationVectorSize { was, explanation } => write!(
                 f,
                 "Invalid initialization vector size of '{}' {}",
@@ -45,6 +81,69 @@ impl ::core::fmt::Display for AesError {
                 "Invalid offset of '{}' for input length of {}",
                 offset, length
             ),
+            Self::InvalidTagSize(size) => {
+                write!(f, "Invalid tag size of '{}'", size)
+            }
+            Self::InvalidRoundCount(rounds) => {
+                write!(f, "Invalid round count of '{}' must be at least 1", rounds)
+            }
+            Self::InvalidDataUnitSize(size) => write!(
+                f,
+                "Invalid data unit size of '{}' must be at least 16",
+                size
+            ),
+            Self::MessageTooLong(size) => {
+                write!(f, "Message of length '{}' is too long for this mode", size)
+            }
+            Self::TooManyComponents(count) => write!(
+                f,
+                "Too many associated data components '{}' must be at most 126",
+                count
+            ),
+            Self::InvalidKeyDataSize(size) => {
+                write!(f, "Invalid key data size of '{}' for key wrap", size)
+            }
+            Self::KeyUnwrapFailed => write!(f, "Key unwrap integrity check failed"),
+            Self::AuthenticationFailed => write!(f, "Authentication tag does not match"),
+            Self::PaddingFailed(error) => write!(f, "Padding failed: {}", error),
+            Self::InvalidPadding(error) => write!(f, "Invalid padding: {}", error),
+            Self::InvalidCounterLayout {
+                nonce_size,
+                counter_size,
+            } => write!(
+                f,
+                "Invalid counter layout of nonce size '{}' and counter size '{}' for the block size",
+                nonce_size, counter_size
+            ),
+            Self::CounterOverflow => write!(f, "Counter would overflow its field"),
+            Self::InvalidEnvelope(explanation) => write!(f, "Invalid envelope: {}", explanation),
+            Self::UnsupportedEnvelopeVersion(version) => {
+                write!(f, "Unsupported envelope version '{}'", version)
+            }
         }
     }
 }
+
+impl From<PadError> for AesError {
+    fn from(error: PadError) -> Self {
+        Self::PaddingFailed(error)
+    }
+}
+
+impl From<UnpadError> for AesError {
+    fn from(error: UnpadError) -> Self {
+        Self::InvalidPadding(error)
+    }
+}
+
+impl From<Pkcs7PadError> for AesError {
+    fn from(error: Pkcs7PadError) -> Self {
+        Self::PaddingFailed(error.into())
+    }
+}
+
+impl From<Pkcs7UnpadError> for AesError {
+    fn from(error: Pkcs7UnpadError) -> Self {
+        Self::InvalidPadding(error.into())
+    }
+}
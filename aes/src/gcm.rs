@@ -0,0 +1,453 @@
+// See: https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38d.pdf
+
+use quickcheck::{Arbitrary, Gen};
+use rand::prelude::*;
+use xor::ct::ct_eq;
+use zeroize::Zeroize;
+
+use crate::ctr::{AesCtrIv, AesCtrKeystream, AesCtrMode};
+use crate::ecb::AesEcbBlockCipher;
+use crate::error::AesError;
+use crate::key::AesKey;
+
+// Multiplication in GF(2^128) using the bit-reflected convention of SP 800-38D
+// (the most significant bit of the first byte is the coefficient of x^0).
+pub fn gf128_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z: u128 = 0;
+    let mut v: u128 = y;
+    for i in 0..128 {
+        if (x >> (127 - i)) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+    }
+    z
+}
+
+// `h` is derived from the key, so it is wiped on drop and kept out of `Debug`.
+#[derive(Clone)]
+pub struct Ghash {
+    h: u128,
+    y: u128,
+}
+
+impl std::fmt::Debug for Ghash {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Ghash").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Ghash {
+    fn drop(&mut self) {
+        self.h.zeroize();
+        self.y.zeroize();
+    }
+}
+
+impl Ghash {
+    pub fn new(h: [u8; 16]) -> Ghash {
+        Ghash {
+            h: u128::from_be_bytes(h),
+            y: 0,
+        }
+    }
+
+    pub fn update_block(&mut self, block: [u8; 16]) {
+        self.y = gf128_mul(self.y ^ u128::from_be_bytes(block), self.h);
+    }
+
+    // Absorbs `input`, zero-padding the final partial block.
+    pub fn update_padded<T: ?Sized + AsRef<[u8]>>(&mut self, input: &T) {
+        for chunk in input.as_ref().chunks(16) {
+            let mut block = [0_u8; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.update_block(block);
+        }
+    }
+
+    pub fn output(&self) -> [u8; 16] {
+        self.y.to_be_bytes()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AesGcmIv(Vec<u8>);
+
+impl AesGcmIv {
+    pub fn aes_gcm_iv(value: [u8; 12]) -> AesGcmIv {
+        AesGcmIv(value.to_vec())
+    }
+
+    pub fn try_copy_from_slice<IV: ?Sized + AsRef<[u8]>>(bytes: &IV) -> Result<AesGcmIv, AesError> {
+        let bytes = bytes.as_ref();
+        match bytes.len() {
+            0 => Err(AesError::InvalidInitializationVectorSize {
+                was: 0,
+                explanation: "must be at least 1 byte",
+            }),
+            _ => Ok(AesGcmIv(bytes.to_vec())),
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+}
+
+impl Distribution<AesGcmIv> for rand::distributions::Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AesGcmIv {
+        AesGcmIv::aes_gcm_iv(rng.gen())
+    }
+}
+
+impl Arbitrary for AesGcmIv {
+    fn arbitrary(g: &mut Gen) -> AesGcmIv {
+        let mut iv: Vec<u8> = Vec::arbitrary(g);
+        if iv.is_empty() || bool::arbitrary(g) {
+            iv.resize(12, 0);
+        }
+        AesGcmIv(iv)
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = AesGcmIv>> {
+        Box::new(self.0.shrink().filter(|iv| !iv.is_empty()).map(AesGcmIv))
+    }
+}
+
+pub struct AesGcmCipher<'k, 'iv> {
+    key: &'k AesKey,
+    iv: &'iv AesGcmIv,
+    tag_size: usize,
+}
+
+impl<'k, 'iv> AesGcmCipher<'k, 'iv> {
+    pub fn new(key: &'k AesKey, iv: &'iv AesGcmIv) -> AesGcmCipher<'k, 'iv> {
+        AesGcmCipher {
+            key,
+            iv,
+            tag_size: 16,
+        }
+    }
+
+    pub fn with_tag_size(
+        key: &'k AesKey,
+        iv: &'iv AesGcmIv,
+        tag_size: usize,
+    ) -> Result<AesGcmCipher<'k, 'iv>, AesError> {
+        if !(4..=16).contains(&tag_size) {
+            return Err(AesError::InvalidTagSize(tag_size));
+        }
+        Ok(AesGcmCipher { key, iv, tag_size })
+    }
+
+    pub fn tag_size(&self) -> usize {
+        self.tag_size
+    }
+
+    fn encrypt_block(block_cipher: &AesEcbBlockCipher, block: &[u8]) -> [u8; 16] {
+        let mut output = [0_u8; 16];
+        output.copy_from_slice(block);
+        block_cipher.encrypt_blocks_mut(&mut output);
+        output
+    }
+
+    // Pre-counter block J0.
+    fn pre_counter_block(&self, block_cipher: &AesEcbBlockCipher) -> AesCtrIv {
+        let iv = self.iv.as_slice();
+        if iv.len() == 12 {
+            let mut j0 = [0_u8; 16];
+            j0[..12].copy_from_slice(iv);
+            j0[15] = 1;
+            AesCtrIv::aes_ctr_iv(j0)
+        } else {
+            let mut ghash = Ghash::new(Self::encrypt_block(block_cipher, &[0_u8; 16]));
+            ghash.update_padded(iv);
+            ghash.update_block(((iv.len() as u128) * 8).to_be_bytes());
+            AesCtrIv::aes_ctr_iv(ghash.output())
+        }
+    }
+
+    fn compute_tag(
+        &self,
+        block_cipher: &AesEcbBlockCipher,
+        j0: &AesCtrIv,
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Vec<u8> {
+        let mut ghash = Ghash::new(Self::encrypt_block(block_cipher, &[0_u8; 16]));
+        ghash.update_padded(aad);
+        ghash.update_padded(ciphertext);
+        let lengths: u128 = (((aad.len() as u128) * 8) << 64) | ((ciphertext.len() as u128) * 8);
+        ghash.update_block(lengths.to_be_bytes());
+        let mask = Self::encrypt_block(block_cipher, j0.as_slice());
+        ghash
+            .output()
+            .iter()
+            .zip(mask.iter())
+            .take(self.tag_size)
+            .map(|(a, b)| a ^ b)
+            .collect()
+    }
+
+    fn crypt(
+        &self,
+        block_cipher: &AesEcbBlockCipher,
+        j0: &AesCtrIv,
+        input: &[u8],
+    ) -> Result<Vec<u8>, AesError> {
+        let mut counter = j0.clone();
+        counter.increment(AesCtrMode::NIST_SP800_38D)?;
+        let mut output = input.to_vec();
+        AesCtrKeystream::from_block_cipher(block_cipher, &counter, AesCtrMode::NIST_SP800_38D)?
+            .apply(&mut output)?;
+        Ok(output)
+    }
+
+    pub fn encrypt_detached<A: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        aad: &A,
+        plaintext: &T,
+    ) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+        let block_cipher = AesEcbBlockCipher::new(self.key);
+        let j0 = self.pre_counter_block(&block_cipher);
+        let ciphertext = self.crypt(&block_cipher, &j0, plaintext.as_ref())?;
+        let tag = self.compute_tag(&block_cipher, &j0, aad.as_ref(), &ciphertext);
+        Ok((ciphertext, tag))
+    }
+
+    pub fn decrypt_detached<
+        A: ?Sized + AsRef<[u8]>,
+        T: ?Sized + AsRef<[u8]>,
+        G: ?Sized + AsRef<[u8]>,
+    >(
+        &self,
+        aad: &A,
+        ciphertext: &T,
+        tag: &G,
+    ) -> Result<Vec<u8>, AesError> {
+        let ciphertext = ciphertext.as_ref();
+        let tag = tag.as_ref();
+        if tag.len() != self.tag_size {
+            return Err(AesError::InvalidTagSize(tag.len()));
+        }
+        let block_cipher = AesEcbBlockCipher::new(self.key);
+        let j0 = self.pre_counter_block(&block_cipher);
+        let challenge = self.compute_tag(&block_cipher, &j0, aad.as_ref(), ciphertext);
+        if !ct_eq(&challenge, tag) {
+            return Err(AesError::AuthenticationFailed);
+        }
+        self.crypt(&block_cipher, &j0, ciphertext)
+    }
+
+    // Returns the ciphertext with the tag appended.
+    pub fn encrypt<A: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        aad: &A,
+        plaintext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let (mut ciphertext, tag) = self.encrypt_detached(aad, plaintext)?;
+        ciphertext.extend(tag);
+        Ok(ciphertext)
+    }
+
+    // Expects the ciphertext with the tag appended.
+    pub fn decrypt<A: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        aad: &A,
+        ciphertext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let ciphertext = ciphertext.as_ref();
+        if ciphertext.len() < self.tag_size {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - self.tag_size);
+        self.decrypt_detached(aad, ciphertext, tag)
+    }
+}
+
+pub fn decrypt<
+    K: ?Sized + AsRef<[u8]>,
+    IV: ?Sized + AsRef<[u8]>,
+    A: ?Sized + AsRef<[u8]>,
+    T: ?Sized + AsRef<[u8]>,
+>(
+    key: &K,
+    iv: &IV,
+    aad: &A,
+    ciphertext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesGcmIv::try_copy_from_slice(iv)?;
+    let cipher = AesGcmCipher::new(&key, &iv);
+    cipher.decrypt(aad, ciphertext)
+}
+
+pub fn encrypt<
+    K: ?Sized + AsRef<[u8]>,
+    IV: ?Sized + AsRef<[u8]>,
+    A: ?Sized + AsRef<[u8]>,
+    T: ?Sized + AsRef<[u8]>,
+>(
+    key: &K,
+    iv: &IV,
+    aad: &A,
+    plaintext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesGcmIv::try_copy_from_slice(iv)?;
+    let cipher = AesGcmCipher::new(&key, &iv);
+    cipher.encrypt(aad, plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::AesError;
+    use crate::gcm::{AesGcmCipher, AesGcmIv};
+    use crate::key::AesKey;
+
+    // (key, iv, plaintext, aad, ciphertext, tag) from the GCM specification.
+    const TEST_VECTORS: [(&str, &str, &str, &str, &str, &str); 9] = [
+        (
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "",
+            "",
+            "58e2fccefa7e3061367f1d57a4e7455a",
+        ),
+        (
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "00000000000000000000000000000000",
+            "",
+            "0388dace60b6a392f328c2b971b2fe78",
+            "ab6e47d42cec13bdf53a67b21257bddf",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbaddecaf888",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+            "",
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985",
+            "4d5c2af327cd64a62cf35abd2ba6fab4",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbaddecaf888",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+            "5bc94fbc3221a5db94fae95ae7121a47",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbad",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c742373806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+            "3612d2e79e3b0785561be14aaca2fccb",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308",
+            "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca701e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5",
+            "619cc5aefffe0bfa462af43c1699d050",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c",
+            "cafebabefacedbaddecaf888",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "3980ca0b3c00e841eb06fac4872a2757859e1ceaa6efd984628593b40ca1e19c7d773d00c144c525ac619d18c84a3f4718e2448b2fe324d9ccda2710",
+            "2519498e80f1478f37ba55bd6d27618c",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "",
+            "",
+            "530f8afbc74536b9a963b4f1c4cb738b",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbaddecaf888",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662",
+            "76fc6ece0f4e1768cddf8853bb2d551b",
+        ),
+    ];
+
+    #[test]
+    fn gcm_specification_test_vectors() {
+        for (key, iv, plaintext, aad, ciphertext, tag) in TEST_VECTORS.iter() {
+            let key = AesKey::try_copy_from_slice(&hex::decode(key).unwrap()).unwrap();
+            let iv = AesGcmIv::try_copy_from_slice(&hex::decode(iv).unwrap()).unwrap();
+            let plaintext: Vec<u8> = hex::decode(plaintext).unwrap();
+            let aad: Vec<u8> = hex::decode(aad).unwrap();
+            let ciphertext: Vec<u8> = hex::decode(ciphertext).unwrap();
+            let tag: Vec<u8> = hex::decode(tag).unwrap();
+            let cipher = AesGcmCipher::new(&key, &iv);
+            let (challenge, challenge_tag) = cipher.encrypt_detached(&aad, &plaintext).unwrap();
+            assert_eq!(ciphertext, challenge);
+            assert_eq!(tag, challenge_tag);
+            let challenge = cipher.decrypt_detached(&aad, &ciphertext, &tag).unwrap();
+            assert_eq!(plaintext, challenge);
+        }
+    }
+
+    #[test]
+    fn gcm_rejects_invalid_tag_sizes() {
+        let key = AesKey::aes_128_key([0_u8; 16]);
+        let iv = AesGcmIv::aes_gcm_iv([0_u8; 12]);
+        assert!(matches!(
+            AesGcmCipher::with_tag_size(&key, &iv, 3),
+            Err(AesError::InvalidTagSize(3))
+        ));
+        assert!(matches!(
+            AesGcmCipher::with_tag_size(&key, &iv, 17),
+            Err(AesError::InvalidTagSize(17))
+        ));
+    }
+
+    #[quickcheck]
+    fn encrypt_and_decrypt_is_identity(
+        key: AesKey,
+        iv: AesGcmIv,
+        tag_size: usize,
+        aad: Vec<u8>,
+        plaintext: Vec<u8>,
+    ) -> bool {
+        let cipher = AesGcmCipher::with_tag_size(&key, &iv, 4 + tag_size % 13).unwrap();
+        let ciphertext: Vec<u8> = cipher.encrypt(&aad, &plaintext).unwrap();
+        let challenge: Vec<u8> = cipher.decrypt(&aad, &ciphertext).unwrap();
+        plaintext == challenge
+    }
+
+    #[quickcheck]
+    fn tampering_fails_authentication(
+        key: AesKey,
+        iv: AesGcmIv,
+        aad: Vec<u8>,
+        plaintext: Vec<u8>,
+        offset: usize,
+        flip: u8,
+    ) -> bool {
+        let cipher = AesGcmCipher::new(&key, &iv);
+        let mut ciphertext: Vec<u8> = cipher.encrypt(&aad, &plaintext).unwrap();
+        let offset = offset % ciphertext.len();
+        ciphertext[offset] ^= flip | 1;
+        matches!(
+            cipher.decrypt(&aad, &ciphertext),
+            Err(AesError::AuthenticationFailed)
+        )
+    }
+}
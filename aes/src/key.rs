@@ -1,9 +1,11 @@
 use quickcheck::{single_shrinker, Arbitrary, Gen};
 use rand::prelude::*;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use crate::error::AesError;
 
-#[derive(Clone, Debug)]
+// The key bytes are wiped on drop and never printed by `Debug`.
+#[derive(Clone)]
 pub enum AesKey {
     Aes128Key([u8; 16]),
     Aes192Key([u8; 24]),
@@ -62,6 +64,34 @@ impl AesKey {
     }
 }
 
+impl std::fmt::Debug for AesKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AesKey::Aes128Key(_) => write!(f, "Aes128Key(..)"),
+            AesKey::Aes192Key(_) => write!(f, "Aes192Key(..)"),
+            AesKey::Aes256Key(_) => write!(f, "Aes256Key(..)"),
+        }
+    }
+}
+
+impl Zeroize for AesKey {
+    fn zeroize(&mut self) {
+        match self {
+            AesKey::Aes128Key(key) => key.zeroize(),
+            AesKey::Aes192Key(key) => key.zeroize(),
+            AesKey::Aes256Key(key) => key.zeroize(),
+        }
+    }
+}
+
+impl Drop for AesKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for AesKey {}
+
 impl Distribution<AesKey> for rand::distributions::Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AesKey {
         match rng.gen_range(0..3) {
@@ -164,3 +194,22 @@ impl Arbitrary for AesKey {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[quickcheck]
+    fn debug_does_not_leak_key(key: AesKey) -> bool {
+        let debug = format!("{:?}", key);
+        debug.ends_with("Key(..)") && !debug.contains(&format!("{:?}", key.as_slice()))
+    }
+
+    #[quickcheck]
+    fn zeroize_clears_key(key: AesKey) -> bool {
+        let mut key = key;
+        let len = key.as_slice().len();
+        key.zeroize();
+        key.as_slice() == &vec![0_u8; len][..]
+    }
+}
@@ -0,0 +1,327 @@
+// See: https://tools.ietf.org/html/rfc3394
+// See: https://tools.ietf.org/html/rfc5649
+
+use xor::ct::ct_eq;
+
+use crate::ecb::AesEcbBlockCipher;
+use crate::error::AesError;
+use crate::key::AesKey;
+
+// RFC 3394 section 2.2.3.1.
+pub const DEFAULT_IV: [u8; 8] = [0xa6; 8];
+// RFC 5649 section 3, followed by the 32-bit message length indicator.
+pub const ALTERNATIVE_IV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+pub struct AesKwCipher<'k> {
+    block_cipher: AesEcbBlockCipher,
+    key: std::marker::PhantomData<&'k AesKey>,
+}
+
+impl<'k> AesKwCipher<'k> {
+    pub fn new(kek: &'k AesKey) -> AesKwCipher<'k> {
+        AesKwCipher {
+            block_cipher: AesEcbBlockCipher::new(kek),
+            key: std::marker::PhantomData,
+        }
+    }
+
+    // RFC 3394: the key data must be at least two 64-bit blocks.
+    pub fn wrap<T: ?Sized + AsRef<[u8]>>(&self, key_data: &T) -> Result<Vec<u8>, AesError> {
+        let key_data = key_data.as_ref();
+        if key_data.len() < 16 || key_data.len() % 8 != 0 {
+            return Err(AesError::InvalidKeyDataSize(key_data.len()));
+        }
+        Ok(self.wrap_with_iv(DEFAULT_IV, key_data))
+    }
+
+    pub fn unwrap<T: ?Sized + AsRef<[u8]>>(&self, wrapped: &T) -> Result<Vec<u8>, AesError> {
+        let wrapped = wrapped.as_ref();
+        if wrapped.len() < 24 || wrapped.len() % 8 != 0 {
+            return Err(AesError::InvalidKeyDataSize(wrapped.len()));
+        }
+        let (iv, key_data) = self.unwrap_with_iv(wrapped);
+        if !ct_eq(&iv, &DEFAULT_IV) {
+            return Err(AesError::KeyUnwrapFailed);
+        }
+        Ok(key_data)
+    }
+
+    // RFC 5649: key data of any length from 1 byte up to 2^32 - 1 bytes.
+    pub fn wrap_with_padding<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        key_data: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let key_data = key_data.as_ref();
+        if key_data.is_empty() || key_data.len() > u32::MAX as usize {
+            return Err(AesError::InvalidKeyDataSize(key_data.len()));
+        }
+        let mut iv = [0_u8; 8];
+        iv[..4].copy_from_slice(&ALTERNATIVE_IV_PREFIX);
+        iv[4..].copy_from_slice(&(key_data.len() as u32).to_be_bytes());
+        let mut padded = key_data.to_vec();
+        padded.resize(key_data.len().div_ceil(8) * 8, 0);
+        if padded.len() == 8 {
+            // A single padded block is encrypted directly as `AIV || P`.
+            let mut block = [0_u8; 16];
+            block[..8].copy_from_slice(&iv);
+            block[8..].copy_from_slice(&padded);
+            self.block_cipher.encrypt_blocks_mut(&mut block);
+            Ok(block.to_vec())
+        } else {
+            Ok(self.wrap_with_iv(iv, &padded))
+        }
+    }
+
+    pub fn unwrap_with_padding<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        wrapped: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let wrapped = wrapped.as_ref();
+        if wrapped.len() < 16 || wrapped.len() % 8 != 0 {
+            return Err(AesError::InvalidKeyDataSize(wrapped.len()));
+        }
+        let (iv, mut padded) = if wrapped.len() == 16 {
+            let mut block = [0_u8; 16];
+            block.copy_from_slice(wrapped);
+            self.block_cipher.decrypt_blocks_mut(&mut block);
+            let mut iv = [0_u8; 8];
+            iv.copy_from_slice(&block[..8]);
+            (iv, block[8..].to_vec())
+        } else {
+            self.unwrap_with_iv(wrapped)
+        };
+        // Every check is folded together so that a failure does not reveal
+        // which part of the integrity check was wrong.
+        let length = u32::from_be_bytes([iv[4], iv[5], iv[6], iv[7]]) as usize;
+        let length_ok = length <= padded.len() && length + 8 > padded.len();
+        let padding_ok = length_ok && ct_eq(&padded[length..], &[0_u8; 8][..padded.len() - length]);
+        if !(ct_eq(&iv[..4], &ALTERNATIVE_IV_PREFIX) & length_ok & padding_ok) {
+            padded.iter_mut().for_each(|b| *b = 0);
+            return Err(AesError::KeyUnwrapFailed);
+        }
+        padded.truncate(length);
+        Ok(padded)
+    }
+
+    // Wraps the raw bytes of `key` with RFC 3394.
+    pub fn wrap_key(&self, key: &AesKey) -> Result<Vec<u8>, AesError> {
+        self.wrap(key.as_slice())
+    }
+
+    // The unwrapped length selects the 128, 192, or 256-bit variant.
+    pub fn unwrap_key<T: ?Sized + AsRef<[u8]>>(&self, wrapped: &T) -> Result<AesKey, AesError> {
+        let mut key_data = self.unwrap(wrapped)?;
+        let key = AesKey::try_copy_from_slice(&key_data);
+        key_data.iter_mut().for_each(|b| *b = 0);
+        key.map_err(|_| AesError::KeyUnwrapFailed)
+    }
+
+    // The wrapping function W from RFC 3394 section 2.2.1, with `n` >= 2.
+    fn wrap_with_iv(&self, iv: [u8; 8], key_data: &[u8]) -> Vec<u8> {
+        let n = key_data.len() / 8;
+        let mut output = vec![0_u8; 8];
+        output.extend_from_slice(key_data);
+        let mut a = iv;
+        let mut block = [0_u8; 16];
+        for j in 0..6 {
+            for i in 1..=n {
+                block[..8].copy_from_slice(&a);
+                block[8..].copy_from_slice(&output[8 * i..8 * (i + 1)]);
+                self.block_cipher.encrypt_blocks_mut(&mut block);
+                let t = ((n * j + i) as u64).to_be_bytes();
+                a.iter_mut()
+                    .zip(block[..8].iter().zip(t.iter()))
+                    .for_each(|(a, (b, t))| *a = b ^ t);
+                output[8 * i..8 * (i + 1)].copy_from_slice(&block[8..]);
+            }
+        }
+        output[..8].copy_from_slice(&a);
+        output
+    }
+
+    // The unwrapping function W^-1, returning the recovered IV and key data.
+    fn unwrap_with_iv(&self, wrapped: &[u8]) -> ([u8; 8], Vec<u8>) {
+        let n = wrapped.len() / 8 - 1;
+        let mut a = [0_u8; 8];
+        a.copy_from_slice(&wrapped[..8]);
+        let mut key_data = wrapped[8..].to_vec();
+        let mut block = [0_u8; 16];
+        for j in (0..6).rev() {
+            for i in (1..=n).rev() {
+                let t = ((n * j + i) as u64).to_be_bytes();
+                block[..8]
+                    .iter_mut()
+                    .zip(a.iter().zip(t.iter()))
+                    .for_each(|(b, (a, t))| *b = a ^ t);
+                block[8..].copy_from_slice(&key_data[8 * (i - 1)..8 * i]);
+                self.block_cipher.decrypt_blocks_mut(&mut block);
+                a.copy_from_slice(&block[..8]);
+                key_data[8 * (i - 1)..8 * i].copy_from_slice(&block[8..]);
+            }
+        }
+        (a, key_data)
+    }
+}
+
+pub fn wrap<K: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    kek: &K,
+    key_data: &T,
+) -> Result<Vec<u8>, AesError> {
+    let kek = AesKey::try_copy_from_slice(kek)?;
+    let cipher = AesKwCipher::new(&kek);
+    cipher.wrap(key_data)
+}
+
+pub fn unwrap<K: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    kek: &K,
+    wrapped: &T,
+) -> Result<Vec<u8>, AesError> {
+    let kek = AesKey::try_copy_from_slice(kek)?;
+    let cipher = AesKwCipher::new(&kek);
+    cipher.unwrap(wrapped)
+}
+
+pub fn wrap_with_padding<K: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    kek: &K,
+    key_data: &T,
+) -> Result<Vec<u8>, AesError> {
+    let kek = AesKey::try_copy_from_slice(kek)?;
+    let cipher = AesKwCipher::new(&kek);
+    cipher.wrap_with_padding(key_data)
+}
+
+pub fn unwrap_with_padding<K: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    kek: &K,
+    wrapped: &T,
+) -> Result<Vec<u8>, AesError> {
+    let kek = AesKey::try_copy_from_slice(kek)?;
+    let cipher = AesKwCipher::new(&kek);
+    cipher.unwrap_with_padding(wrapped)
+}
+
+pub fn wrap_key(kek: &AesKey, key: &AesKey) -> Result<Vec<u8>, AesError> {
+    AesKwCipher::new(kek).wrap_key(key)
+}
+
+pub fn unwrap_key<T: ?Sized + AsRef<[u8]>>(kek: &AesKey, wrapped: &T) -> Result<AesKey, AesError> {
+    AesKwCipher::new(kek).unwrap_key(wrapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::AesError;
+    use crate::key::AesKey;
+    use crate::kw::AesKwCipher;
+
+    // RFC 3394 sections 4.1 through 4.6.
+    #[test]
+    fn rfc_3394_vectors() {
+        let kek = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+        let key_data = "00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f";
+        for (kek_size, key_data_size, wrapped) in [
+            (16, 16, "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5"),
+            (24, 16, "96778b25ae6ca435f92b5b97c050aed2468ab8a17ad84e5d"),
+            (32, 16, "64e8c3f9ce0f5ba263e9777905818a2a93c8191e7d6e8ae7"),
+            (
+                24,
+                24,
+                "031d33264e15d33268f24ec260743edce1c6c7ddee725a936ba814915c6762d2",
+            ),
+            (
+                32,
+                24,
+                "a8f9bc1612c68b3ff6e6f4fbe30e71e4769c8b80a32cb8958cd5d17d6b254da1",
+            ),
+            (
+                32,
+                32,
+                "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21",
+            ),
+        ] {
+            let kek = &hex::decode(kek).unwrap()[..kek_size];
+            let key_data = &hex::decode(key_data).unwrap()[..key_data_size];
+            let wrapped = hex::decode(wrapped).unwrap();
+            assert_eq!(wrapped, crate::kw::wrap(kek, key_data).unwrap());
+            assert_eq!(key_data, &crate::kw::unwrap(kek, &wrapped).unwrap()[..]);
+        }
+    }
+
+    // RFC 5649 section 6.
+    #[test]
+    fn rfc_5649_vectors() {
+        let kek = hex::decode("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").unwrap();
+        for (key_data, wrapped) in [
+            (
+                "c37b7e6492584340bed12207808941155068f738",
+                "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+            ),
+            ("466f7250617369", "afbeb0f07dfbf5419200f2ccb50bb24f"),
+        ] {
+            let key_data = hex::decode(key_data).unwrap();
+            let wrapped = hex::decode(wrapped).unwrap();
+            assert_eq!(
+                wrapped,
+                crate::kw::wrap_with_padding(&kek, &key_data).unwrap()
+            );
+            assert_eq!(
+                key_data,
+                crate::kw::unwrap_with_padding(&kek, &wrapped).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        let kek = AesKey::aes_128_key([0_u8; 16]);
+        let cipher = AesKwCipher::new(&kek);
+        for size in [0, 8, 17] {
+            assert_eq!(
+                Err(AesError::InvalidKeyDataSize(size)),
+                cipher.wrap(&vec![0_u8; size])
+            );
+        }
+        assert_eq!(
+            Err(AesError::InvalidKeyDataSize(0)),
+            cipher.wrap_with_padding(&[])
+        );
+        assert_eq!(
+            Err(AesError::InvalidKeyDataSize(16)),
+            cipher.unwrap(&[0_u8; 16])
+        );
+    }
+
+    #[quickcheck]
+    fn wrap_key_preserves_variant(kek: AesKey, key: AesKey) -> bool {
+        let cipher = AesKwCipher::new(&kek);
+        let wrapped: Vec<u8> = cipher.wrap_key(&key).unwrap();
+        let unwrapped = cipher.unwrap_key(&wrapped).unwrap();
+        std::mem::discriminant(&key) == std::mem::discriminant(&unwrapped)
+            && key.as_slice() == unwrapped.as_slice()
+    }
+
+    #[quickcheck]
+    fn unwrap_with_padding_inverts_wrap(kek: AesKey, key_data: Vec<u8>) -> bool {
+        let cipher = AesKwCipher::new(&kek);
+        match cipher.wrap_with_padding(&key_data) {
+            Ok(wrapped) => key_data == cipher.unwrap_with_padding(&wrapped).unwrap(),
+            Err(_) => key_data.is_empty(),
+        }
+    }
+
+    #[quickcheck]
+    fn tampering_is_detected(kek: AesKey, key: AesKey, index: usize, padded: bool) -> bool {
+        let cipher = AesKwCipher::new(&kek);
+        let mut wrapped: Vec<u8> = if padded {
+            cipher.wrap_with_padding(key.as_slice()).unwrap()
+        } else {
+            cipher.wrap_key(&key).unwrap()
+        };
+        let index = index % wrapped.len();
+        wrapped[index] ^= 0x01;
+        if padded {
+            cipher.unwrap_with_padding(&wrapped) == Err(AesError::KeyUnwrapFailed)
+        } else {
+            cipher.unwrap(&wrapped) == Err(AesError::KeyUnwrapFailed)
+        }
+    }
+}
@@ -11,8 +11,25 @@ extern crate quickcheck;
 #[macro_use(quickcheck)]
 extern crate quickcheck_macros;
 
+pub mod block_cipher;
 pub mod cbc;
+pub mod ccm;
+pub mod cfb128;
+pub mod cfb8;
+pub mod cmac;
 pub mod ctr;
+pub mod ctr_stream;
 pub mod ecb;
+pub mod envelope;
 pub mod error;
+pub mod gcm;
 pub mod key;
+pub mod kw;
+pub mod native;
+pub mod ofb;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod pcbc;
+pub mod siv;
+pub mod toy_spn;
+pub mod xts;
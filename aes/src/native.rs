@@ -0,0 +1,445 @@
+// See: https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.197-upd1.pdf
+//
+// A straightforward (table-based, not constant-time) AES whose individual
+// steps are exposed for cryptanalysis. The state is the FIPS-197 column-major
+// byte order, which is the same order as the input and output blocks.
+
+use zeroize::Zeroize;
+
+use crate::block_cipher::BlockCipher;
+use crate::error::AesError;
+use crate::key::AesKey;
+
+pub type AesState = [u8; 16];
+
+pub const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+pub const INV_SBOX: [u8; 256] = [
+    0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
+    0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
+    0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
+    0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
+    0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
+    0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
+    0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
+    0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
+    0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
+    0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
+    0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
+    0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
+    0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
+    0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
+    0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
+    0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d,
+];
+
+// Multiplication by x in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1.
+pub fn xtime(a: u8) -> u8 {
+    (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0x00 }
+}
+
+pub fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut p: u8 = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            p ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    p
+}
+
+pub fn sub_bytes(state: &mut AesState) {
+    state.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+}
+
+pub fn inv_sub_bytes(state: &mut AesState) {
+    state.iter_mut().for_each(|b| *b = INV_SBOX[*b as usize]);
+}
+
+// Row `r` is rotated left by `r` positions.
+pub fn shift_rows(state: &mut AesState) {
+    let input = *state;
+    for c in 0..4 {
+        for r in 0..4 {
+            state[r + 4 * c] = input[r + 4 * ((c + r) % 4)];
+        }
+    }
+}
+
+pub fn inv_shift_rows(state: &mut AesState) {
+    let input = *state;
+    for c in 0..4 {
+        for r in 0..4 {
+            state[r + 4 * ((c + r) % 4)] = input[r + 4 * c];
+        }
+    }
+}
+
+pub fn mix_columns(state: &mut AesState) {
+    for column in state.chunks_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+        column[0] = gmul(a0, 2) ^ gmul(a1, 3) ^ a2 ^ a3;
+        column[1] = a0 ^ gmul(a1, 2) ^ gmul(a2, 3) ^ a3;
+        column[2] = a0 ^ a1 ^ gmul(a2, 2) ^ gmul(a3, 3);
+        column[3] = gmul(a0, 3) ^ a1 ^ a2 ^ gmul(a3, 2);
+    }
+}
+
+pub fn inv_mix_columns(state: &mut AesState) {
+    for column in state.chunks_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+        column[0] = gmul(a0, 14) ^ gmul(a1, 11) ^ gmul(a2, 13) ^ gmul(a3, 9);
+        column[1] = gmul(a0, 9) ^ gmul(a1, 14) ^ gmul(a2, 11) ^ gmul(a3, 13);
+        column[2] = gmul(a0, 13) ^ gmul(a1, 9) ^ gmul(a2, 14) ^ gmul(a3, 11);
+        column[3] = gmul(a0, 11) ^ gmul(a1, 13) ^ gmul(a2, 9) ^ gmul(a3, 14);
+    }
+}
+
+pub fn add_round_key(state: &mut AesState, round_key: &AesState) {
+    state
+        .iter_mut()
+        .zip(round_key.iter())
+        .for_each(|(b, k)| *b ^= k);
+}
+
+// The standard number of rounds for the key size.
+pub fn rounds(key: &AesKey) -> usize {
+    match key {
+        AesKey::Aes128Key(_) => 10,
+        AesKey::Aes192Key(_) => 12,
+        AesKey::Aes256Key(_) => 14,
+    }
+}
+
+// Expands `key` into `rounds + 1` round keys. Asking for more rounds than the
+// standard simply keeps running the key schedule, with the round constant
+// doubled by `xtime` each time as before (0x1b, 0x36, 0x6c, 0xd8, ...).
+pub fn expand_key(key: &AesKey, rounds: usize) -> Vec<AesState> {
+    let key = key.as_slice();
+    let nk = key.len() / 4;
+    let total = 4 * (rounds + 1);
+    let mut words: Vec<[u8; 4]> = Vec::with_capacity(std::cmp::max(total, nk));
+    for word in key.chunks(4) {
+        words.push([word[0], word[1], word[2], word[3]]);
+    }
+    let mut rcon: u8 = 0x01;
+    for i in nk..total {
+        let mut temp = words[i - 1];
+        if i % nk == 0 {
+            temp.rotate_left(1);
+            temp.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+            temp[0] ^= rcon;
+            rcon = xtime(rcon);
+        } else if nk > 6 && i % nk == 4 {
+            temp.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+        }
+        let previous = words[i - nk];
+        words.push([
+            previous[0] ^ temp[0],
+            previous[1] ^ temp[1],
+            previous[2] ^ temp[2],
+            previous[3] ^ temp[3],
+        ]);
+    }
+    words
+        .chunks(4)
+        .take(rounds + 1)
+        .map(|round_words| {
+            let mut round_key: AesState = [0_u8; 16];
+            for (bytes, word) in round_key.chunks_mut(4).zip(round_words.iter()) {
+                bytes.copy_from_slice(word);
+            }
+            round_key
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct AesNativeBlockCipher {
+    round_keys: Vec<AesState>,
+}
+
+impl std::fmt::Debug for AesNativeBlockCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AesNativeBlockCipher")
+            .field("rounds", &self.rounds())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for AesNativeBlockCipher {
+    fn drop(&mut self) {
+        self.round_keys.zeroize();
+    }
+}
+
+impl AesNativeBlockCipher {
+    pub fn new(key: &AesKey) -> Self {
+        Self {
+            round_keys: expand_key(key, rounds(key)),
+        }
+    }
+
+    // Round-reduced AES. As in the full cipher, the final round omits MixColumns.
+    pub fn with_rounds(key: &AesKey, rounds: usize) -> Result<Self, AesError> {
+        if rounds == 0 {
+            return Err(AesError::InvalidRoundCount(rounds));
+        }
+        Ok(Self {
+            round_keys: expand_key(key, rounds),
+        })
+    }
+
+    pub fn from_round_keys(round_keys: Vec<AesState>) -> Result<Self, AesError> {
+        if round_keys.len() < 2 {
+            return Err(AesError::InvalidRoundCount(
+                round_keys.len().saturating_sub(1),
+            ));
+        }
+        Ok(Self { round_keys })
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    pub fn round_keys(&self) -> &[AesState] {
+        &self.round_keys
+    }
+
+    pub fn encrypt_state(&self, state: &mut AesState) {
+        let rounds = self.rounds();
+        add_round_key(state, &self.round_keys[0]);
+        for round_key in self.round_keys[1..rounds].iter() {
+            sub_bytes(state);
+            shift_rows(state);
+            mix_columns(state);
+            add_round_key(state, round_key);
+        }
+        sub_bytes(state);
+        shift_rows(state);
+        add_round_key(state, &self.round_keys[rounds]);
+    }
+
+    pub fn decrypt_state(&self, state: &mut AesState) {
+        let rounds = self.rounds();
+        add_round_key(state, &self.round_keys[rounds]);
+        inv_shift_rows(state);
+        inv_sub_bytes(state);
+        for round_key in self.round_keys[1..rounds].iter().rev() {
+            add_round_key(state, round_key);
+            inv_mix_columns(state);
+            inv_shift_rows(state);
+            inv_sub_bytes(state);
+        }
+        add_round_key(state, &self.round_keys[0]);
+    }
+
+    pub fn decrypt_block<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        ciphertext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let ciphertext = ciphertext.as_ref();
+        if ciphertext.len() == 16 {
+            let mut state: AesState = [0_u8; 16];
+            state.copy_from_slice(ciphertext);
+            self.decrypt_state(&mut state);
+            Ok(state.to_vec())
+        } else {
+            Err(AesError::InvalidBlockSize(ciphertext.len()))
+        }
+    }
+
+    pub fn encrypt_block<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        plaintext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let plaintext = plaintext.as_ref();
+        if plaintext.len() == 16 {
+            let mut state: AesState = [0_u8; 16];
+            state.copy_from_slice(plaintext);
+            self.encrypt_state(&mut state);
+            Ok(state.to_vec())
+        } else {
+            Err(AesError::InvalidBlockSize(plaintext.len()))
+        }
+    }
+}
+
+impl BlockCipher for AesNativeBlockCipher {
+    type Key = AesKey;
+    type Error = AesError;
+
+    fn block_size() -> usize {
+        16
+    }
+
+    fn from_key(key: &AesKey) -> Self {
+        Self::new(key)
+    }
+
+    fn key_from_slice(bytes: &[u8]) -> Result<AesKey, AesError> {
+        AesKey::try_copy_from_slice(bytes)
+    }
+
+    fn encrypt_block_mut(&self, block: &mut [u8]) {
+        let mut state: AesState = [0_u8; 16];
+        state.copy_from_slice(block);
+        self.encrypt_state(&mut state);
+        block.copy_from_slice(&state);
+    }
+
+    fn decrypt_block_mut(&self, block: &mut [u8]) {
+        let mut state: AesState = [0_u8; 16];
+        state.copy_from_slice(block);
+        self.decrypt_state(&mut state);
+        block.copy_from_slice(&state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cbc::{AesCbcCipher, AesCbcIv, CbcCipher};
+    use crate::ecb::AesEcbBlockCipher;
+    use crate::key::AesKey;
+    use crate::native::*;
+
+    #[test]
+    fn fips_197_cipher_examples() {
+        let plaintext = hex::decode("00112233445566778899aabbccddeeff").unwrap();
+        for (key, ciphertext) in [
+            (
+                "000102030405060708090a0b0c0d0e0f",
+                "69c4e0d86a7b0430d8cdb78070b4c55a",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f1011121314151617",
+                "dda97ca4864cdfe06eaf70a0ec0d7191",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "8ea2b7ca516745bfeafc49904b496089",
+            ),
+        ] {
+            let key = AesKey::try_copy_from_slice(&hex::decode(key).unwrap()).unwrap();
+            let ciphertext = hex::decode(ciphertext).unwrap();
+            let cipher = AesNativeBlockCipher::new(&key);
+            assert_eq!(ciphertext, cipher.encrypt_block(&plaintext).unwrap());
+            assert_eq!(plaintext, cipher.decrypt_block(&ciphertext).unwrap());
+        }
+    }
+
+    #[test]
+    fn fips_197_key_expansion_example() {
+        let key =
+            AesKey::try_copy_from_slice(&hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap())
+                .unwrap();
+        let round_keys = expand_key(&key, 10);
+        assert_eq!(11, round_keys.len());
+        assert_eq!(
+            hex::decode("d014f9a8c9ee2589e13f0cc8b6630ca6").unwrap(),
+            round_keys[10].to_vec()
+        );
+    }
+
+    // The eleventh AES-128 round key uses the round constant after 0x36, which
+    // is 0x6c.
+    #[test]
+    fn extended_key_schedule_keeps_doubling_the_round_constant() {
+        let key =
+            AesKey::try_copy_from_slice(&hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap())
+                .unwrap();
+        let round_keys = expand_key(&key, 11);
+        let mut temp = [
+            round_keys[10][13],
+            round_keys[10][14],
+            round_keys[10][15],
+            round_keys[10][12],
+        ];
+        temp.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+        temp[0] ^= 0x6c;
+        for j in 0..4 {
+            assert_eq!(round_keys[10][j] ^ temp[j], round_keys[11][j]);
+        }
+    }
+
+    #[quickcheck]
+    fn native_matches_extern_aes(key: AesKey, block: u128) -> bool {
+        let block = block.to_be_bytes();
+        let native = AesNativeBlockCipher::new(&key);
+        let aesimpl = AesEcbBlockCipher::new(&key);
+        let ciphertext = native.encrypt_block(&block).unwrap();
+        ciphertext == aesimpl.encrypt_block(&block).unwrap()
+            && native.decrypt_block(&ciphertext).unwrap() == block.to_vec()
+    }
+
+    #[quickcheck]
+    fn native_cbc_matches_extern_aes_cbc(key: AesKey, iv: AesCbcIv, blocks: Vec<u128>) -> bool {
+        let plaintext: Vec<u8> = blocks.iter().flat_map(|b| b.to_be_bytes()).collect();
+        let native = CbcCipher::<AesNativeBlockCipher>::try_new(&key, &iv).unwrap();
+        let aesimpl = AesCbcCipher::new(&key, &iv);
+        let ciphertext = native.encrypt(&plaintext).unwrap();
+        ciphertext == aesimpl.encrypt(&plaintext).unwrap()
+            && native.decrypt(&ciphertext).unwrap() == plaintext
+    }
+
+    #[quickcheck]
+    fn round_reduced_decrypt_inverts_encrypt(key: AesKey, block: u128, rounds: u8) -> bool {
+        let rounds = 1 + (rounds as usize % 16);
+        let cipher = AesNativeBlockCipher::with_rounds(&key, rounds).unwrap();
+        let mut state = block.to_be_bytes();
+        cipher.encrypt_state(&mut state);
+        cipher.decrypt_state(&mut state);
+        state == block.to_be_bytes()
+    }
+
+    #[quickcheck]
+    fn round_steps_are_invertible(block: u128, round_key: u128) -> bool {
+        let block = block.to_be_bytes();
+        let round_key = round_key.to_be_bytes();
+        let mut state = block;
+        sub_bytes(&mut state);
+        shift_rows(&mut state);
+        mix_columns(&mut state);
+        add_round_key(&mut state, &round_key);
+        add_round_key(&mut state, &round_key);
+        inv_mix_columns(&mut state);
+        inv_shift_rows(&mut state);
+        inv_sub_bytes(&mut state);
+        state == block
+    }
+
+    // The basis of the Square attack: encrypting a set of 256 plaintexts that
+    // differ only in one byte through 3 rounds leaves every byte balanced.
+    #[quickcheck]
+    fn three_rounds_preserve_the_integral_property(key: AesKey, constant: u8, active: u8) -> bool {
+        let cipher = AesNativeBlockCipher::with_rounds(&key, 3).unwrap();
+        let mut sum: AesState = [0_u8; 16];
+        for value in 0_u8..=255_u8 {
+            let mut state: AesState = [constant; 16];
+            state[active as usize % 16] = value;
+            cipher.encrypt_state(&mut state);
+            add_round_key(&mut sum, &state);
+        }
+        sum == [0_u8; 16]
+    }
+}
@@ -0,0 +1,122 @@
+use crate::cbc::AesCbcIv;
+use crate::ecb::AesEcbBlockCipher;
+use crate::error::AesError;
+use crate::key::AesKey;
+
+// Output feedback (OFB). The keystream is the IV encrypted repeatedly, so
+// encryption and decryption are the same operation on data of any length.
+pub struct AesOfbCipher<'k, 'iv> {
+    block_cipher: AesEcbBlockCipher,
+    key: std::marker::PhantomData<&'k AesKey>,
+    iv: &'iv AesCbcIv,
+}
+
+impl<'k, 'iv> AesOfbCipher<'k, 'iv> {
+    pub fn new(key: &'k AesKey, iv: &'iv AesCbcIv) -> AesOfbCipher<'k, 'iv> {
+        AesOfbCipher {
+            block_cipher: AesEcbBlockCipher::new(key),
+            key: std::marker::PhantomData,
+            iv,
+        }
+    }
+
+    pub fn decrypt<T: ?Sized + AsRef<[u8]>>(&self, ciphertext: &T) -> Result<Vec<u8>, AesError> {
+        self.crypt(ciphertext)
+    }
+
+    pub fn encrypt<T: ?Sized + AsRef<[u8]>>(&self, plaintext: &T) -> Result<Vec<u8>, AesError> {
+        self.crypt(plaintext)
+    }
+
+    fn crypt<T: ?Sized + AsRef<[u8]>>(&self, input: &T) -> Result<Vec<u8>, AesError> {
+        let mut output = input.as_ref().to_vec();
+        let mut register = [0u8; 16];
+        register.copy_from_slice(self.iv.as_slice());
+        for segment in output.chunks_mut(16) {
+            self.block_cipher.encrypt_blocks_mut(&mut register);
+            segment
+                .iter_mut()
+                .zip(register.iter())
+                .for_each(|(b, k)| *b ^= k);
+        }
+        Ok(output)
+    }
+}
+
+pub fn decrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    ciphertext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesOfbCipher::new(&key, &iv);
+    cipher.decrypt(ciphertext)
+}
+
+pub fn encrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    plaintext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesOfbCipher::new(&key, &iv);
+    cipher.encrypt(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cbc::AesCbcIv;
+    use crate::key::AesKey;
+    use crate::ofb::AesOfbCipher;
+
+    // NIST SP 800-38A, F.4.1 through F.4.6.
+    #[test]
+    fn nist_sp800_38a_vectors() {
+        let iv = "000102030405060708090a0b0c0d0e0f";
+        let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+        for (key, ciphertext) in [
+            (
+                "2b7e151628aed2a6abf7158809cf4f3c",
+                "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+                 9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e",
+            ),
+            (
+                "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+                "cdc80d6fddf18cab34c25909c99a4174fcc28b8d4c63837c09e81700c1100401\
+                 8d9a9aeac0f6596f559c6d4daf59a5f26d9f200857ca6c3e9cac524bd9acc92a",
+            ),
+            (
+                "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+                "dc7e84bfda79164b7ecd8486985d38604febdc6740d20b3ac88f6ad82a4fb08d\
+                 71ab47a086e86eedf39d1c5bba97c4080126141d67f37be8538f5a8be740e484",
+            ),
+        ] {
+            let plaintext = hex::decode(plaintext).unwrap();
+            let ciphertext = hex::decode(ciphertext).unwrap();
+            let challenge: Vec<u8> = crate::ofb::encrypt(
+                &hex::decode(key).unwrap(),
+                &hex::decode(iv).unwrap(),
+                &plaintext,
+            )
+            .unwrap();
+            assert_eq!(ciphertext, challenge);
+            let challenge: Vec<u8> = crate::ofb::decrypt(
+                &hex::decode(key).unwrap(),
+                &hex::decode(iv).unwrap(),
+                &ciphertext,
+            )
+            .unwrap();
+            assert_eq!(plaintext, challenge);
+        }
+    }
+
+    #[quickcheck]
+    fn encrypt_and_decrypt_is_identity(key: AesKey, iv: AesCbcIv, plaintext: Vec<u8>) -> bool {
+        let cipher = AesOfbCipher::new(&key, &iv);
+        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        plaintext.len() == ciphertext.len() && plaintext == cipher.decrypt(&ciphertext).unwrap()
+    }
+}
@@ -0,0 +1,12 @@
+// Bulk ECB and CTR on the rayon thread pool. Blocks are independent in both
+// modes, so every chunk is processed on its own and the output is
+// byte-identical to the sequential path.
+
+// Large enough that per-chunk overhead is negligible next to the AES work.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+// Rounds `chunk_size` up to a non-zero multiple of `block_size` so that no
+// block straddles two chunks.
+pub(crate) fn chunk_len(chunk_size: usize, block_size: usize) -> usize {
+    chunk_size.max(1).div_ceil(block_size) * block_size
+}
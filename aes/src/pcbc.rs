@@ -0,0 +1,139 @@
+use xor::exor::exor_mut;
+
+use crate::cbc::AesCbcIv;
+use crate::ecb::AesEcbBlockCipher;
+use crate::error::AesError;
+use crate::key::AesKey;
+
+// Propagating cipher block chaining (PCBC). Each block is XOR'd with both the
+// previous plaintext and ciphertext blocks before encryption, so a single
+// corrupted ciphertext block garbles every block that follows it.
+pub struct AesPcbcCipher<'k, 'iv> {
+    block_cipher: AesEcbBlockCipher,
+    key: std::marker::PhantomData<&'k AesKey>,
+    iv: &'iv AesCbcIv,
+}
+
+impl<'k, 'iv> AesPcbcCipher<'k, 'iv> {
+    pub fn new(key: &'k AesKey, iv: &'iv AesCbcIv) -> AesPcbcCipher<'k, 'iv> {
+        AesPcbcCipher {
+            block_cipher: AesEcbBlockCipher::new(key),
+            key: std::marker::PhantomData,
+            iv,
+        }
+    }
+
+    pub fn decrypt<T: ?Sized + AsRef<[u8]>>(&self, ciphertext: &T) -> Result<Vec<u8>, AesError> {
+        let ciphertext = ciphertext.as_ref();
+        match ciphertext.len() {
+            0 => Ok(vec![]),
+            x => {
+                if x % 16 == 0 {
+                    let mut plaintext = ciphertext.to_vec();
+                    self.block_cipher.decrypt_blocks_mut(&mut plaintext);
+                    let mut chain = [0u8; 16];
+                    chain.copy_from_slice(self.iv.as_slice());
+                    for (block, ciphertext_block) in
+                        plaintext.chunks_mut(16).zip(ciphertext.chunks(16))
+                    {
+                        exor_mut(block, &chain).unwrap();
+                        chain.copy_from_slice(block);
+                        exor_mut(&mut chain[..], ciphertext_block).unwrap();
+                    }
+                    Ok(plaintext)
+                } else {
+                    Err(AesError::InvalidBlockSize(x))
+                }
+            }
+        }
+    }
+
+    pub fn encrypt<T: ?Sized + AsRef<[u8]>>(&self, plaintext: &T) -> Result<Vec<u8>, AesError> {
+        let plaintext = plaintext.as_ref();
+        match plaintext.len() {
+            0 => Ok(vec![]),
+            x => {
+                if x % 16 == 0 {
+                    let mut ciphertext = plaintext.to_vec();
+                    let mut chain = [0u8; 16];
+                    chain.copy_from_slice(self.iv.as_slice());
+                    for (block, plaintext_block) in
+                        ciphertext.chunks_mut(16).zip(plaintext.chunks(16))
+                    {
+                        exor_mut(block, &chain).unwrap();
+                        self.block_cipher.encrypt_blocks_mut(block);
+                        chain.copy_from_slice(block);
+                        exor_mut(&mut chain[..], plaintext_block).unwrap();
+                    }
+                    Ok(ciphertext)
+                } else {
+                    Err(AesError::InvalidBlockSize(x))
+                }
+            }
+        }
+    }
+}
+
+pub fn decrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    ciphertext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesPcbcCipher::new(&key, &iv);
+    cipher.decrypt(ciphertext)
+}
+
+pub fn encrypt<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    plaintext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesKey::try_copy_from_slice(key)?;
+    let iv = AesCbcIv::try_copy_from_slice(iv)?;
+    let cipher = AesPcbcCipher::new(&key, &iv);
+    cipher.encrypt(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cbc::AesCbcIv;
+    use crate::ecb::AesEcbBlockCipher;
+    use crate::key::AesKey;
+    use crate::pcbc::AesPcbcCipher;
+
+    #[quickcheck]
+    fn encrypt_matches_manual_chaining(key: AesKey, iv: AesCbcIv, blocks: Vec<u128>) -> bool {
+        let plaintext: Vec<u8> = blocks.iter().flat_map(|b| b.to_be_bytes()).collect();
+        let block_cipher = AesEcbBlockCipher::new(&key);
+        let mut chain: Vec<u8> = iv.to_vec();
+        let mut expected: Vec<u8> = Vec::new();
+        for block in plaintext.chunks(16) {
+            let input: Vec<u8> = xor::exor::exor(&block, &chain).unwrap();
+            let output: Vec<u8> = block_cipher.encrypt_block(&input).unwrap();
+            chain = xor::exor::exor(&block, &output).unwrap();
+            expected.extend_from_slice(&output);
+        }
+        let cipher = AesPcbcCipher::new(&key, &iv);
+        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        expected == ciphertext && plaintext == cipher.decrypt(&ciphertext).unwrap()
+    }
+
+    #[quickcheck]
+    fn encrypt_and_decrypt_is_identity(key: AesKey, iv: AesCbcIv, blocks: Vec<u128>) -> bool {
+        let plaintext: Vec<u8> = blocks.iter().flat_map(|b| b.to_be_bytes()).collect();
+        let cipher = AesPcbcCipher::new(&key, &iv);
+        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        plaintext == cipher.decrypt(&ciphertext).unwrap()
+    }
+
+    #[test]
+    fn rejects_partial_blocks() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let iv = AesCbcIv::aes_cbc_iv([0_u8; 16]);
+        let cipher = AesPcbcCipher::new(&key, &iv);
+        assert!(cipher.encrypt(&[0_u8; 17]).is_err());
+        assert!(cipher.decrypt(&[0_u8; 15]).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+// See: https://tools.ietf.org/html/rfc5297
+
+use quickcheck::{Arbitrary, Gen};
+use rand::prelude::*;
+use xor::ct::ct_eq;
+
+use crate::cmac::{dbl, AesCmac};
+use crate::ctr::{AesCtrIv, AesCtrKeystream, AesCtrMode};
+use crate::error::AesError;
+use crate::key::AesKey;
+
+// S2V takes at most 126 associated data components besides the plaintext.
+pub const MAX_ASSOCIATED_DATA: usize = 126;
+
+// The pair of keys used by SIV: `mac_key` keys S2V and `ctr_key` keys CTR.
+#[derive(Clone, Debug)]
+pub struct AesSivKey {
+    mac_key: AesKey,
+    ctr_key: AesKey,
+}
+
+impl AesSivKey {
+    pub fn new(mac_key: AesKey, ctr_key: AesKey) -> AesSivKey {
+        AesSivKey { mac_key, ctr_key }
+    }
+
+    // Splits a concatenated `mac_key || ctr_key` (32, 48, or 64 bytes).
+    pub fn try_copy_from_slice<K: ?Sized + AsRef<[u8]>>(bytes: &K) -> Result<AesSivKey, AesError> {
+        let bytes = bytes.as_ref();
+        if bytes.len() % 2 != 0 {
+            return Err(AesError::InvalidKeySize(bytes.len()));
+        }
+        let (mac_key, ctr_key) = bytes.split_at(bytes.len() / 2);
+        Ok(Self::new(
+            AesKey::try_copy_from_slice(mac_key)?,
+            AesKey::try_copy_from_slice(ctr_key)?,
+        ))
+    }
+
+    pub fn mac_key(&self) -> &AesKey {
+        &self.mac_key
+    }
+
+    pub fn ctr_key(&self) -> &AesKey {
+        &self.ctr_key
+    }
+}
+
+impl Distribution<AesSivKey> for rand::distributions::Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AesSivKey {
+        AesSivKey::new(rng.gen(), rng.gen())
+    }
+}
+
+impl Arbitrary for AesSivKey {
+    fn arbitrary(g: &mut Gen) -> AesSivKey {
+        AesSivKey::new(AesKey::arbitrary(g), AesKey::arbitrary(g))
+    }
+}
+
+// S2V over the associated data components followed by the plaintext.
+pub fn s2v<A: AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &AesKey,
+    associated_data: &[A],
+    plaintext: &T,
+) -> Result<[u8; 16], AesError> {
+    if associated_data.len() > MAX_ASSOCIATED_DATA {
+        return Err(AesError::TooManyComponents(associated_data.len()));
+    }
+    // One CMAC context, reset for each component, so the subkeys are only
+    // derived once.
+    let mut context = AesCmac::new(key);
+    let mut cmac = |input: &[u8]| {
+        context.reset();
+        context.update(input);
+        context.tag()
+    };
+    let plaintext = plaintext.as_ref();
+    let mut d = cmac(&[0_u8; 16]);
+    for component in associated_data {
+        let mac = cmac(component.as_ref());
+        d = dbl(d);
+        d.iter_mut().zip(mac.iter()).for_each(|(d, m)| *d ^= m);
+    }
+    let t: Vec<u8> = if plaintext.len() >= 16 {
+        // xorend: D is XOR'd into the last 16 bytes of the plaintext.
+        let mut t = plaintext.to_vec();
+        let offset = t.len() - 16;
+        t[offset..]
+            .iter_mut()
+            .zip(d.iter())
+            .for_each(|(t, d)| *t ^= d);
+        t
+    } else {
+        let mut t = dbl(d);
+        t.iter_mut()
+            .zip(plaintext.iter().chain(std::iter::once(&0x80)))
+            .for_each(|(t, p)| *t ^= p);
+        t.to_vec()
+    };
+    Ok(cmac(&t))
+}
+
+pub struct AesSivCipher<'k> {
+    key: &'k AesSivKey,
+}
+
+impl<'k> AesSivCipher<'k> {
+    pub fn new(key: &'k AesSivKey) -> AesSivCipher<'k> {
+        AesSivCipher { key }
+    }
+
+    // The synthetic IV doubles as the initial counter block once bits 31 and
+    // 63 are cleared, which lets 32- and 64-bit counter implementations
+    // increment it without carrying.
+    fn crypt(&self, v: &[u8; 16], input: &[u8]) -> Result<Vec<u8>, AesError> {
+        let mut q = *v;
+        q[8] &= 0x7f;
+        q[12] &= 0x7f;
+        let mut output = input.to_vec();
+        AesCtrKeystream::new(
+            &self.key.ctr_key,
+            &AesCtrIv::aes_ctr_iv(q),
+            AesCtrMode::NIST_SP800_38A,
+        )?
+        .apply(&mut output)?;
+        Ok(output)
+    }
+
+    // Returns `V || C`. Without a nonce among the associated data this is
+    // deterministic: equal inputs give equal outputs, and nothing else leaks.
+    pub fn encrypt<A: AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        associated_data: &[A],
+        plaintext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let v = s2v(&self.key.mac_key, associated_data, plaintext)?;
+        let mut output = v.to_vec();
+        output.extend(self.crypt(&v, plaintext.as_ref())?);
+        Ok(output)
+    }
+
+    // Expects `V || C`; the plaintext is only returned if `V` verifies.
+    pub fn decrypt<A: AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        associated_data: &[A],
+        ciphertext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let ciphertext = ciphertext.as_ref();
+        if ciphertext.len() < 16 {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let mut v = [0_u8; 16];
+        v.copy_from_slice(&ciphertext[..16]);
+        let mut plaintext = self.crypt(&v, &ciphertext[16..])?;
+        let challenge = s2v(&self.key.mac_key, associated_data, &plaintext)?;
+        if !ct_eq(&challenge, &v) {
+            plaintext.iter_mut().for_each(|b| *b = 0);
+            return Err(AesError::AuthenticationFailed);
+        }
+        Ok(plaintext)
+    }
+
+    // Nonce-based use places the nonce as the final associated data component.
+    pub fn encrypt_with_nonce<A: AsRef<[u8]>, N: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        associated_data: &[A],
+        nonce: &N,
+        plaintext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let mut components: Vec<&[u8]> = associated_data.iter().map(|a| a.as_ref()).collect();
+        components.push(nonce.as_ref());
+        self.encrypt(&components, plaintext)
+    }
+
+    pub fn decrypt_with_nonce<A: AsRef<[u8]>, N: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        associated_data: &[A],
+        nonce: &N,
+        ciphertext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let mut components: Vec<&[u8]> = associated_data.iter().map(|a| a.as_ref()).collect();
+        components.push(nonce.as_ref());
+        self.decrypt(&components, ciphertext)
+    }
+}
+
+pub fn decrypt<K: ?Sized + AsRef<[u8]>, A: AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    associated_data: &[A],
+    ciphertext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesSivKey::try_copy_from_slice(key)?;
+    let cipher = AesSivCipher::new(&key);
+    cipher.decrypt(associated_data, ciphertext)
+}
+
+pub fn encrypt<K: ?Sized + AsRef<[u8]>, A: AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    associated_data: &[A],
+    plaintext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesSivKey::try_copy_from_slice(key)?;
+    let cipher = AesSivCipher::new(&key);
+    cipher.encrypt(associated_data, plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::AesError;
+    use crate::siv::{AesSivCipher, AesSivKey, MAX_ASSOCIATED_DATA};
+
+    // RFC 5297, Appendix A.1.
+    #[test]
+    fn rfc_5297_deterministic_vector() {
+        let key = hex::decode("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff")
+            .unwrap();
+        let ad = hex::decode("101112131415161718191a1b1c1d1e1f2021222324252627").unwrap();
+        let plaintext = hex::decode("112233445566778899aabbccddee").unwrap();
+        let output =
+            hex::decode("85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c").unwrap();
+        assert_eq!(
+            output,
+            crate::siv::encrypt(&key, &[&ad], &plaintext).unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            crate::siv::decrypt(&key, &[&ad], &output).unwrap()
+        );
+    }
+
+    // RFC 5297, Appendix A.2.
+    #[test]
+    fn rfc_5297_nonce_based_vector() {
+        let key = AesSivKey::try_copy_from_slice(
+            &hex::decode("7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f")
+                .unwrap(),
+        )
+        .unwrap();
+        let ad = [
+            hex::decode(
+                "00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa99887766554433221100",
+            )
+            .unwrap(),
+            hex::decode("102030405060708090a0").unwrap(),
+        ];
+        let nonce = hex::decode("09f911029d74e35bd84156c5635688c0").unwrap();
+        let plaintext = hex::decode(
+            "7468697320697320736f6d6520706c61696e7465787420746f20656e6372797074207573696e67205349562d414553",
+        )
+        .unwrap();
+        let output = hex::decode(
+            "7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17\
+             dba77ceb094fa663b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d",
+        )
+        .unwrap();
+        let cipher = AesSivCipher::new(&key);
+        assert_eq!(
+            output,
+            cipher.encrypt_with_nonce(&ad, &nonce, &plaintext).unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            cipher.decrypt_with_nonce(&ad, &nonce, &output).unwrap()
+        );
+    }
+
+    #[test]
+    fn too_many_components() {
+        let key = AesSivKey::try_copy_from_slice(&[0_u8; 32]).unwrap();
+        let cipher = AesSivCipher::new(&key);
+        let ad = vec![Vec::<u8>::new(); MAX_ASSOCIATED_DATA];
+        assert!(cipher.encrypt(&ad, b"").is_ok());
+        let ad = vec![Vec::<u8>::new(); MAX_ASSOCIATED_DATA + 1];
+        assert_eq!(
+            Err(AesError::TooManyComponents(MAX_ASSOCIATED_DATA + 1)),
+            cipher.encrypt(&ad, b"")
+        );
+    }
+
+    #[quickcheck]
+    fn encrypt_and_decrypt_is_identity(
+        key: AesSivKey,
+        ad: Vec<Vec<u8>>,
+        plaintext: Vec<u8>,
+    ) -> bool {
+        let cipher = AesSivCipher::new(&key);
+        let ciphertext: Vec<u8> = cipher.encrypt(&ad, &plaintext).unwrap();
+        ciphertext.len() == plaintext.len() + 16
+            && plaintext == cipher.decrypt(&ad, &ciphertext).unwrap()
+    }
+
+    #[quickcheck]
+    fn tampering_is_detected(
+        key: AesSivKey,
+        ad: Vec<Vec<u8>>,
+        plaintext: Vec<u8>,
+        index: usize,
+    ) -> bool {
+        let cipher = AesSivCipher::new(&key);
+        let mut ciphertext: Vec<u8> = cipher.encrypt(&ad, &plaintext).unwrap();
+        let index = index % ciphertext.len();
+        ciphertext[index] ^= 0x01;
+        let tampered = cipher.decrypt(&ad, &ciphertext);
+        ciphertext[index] ^= 0x01;
+        let mut ad = ad;
+        ad.push(Vec::new());
+        tampered == Err(AesError::AuthenticationFailed)
+            && cipher.decrypt(&ad, &ciphertext) == Err(AesError::AuthenticationFailed)
+    }
+}
@@ -0,0 +1,187 @@
+// A toy substitution-permutation network with 64-bit blocks, modelled on
+// PRESENT (4-bit S-box, bit permutation) but with a trivial key schedule.
+// It is NOT secure; it exists to exercise the generic modes and attacks with
+// a block size other than 16.
+
+use crate::block_cipher::BlockCipher;
+
+const ROUNDS: usize = 8;
+
+const SBOX: [u8; 16] = [
+    0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2,
+];
+
+const INV_SBOX: [u8; 16] = [
+    0x5, 0xe, 0xf, 0x8, 0xc, 0x1, 0x2, 0xd, 0xb, 0x4, 0x6, 0x3, 0x0, 0x7, 0x9, 0xa,
+];
+
+fn substitute(state: u64, sbox: &[u8; 16]) -> u64 {
+    (0..16).fold(0_u64, |output, i| {
+        let nibble = (state >> (4 * i)) & 0xf;
+        output | (sbox[nibble as usize] as u64) << (4 * i)
+    })
+}
+
+// Bit `i` moves to bit `16 * i mod 63`, and bit 63 stays in place.
+fn permute(state: u64) -> u64 {
+    (0..64).fold(0_u64, |output, i| {
+        let j = if i == 63 { 63 } else { (16 * i) % 63 };
+        output | ((state >> i) & 1) << j
+    })
+}
+
+fn inv_permute(state: u64) -> u64 {
+    (0..64).fold(0_u64, |output, i| {
+        let j = if i == 63 { 63 } else { (16 * i) % 63 };
+        output | ((state >> j) & 1) << i
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ToySpnError {
+    InvalidKeySize(usize),
+}
+
+// This is important for other errors to wrap this one.
+impl std::error::Error for ToySpnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl std::fmt::Display for ToySpnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ToySpnError::InvalidKeySize(size) => {
+                write!(f, "invalid key size {} (must be 16 bytes)", size)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ToySpnBlockCipher {
+    round_keys: [u64; ROUNDS + 1],
+}
+
+impl ToySpnBlockCipher {
+    pub fn new(key: u128) -> Self {
+        let mut round_keys = [0_u64; ROUNDS + 1];
+        for (i, round_key) in round_keys.iter_mut().enumerate() {
+            *round_key = (key.rotate_left(13 * i as u32) >> 64) as u64 ^ i as u64;
+        }
+        Self { round_keys }
+    }
+
+    pub fn encrypt_u64(&self, mut state: u64) -> u64 {
+        for round_key in self.round_keys[..ROUNDS].iter() {
+            state = permute(substitute(state ^ round_key, &SBOX));
+        }
+        state ^ self.round_keys[ROUNDS]
+    }
+
+    pub fn decrypt_u64(&self, mut state: u64) -> u64 {
+        state ^= self.round_keys[ROUNDS];
+        for round_key in self.round_keys[..ROUNDS].iter().rev() {
+            state = substitute(inv_permute(state), &INV_SBOX) ^ round_key;
+        }
+        state
+    }
+}
+
+impl BlockCipher for ToySpnBlockCipher {
+    type Key = u128;
+    type Error = ToySpnError;
+
+    fn block_size() -> usize {
+        8
+    }
+
+    fn from_key(key: &u128) -> Self {
+        Self::new(*key)
+    }
+
+    fn key_from_slice(bytes: &[u8]) -> Result<u128, ToySpnError> {
+        let key: [u8; 16] = bytes
+            .try_into()
+            .map_err(|_| ToySpnError::InvalidKeySize(bytes.len()))?;
+        Ok(u128::from_be_bytes(key))
+    }
+
+    fn encrypt_block_mut(&self, block: &mut [u8]) {
+        let state = u64::from_be_bytes(block.try_into().unwrap());
+        block.copy_from_slice(&self.encrypt_u64(state).to_be_bytes());
+    }
+
+    fn decrypt_block_mut(&self, block: &mut [u8]) {
+        let state = u64::from_be_bytes(block.try_into().unwrap());
+        block.copy_from_slice(&self.decrypt_u64(state).to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cbc::{AesCbcMode, CbcCipher};
+    use crate::ctr::{AesCtrMode, CtrCipher};
+    use crate::ecb::EcbCipher;
+    use crate::toy_spn::*;
+
+    #[test]
+    fn sbox_and_permutation_are_invertible() {
+        for (i, &s) in SBOX.iter().enumerate() {
+            assert_eq!(i as u8, INV_SBOX[s as usize]);
+        }
+        for i in 0..64 {
+            assert_eq!(1 << i, inv_permute(permute(1 << i)));
+        }
+    }
+
+    #[quickcheck]
+    fn encrypt_and_decrypt_is_identity(key: u128, block: u64) -> bool {
+        let cipher = ToySpnBlockCipher::new(key);
+        block == cipher.decrypt_u64(cipher.encrypt_u64(block))
+    }
+
+    #[quickcheck]
+    fn generic_modes_round_trip(key: u128, iv: u64, blocks: Vec<u64>, mode: AesCtrMode) -> bool {
+        let plaintext: Vec<u8> = blocks.iter().flat_map(|b| b.to_be_bytes()).collect();
+        let iv = iv.to_be_bytes();
+        let ecb = EcbCipher::<ToySpnBlockCipher>::new(&key);
+        let cbc = CbcCipher::<ToySpnBlockCipher>::try_new(&key, &iv).unwrap();
+        let cts =
+            CbcCipher::<ToySpnBlockCipher>::try_with_mode(&key, &iv, AesCbcMode::CS3).unwrap();
+        let ctr = CtrCipher::<ToySpnBlockCipher>::try_new(&key, &iv, mode).unwrap();
+        let ecb_ciphertext: Vec<u8> = ecb.encrypt(&plaintext).unwrap();
+        let cbc_ciphertext: Vec<u8> = cbc.encrypt(&plaintext).unwrap();
+        let ctr_ciphertext: Vec<u8> = ctr.encrypt(&plaintext).unwrap();
+        let cts_ok = match cts.encrypt(&plaintext[plaintext.len().min(3)..]) {
+            Ok(ciphertext) => {
+                plaintext[plaintext.len().min(3)..] == cts.decrypt(&ciphertext).unwrap()[..]
+            }
+            Err(_) => plaintext.len() < 11,
+        };
+        plaintext == ecb.decrypt(&ecb_ciphertext).unwrap()
+            && plaintext == cbc.decrypt(&cbc_ciphertext).unwrap()
+            && plaintext == ctr.decrypt(&ctr_ciphertext).unwrap()
+            && cts_ok
+    }
+
+    #[test]
+    fn generic_modes_reject_wrong_iv_size() {
+        let key = 0_u128;
+        assert!(CbcCipher::<ToySpnBlockCipher>::try_new(&key, &[0_u8; 16]).is_err());
+        assert!(
+            CtrCipher::<ToySpnBlockCipher>::try_new(&key, &[0_u8; 16], AesCtrMode::CRYPTOPALS)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn key_from_slice_rejects_wrong_key_size() {
+        assert_eq!(
+            Err(ToySpnError::InvalidKeySize(15)),
+            ToySpnBlockCipher::key_from_slice(&[0_u8; 15])
+        );
+    }
+}
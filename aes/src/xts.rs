@@ -0,0 +1,552 @@
+// See: IEEE Std 1619-2007, "XTS-AES Tweakable Block Cipher"
+
+use quickcheck::{single_shrinker, Arbitrary, Gen};
+use rand::prelude::*;
+use xor::ct::ct_eq;
+
+use crate::ecb::AesEcbBlockCipher;
+use crate::error::AesError;
+use crate::key::AesKey;
+
+// Multiplies a tweak by the primitive element α of GF(2^128). The tweak is a
+// little-endian polynomial, reduced modulo x^128 + x^7 + x^2 + x + 1.
+pub fn gf128_mul_alpha(tweak: &mut [u8; 16]) {
+    let t = u128::from_le_bytes(*tweak);
+    let carry = (t >> 127) as u8;
+    *tweak = ((t << 1) ^ (0x87 * carry as u128)).to_le_bytes();
+}
+
+// The pair of keys used by XTS: `data_key` encrypts the data blocks and
+// `tweak_key` encrypts the tweak.
+#[derive(Clone, Debug)]
+pub struct AesXtsKey {
+    data_key: AesKey,
+    tweak_key: AesKey,
+}
+
+impl AesXtsKey {
+    // XTS-AES is only defined for AES-128 and AES-256, with both keys the same
+    // size. Equal keys are rejected as well, since they make XTS degrade to
+    // something much weaker.
+    pub fn new(data_key: AesKey, tweak_key: AesKey) -> Result<AesXtsKey, AesError> {
+        match (data_key.as_slice().len(), tweak_key.as_slice().len()) {
+            (16, 16) | (32, 32) => {}
+            (x, y) if x == y => return Err(AesError::InvalidKeySize(x + y)),
+            _ => {
+                return Err(AesError::InvalidKey(
+                    "data and tweak keys must be the same size",
+                ))
+            }
+        }
+        if ct_eq(data_key.as_slice(), tweak_key.as_slice()) {
+            return Err(AesError::InvalidKey("data and tweak keys must differ"));
+        }
+        Ok(AesXtsKey {
+            data_key,
+            tweak_key,
+        })
+    }
+
+    // Splits a concatenated `data_key || tweak_key` (32 or 64 bytes).
+    pub fn try_copy_from_slice<K: ?Sized + AsRef<[u8]>>(bytes: &K) -> Result<AesXtsKey, AesError> {
+        let bytes = bytes.as_ref();
+        if bytes.len() != 32 && bytes.len() != 64 {
+            return Err(AesError::InvalidKeySize(bytes.len()));
+        }
+        let (data_key, tweak_key) = bytes.split_at(bytes.len() / 2);
+        Self::new(
+            AesKey::try_copy_from_slice(data_key)?,
+            AesKey::try_copy_from_slice(tweak_key)?,
+        )
+    }
+
+    pub fn data_key(&self) -> &AesKey {
+        &self.data_key
+    }
+
+    pub fn tweak_key(&self) -> &AesKey {
+        &self.tweak_key
+    }
+}
+
+impl Distribution<AesXtsKey> for rand::distributions::Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AesXtsKey {
+        let mut bytes = vec![0_u8; if rng.gen() { 32 } else { 64 }];
+        loop {
+            rng.fill(&mut bytes[..]);
+            if let Ok(key) = AesXtsKey::try_copy_from_slice(&bytes) {
+                return key;
+            }
+        }
+    }
+}
+
+impl Arbitrary for AesXtsKey {
+    fn arbitrary(g: &mut Gen) -> AesXtsKey {
+        let size = *g.choose(&[16, 32]).unwrap();
+        let mut bytes: Vec<u8> = (0..(2 * size)).map(|_| u8::arbitrary(g)).collect();
+        if bytes[..size] == bytes[size..] {
+            bytes[size] ^= 0x01;
+        }
+        AesXtsKey::try_copy_from_slice(&bytes).unwrap()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AesXtsTweak([u8; 16]);
+
+impl AesXtsTweak {
+    pub fn aes_xts_tweak(value: [u8; 16]) -> AesXtsTweak {
+        AesXtsTweak(value)
+    }
+
+    // The tweak for a data unit (sector) number, encoded little-endian.
+    pub fn from_data_unit(data_unit: u128) -> AesXtsTweak {
+        AesXtsTweak(data_unit.to_le_bytes())
+    }
+
+    pub fn try_copy_from_slice<T: ?Sized + AsRef<[u8]>>(
+        bytes: &T,
+    ) -> Result<AesXtsTweak, AesError> {
+        let bytes = bytes.as_ref();
+        match bytes.len() {
+            16 => {
+                let mut tweak: [u8; 16] = [0u8; 16];
+                tweak.copy_from_slice(bytes);
+                Ok(Self::aes_xts_tweak(tweak))
+            }
+            x => Err(AesError::InvalidInitializationVectorSize {
+                was: x,
+                explanation: "must be 16 bytes",
+            }),
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+impl Distribution<AesXtsTweak> for rand::distributions::Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AesXtsTweak {
+        AesXtsTweak(rng.gen())
+    }
+}
+
+impl Arbitrary for AesXtsTweak {
+    fn arbitrary(g: &mut Gen) -> AesXtsTweak {
+        AesXtsTweak::from_data_unit(u128::arbitrary(g))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = AesXtsTweak>> {
+        single_shrinker(AesXtsTweak([0_u8; 16]))
+    }
+}
+
+pub struct AesXtsCipher<'k> {
+    data_cipher: AesEcbBlockCipher,
+    tweak_cipher: AesEcbBlockCipher,
+    key: std::marker::PhantomData<&'k AesXtsKey>,
+}
+
+impl<'k> AesXtsCipher<'k> {
+    pub fn new(key: &'k AesXtsKey) -> AesXtsCipher<'k> {
+        AesXtsCipher {
+            data_cipher: AesEcbBlockCipher::new(&key.data_key),
+            tweak_cipher: AesEcbBlockCipher::new(&key.tweak_key),
+            key: std::marker::PhantomData,
+        }
+    }
+
+    // Decrypts one data unit. Data units that are not a multiple of 16 bytes
+    // use ciphertext stealing, but must hold at least one full block.
+    pub fn decrypt<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        tweak: &AesXtsTweak,
+        ciphertext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let ciphertext = ciphertext.as_ref();
+        if ciphertext.len() < 16 {
+            return Err(AesError::InvalidDataUnitSize(ciphertext.len()));
+        }
+        let mut plaintext = ciphertext.to_vec();
+        let remainder = plaintext.len() % 16;
+        let full_blocks = plaintext.len() / 16;
+        let mut t = self.initial_tweak(tweak);
+        // With stealing, the last full block is handled out of order below.
+        let chained_blocks = if remainder == 0 {
+            full_blocks
+        } else {
+            full_blocks - 1
+        };
+        for block in plaintext.chunks_exact_mut(16).take(chained_blocks) {
+            self.decrypt_block_mut(block, &t);
+            gf128_mul_alpha(&mut t);
+        }
+        if remainder != 0 {
+            let mut next = t;
+            gf128_mul_alpha(&mut next);
+            let (head, tail) = plaintext.split_at_mut(full_blocks * 16);
+            let last_block = &mut head[(full_blocks - 1) * 16..];
+            self.decrypt_block_mut(last_block, &next);
+            // `last_block` now holds the final partial plaintext followed by
+            // the bytes that were stolen from the penultimate ciphertext.
+            tail.swap_with_slice(&mut last_block[..remainder]);
+            self.decrypt_block_mut(last_block, &t);
+        }
+        Ok(plaintext)
+    }
+
+    // Encrypts one data unit. Data units that are not a multiple of 16 bytes
+    // use ciphertext stealing, but must hold at least one full block.
+    pub fn encrypt<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        tweak: &AesXtsTweak,
+        plaintext: &T,
+    ) -> Result<Vec<u8>, AesError> {
+        let plaintext = plaintext.as_ref();
+        if plaintext.len() < 16 {
+            return Err(AesError::InvalidDataUnitSize(plaintext.len()));
+        }
+        let mut ciphertext = plaintext.to_vec();
+        let remainder = ciphertext.len() % 16;
+        let full_blocks = ciphertext.len() / 16;
+        let mut t = self.initial_tweak(tweak);
+        for block in ciphertext.chunks_exact_mut(16) {
+            self.encrypt_block_mut(block, &t);
+            gf128_mul_alpha(&mut t);
+        }
+        if remainder != 0 {
+            let (head, tail) = ciphertext.split_at_mut(full_blocks * 16);
+            let last_block = &mut head[(full_blocks - 1) * 16..];
+            // The partial plaintext takes the place of the leading bytes of
+            // the penultimate ciphertext, which move to the end.
+            tail.swap_with_slice(&mut last_block[..remainder]);
+            self.encrypt_block_mut(last_block, &t);
+        }
+        Ok(ciphertext)
+    }
+
+    fn initial_tweak(&self, tweak: &AesXtsTweak) -> [u8; 16] {
+        let mut t = tweak.0;
+        self.tweak_cipher.encrypt_blocks_mut(&mut t);
+        t
+    }
+
+    fn decrypt_block_mut(&self, block: &mut [u8], t: &[u8; 16]) {
+        block.iter_mut().zip(t.iter()).for_each(|(b, t)| *b ^= t);
+        self.data_cipher.decrypt_blocks_mut(block);
+        block.iter_mut().zip(t.iter()).for_each(|(b, t)| *b ^= t);
+    }
+
+    fn encrypt_block_mut(&self, block: &mut [u8], t: &[u8; 16]) {
+        block.iter_mut().zip(t.iter()).for_each(|(b, t)| *b ^= t);
+        self.data_cipher.encrypt_blocks_mut(block);
+        block.iter_mut().zip(t.iter()).for_each(|(b, t)| *b ^= t);
+    }
+}
+
+pub fn decrypt<K: ?Sized + AsRef<[u8]>, TW: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    tweak: &TW,
+    ciphertext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesXtsKey::try_copy_from_slice(key)?;
+    let tweak = AesXtsTweak::try_copy_from_slice(tweak)?;
+    let cipher = AesXtsCipher::new(&key);
+    cipher.decrypt(&tweak, ciphertext)
+}
+
+pub fn encrypt<K: ?Sized + AsRef<[u8]>, TW: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    tweak: &TW,
+    plaintext: &T,
+) -> Result<Vec<u8>, AesError> {
+    let key = AesXtsKey::try_copy_from_slice(key)?;
+    let tweak = AesXtsTweak::try_copy_from_slice(tweak)?;
+    let cipher = AesXtsCipher::new(&key);
+    cipher.encrypt(&tweak, plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::AesError;
+    use crate::key::AesKey;
+    use crate::xts::{AesXtsCipher, AesXtsKey, AesXtsTweak};
+
+    // IEEE Std 1619-2007, Annex B: vectors 10 through 14, which share a pair
+    // of 256-bit keys and encrypt the 512-byte data unit 00 01 .. ff 00 01 .. ff.
+    const XTS_AES_256_VECTORS: [(u128, &str); 5] = [
+        (
+            0xff,
+            "1c3b3a102f770386e4836c99e370cf9bea00803f5e482357a4ae12d414a3e63b\
+             5d31e276f8fe4a8d66b317f9ac683f44680a86ac35adfc3345befecb4bb188fd\
+             5776926c49a3095eb108fd1098baec70aaa66999a72a82f27d848b21d4a741b0\
+             c5cd4d5fff9dac89aeba122961d03a757123e9870f8acf1000020887891429ca\
+             2a3e7a7d7df7b10355165c8b9a6d0a7de8b062c4500dc4cd120c0f7418dae3d0\
+             b5781c34803fa75421c790dfe1de1834f280d7667b327f6c8cd7557e12ac3a0f\
+             93ec05c52e0493ef31a12d3d9260f79a289d6a379bc70c50841473d1a8cc81ec\
+             583e9645e07b8d9670655ba5bbcfecc6dc3966380ad8fecb17b6ba02469a020a\
+             84e18e8f84252070c13e9f1f289be54fbc481457778f616015e1327a02b140f1\
+             505eb309326d68378f8374595c849d84f4c333ec4423885143cb47bd71c5edae\
+             9be69a2ffeceb1bec9de244fbe15992b11b77c040f12bd8f6a975a44a0f90c29\
+             a9abc3d4d893927284c58754cce294529f8614dcd2aba991925fedc4ae74ffac\
+             6e333b93eb4aff0479da9a410e4450e0dd7ae4c6e2910900575da401fc07059f\
+             645e8b7e9bfdef33943054ff84011493c27b3429eaedb4ed5376441a77ed4385\
+             1ad77f16f541dfd269d50d6a5f14fb0aab1cbb4c1550be97f7ab4066193c4caa\
+             773dad38014bd2092fa755c824bb5e54c4f36ffda9fcea70b9c6e693e148c151",
+        ),
+        (
+            0xffff,
+            "77a31251618a15e6b92d1d66dffe7b50b50bad552305ba0217a610688eff7e11\
+             e1d0225438e093242d6db274fde801d4cae06f2092c728b2478559df58e837c2\
+             469ee4a4fa794e4bbc7f39bc026e3cb72c33b0888f25b4acf56a2a9804f1ce6d\
+             3d6e1dc6ca181d4b546179d55544aa7760c40d06741539c7e3cd9d2f6650b201\
+             3fd0eeb8c2b8e3d8d240ccae2d4c98320a7442e1c8d75a42d6e6cfa4c2eca179\
+             8d158c7aecdf82490f24bb9b38e108bcda12c3faf9a21141c3613b58367f922a\
+             aa26cd22f23d708dae699ad7cb40a8ad0b6e2784973dcb605684c08b8d6998c6\
+             9aac049921871ebb65301a4619ca80ecb485a31d744223ce8ddc2394828d6a80\
+             470c092f5ba413c3378fa6054255c6f9df4495862bbb3287681f931b687c888a\
+             bf844dfc8fc28331e579928cd12bd2390ae123cf03818d14dedde5c0c24c8ab0\
+             18bfca75ca096f2d531f3d1619e785f1ada437cab92e980558b3dce1474afb75\
+             bfedbf8ff54cb2618e0244c9ac0d3c66fb51598cd2db11f9be39791abe447c63\
+             094f7c453b7ff87cb5bb36b7c79efb0872d17058b83b15ab0866ad8a58656c5a\
+             7e20dbdf308b2461d97c0ec0024a2715055249cf3b478ddd4740de654f75ca68\
+             6e0d7345c69ed50cdc2a8b332b1f8824108ac937eb050585608ee734097fc090\
+             54fbff89eeaeea791f4a7ab1f9868294a4f9e27b42af8100cb9d59cef9645803",
+        ),
+        (
+            0xffffff,
+            "e387aaa58ba483afa7e8eb469778317ecf4cf573aa9d4eac23f2cdf914e4e200\
+             a8b490e42ee646802dc6ee2b471b278195d60918ececb44bf79966f83faba049\
+             9298ebc699c0c8634715a320bb4f075d622e74c8c932004f25b41e361025b5a8\
+             7815391f6108fc4afa6a05d9303c6ba68a128a55705d415985832fdeaae6c8e1\
+             9110e84d1b1f199a2692119edc96132658f09da7c623efcec712537a3d94c0bf\
+             5d7e352ec94ae5797fdb377dc1551150721adf15bd26a8efc2fcaad56881fa9e\
+             62462c28f30ae1ceaca93c345cf243b73f542e2074a705bd2643bb9f7cc79bb6\
+             e7091ea6e232df0f9ad0d6cf502327876d82207abf2115cdacf6d5a48f6c1879\
+             a65b115f0f8b3cb3c59d15dd8c769bc014795a1837f3901b5845eb491adfefe0\
+             97b1fa30a12fc1f65ba22905031539971a10f2f36c321bb51331cdefb39e3964\
+             c7ef079994f5b69b2edd83a71ef549971ee93f44eac3938fcdd61d01fa71799d\
+             a3a8091c4c48aa9ed263ff0749df95d44fef6a0bb578ec69456aa5408ae32c7a\
+             f08ad7ba8921287e3bbee31b767be06a0e705c864a769137df28292283ea81a2\
+             480241b44d9921cdbec1bc28dc1fda114bd8e5217ac9d8ebafa720e9da4f9ace\
+             231cc949e5b96fe76ffc21063fddc83a6b8679c00d35e09576a875305bed5f36\
+             ed242c8900dd1fa965bc950dfce09b132263a1eef52dd6888c309f5a7d712826",
+        ),
+        (
+            0xffffffff,
+            "bf53d2dade78e822a4d949a9bc6766b01b06a8ef70d26748c6a7fc36d80ae4c5\
+             520f7c4ab0ac8544424fa405162fef5a6b7f229498063618d39f0003cb5fb8d1\
+             c86b643497da1ff945c8d3bedeca4f479702a7a735f043ddb1d6aaade3c4a0ac\
+             7ca7f3fa5279bef56f82cd7a2f38672e824814e10700300a055e1630b8f1cb0e\
+             919f5e942010a416e2bf48cb46993d3cb6a51c19bacf864785a00bc2ecff15d3\
+             50875b246ed53e68be6f55bd7e05cfc2b2ed6432198a6444b6d8c247fab941f5\
+             69768b5c429366f1d3f00f0345b96123d56204c01c63b22ce78baf116e525ed9\
+             0fdea39fa469494d3866c31e05f295ff21fea8d4e6e13d67e47ce722e9698a1c\
+             1048d68ebcde76b86fcf976eab8aa9790268b7068e017a8b9b749409514f1053\
+             027fd16c3786ea1bac5f15cb79711ee2abe82f5cf8b13ae73030ef5b9e4457e7\
+             5d1304f988d62dd6fc4b94ed38ba831da4b7634971b6cd8ec325d9c61c00f1df\
+             73627ed3745a5e8489f3a95c69639c32cd6e1d537a85f75cc844726e8a72fc00\
+             77ad22000f1d5078f6b866318c668f1ad03d5a5fced5219f2eabbd0aa5c0f460\
+             d183f04404a0d6f469558e81fab24a167905ab4c7878502ad3e38fdbe62a4155\
+             6cec37325759533ce8f25f367c87bb5578d667ae93f9e2fd99bcbc5f2fbba88c\
+             f6516139420fcff3b7361d86322c4bd84c82f335abb152c4a93411373aaa8220",
+        ),
+        (
+            0xffffffffff,
+            "64497e5a831e4a932c09be3e5393376daa599548b816031d224bbf50a818ed23\
+             50eae7e96087c8a0db51ad290bd00c1ac1620857635bf246c176ab463be30b80\
+             8da548081ac847b158e1264be25bb0910bbc92647108089415d45fab1b3d2604\
+             e8a8eff1ae4020cfa39936b66827b23f371b92200be90251e6d73c5f86de5fd4\
+             a950781933d79a28272b782a2ec313efdfcc0628f43d744c2dc2ff3dcb66999b\
+             50c7ca895b0c64791eeaa5f29499fb1c026f84ce5b5c72ba1083cddb5ce45434\
+             631665c333b60b11593fb253c5179a2c8db813782a004856a1653011e93fb6d8\
+             76c18366dd8683f53412c0c180f9c848592d593f8609ca736317d356e13e2bff\
+             3a9f59cd9aeb19cd482593d8c46128bb32423b37a9adfb482b99453fbe25a41b\
+             f6feb4aa0bef5ed24bf73c762978025482c13115e4015aac992e5613a3b5c2f6\
+             85b84795cb6e9b2656d8c88157e52c42f978d8634c43d06fea928f2822e465aa\
+             6576e9bf419384506cc3ce3c54ac1a6f67dc66f3b30191e698380bc999b05abc\
+             e19dc0c6dcc2dd001ec535ba18deb2df1a101023108318c75dc98611a09dc48a\
+             0acdec676fabdf222f07e026f059b672b56e5cbc8e1d21bbd867dd9272120546\
+             81d70ea737134cdfce93b6f82ae22423274e58a0821cc5502e2d0ab4585e94de\
+             6975be5e0b4efce51cd3e70c25a1fbbbd609d273ad5b0d59631c531f6a0a57b9",
+        ),
+    ];
+
+    // IEEE Std 1619-2007, Annex B: vectors 2, 3 and 15 through 18. Vector 1
+    // uses the same all-zero data and tweak key, which `AesXtsKey` rejects.
+    #[test]
+    fn ieee_1619_vectors() {
+        let stealing_plaintext: Vec<u8> = (0_u8..20).collect();
+        for (key1, key2, data_unit, plaintext, ciphertext) in [
+            (
+                "11111111111111111111111111111111",
+                "22222222222222222222222222222222",
+                0x3333333333,
+                vec![0x44_u8; 32],
+                "c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0",
+            ),
+            (
+                "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+                "22222222222222222222222222222222",
+                0x3333333333,
+                vec![0x44_u8; 32],
+                "af85336b597afc1a900b2eb21ec949d292df4c047e0b21532186a5971a227a89",
+            ),
+            (
+                "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+                "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+                0x123456789a,
+                stealing_plaintext[..17].to_vec(),
+                "6c1625db4671522d3d7599601de7ca09ed",
+            ),
+            (
+                "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+                "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+                0x123456789a,
+                stealing_plaintext[..18].to_vec(),
+                "d069444b7a7e0cab09e24447d24deb1fedbf",
+            ),
+            (
+                "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+                "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+                0x123456789a,
+                stealing_plaintext[..19].to_vec(),
+                "e5df1351c0544ba1350b3363cd8ef4beedbf9d",
+            ),
+            (
+                "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+                "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+                0x123456789a,
+                stealing_plaintext[..20].to_vec(),
+                "9d84c813f719aa2c7be3f66171c7c5c2edbf9dac",
+            ),
+        ] {
+            let key = hex::decode(format!("{}{}", key1, key2)).unwrap();
+            let tweak = AesXtsTweak::from_data_unit(data_unit);
+            let ciphertext = hex::decode(ciphertext).unwrap();
+            let challenge: Vec<u8> =
+                crate::xts::encrypt(&key, tweak.as_slice(), &plaintext).unwrap();
+            assert_eq!(ciphertext, challenge);
+            let challenge: Vec<u8> =
+                crate::xts::decrypt(&key, tweak.as_slice(), &ciphertext).unwrap();
+            assert_eq!(plaintext, challenge);
+        }
+    }
+
+    #[test]
+    fn ieee_1619_xts_aes_256_vectors() {
+        let key = hex::decode(
+            "2718281828459045235360287471352662497757247093699959574966967627\
+             3141592653589793238462643383279502884197169399375105820974944592",
+        )
+        .unwrap();
+        let cipher_key = AesXtsKey::try_copy_from_slice(&key).unwrap();
+        let cipher = AesXtsCipher::new(&cipher_key);
+        let plaintext: Vec<u8> = (0..512).map(|i| i as u8).collect();
+        for (data_unit, ciphertext) in XTS_AES_256_VECTORS {
+            let tweak = AesXtsTweak::from_data_unit(data_unit);
+            let ciphertext = hex::decode(ciphertext).unwrap();
+            assert_eq!(ciphertext, cipher.encrypt(&tweak, &plaintext).unwrap());
+            assert_eq!(plaintext, cipher.decrypt(&tweak, &ciphertext).unwrap());
+        }
+    }
+
+    #[test]
+    fn rejects_short_data_units() {
+        let key = AesXtsKey::try_copy_from_slice(&(0_u8..32).collect::<Vec<u8>>()).unwrap();
+        let cipher = AesXtsCipher::new(&key);
+        let tweak = AesXtsTweak::from_data_unit(0);
+        for length in 0..16 {
+            let input = vec![0_u8; length];
+            assert_eq!(
+                Err(AesError::InvalidDataUnitSize(length)),
+                cipher.encrypt(&tweak, &input)
+            );
+            assert_eq!(
+                Err(AesError::InvalidDataUnitSize(length)),
+                cipher.decrypt(&tweak, &input)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        let bytes: Vec<u8> = (0_u8..96).collect();
+        for length in [0, 16, 31, 48, 63, 96] {
+            assert_eq!(
+                Some(AesError::InvalidKeySize(length)),
+                AesXtsKey::try_copy_from_slice(&bytes[..length]).err()
+            );
+        }
+        assert_eq!(
+            Some(AesError::InvalidKeySize(48)),
+            AesXtsKey::new(
+                AesKey::try_copy_from_slice(&bytes[..24]).unwrap(),
+                AesKey::try_copy_from_slice(&bytes[24..48]).unwrap()
+            )
+            .err()
+        );
+        assert_eq!(
+            Some(AesError::InvalidKey(
+                "data and tweak keys must be the same size"
+            )),
+            AesXtsKey::new(
+                AesKey::try_copy_from_slice(&bytes[..16]).unwrap(),
+                AesKey::try_copy_from_slice(&bytes[16..48]).unwrap()
+            )
+            .err()
+        );
+        for size in [16, 32] {
+            let mut key = bytes[..size].to_vec();
+            key.extend_from_slice(&bytes[..size]);
+            assert_eq!(
+                Some(AesError::InvalidKey("data and tweak keys must differ")),
+                AesXtsKey::try_copy_from_slice(&key).err()
+            );
+        }
+    }
+
+    #[quickcheck]
+    fn arbitrary_keys_are_valid(key: AesXtsKey) -> bool {
+        let size = key.data_key().as_slice().len();
+        let tweak_key = key.tweak_key().as_slice();
+        (size == 16 || size == 32)
+            && tweak_key.len() == size
+            && key.data_key().as_slice() != tweak_key
+    }
+
+    #[quickcheck]
+    fn encrypt_and_decrypt_is_identity(
+        key: AesXtsKey,
+        tweak: AesXtsTweak,
+        plaintext: Vec<u8>,
+    ) -> bool {
+        let cipher = AesXtsCipher::new(&key);
+        match cipher.encrypt(&tweak, &plaintext) {
+            Ok(ciphertext) => {
+                ciphertext.len() == plaintext.len()
+                    && plaintext == cipher.decrypt(&tweak, &ciphertext).unwrap()
+            }
+            Err(_) => plaintext.len() < 16,
+        }
+    }
+
+    #[quickcheck]
+    fn tweak_changes_ciphertext(key: AesXtsKey, data_unit: u128, block: u128) -> bool {
+        let cipher = AesXtsCipher::new(&key);
+        let plaintext = block.to_be_bytes();
+        let a: Vec<u8> = cipher
+            .encrypt(&AesXtsTweak::from_data_unit(data_unit), &plaintext)
+            .unwrap();
+        let b: Vec<u8> = cipher
+            .encrypt(&AesXtsTweak::from_data_unit(data_unit ^ 1), &plaintext)
+            .unwrap();
+        a != b
+    }
+}
@@ -0,0 +1,334 @@
+// Interactive crib dragging over ciphertexts that share a keystream.
+//
+// Usage: crib_drag [--hex | --base64] FILE...
+//
+// Each line of each FILE is one hex or base64 ciphertext. Without a flag, a
+// file is read as hex if every line in it is valid hex, and as base64
+// otherwise. Commands are read from standard input; see `help`.
+
+use std::io::{self, BufRead, Write};
+
+use cryptopals::set03::challenge19::crib_drag::CribDragger;
+
+const HELP: &str = "\
+commands:
+  drag CRIB                    rank placements of CRIB in every ciphertext
+  place INDEX OFFSET CRIB      take CRIB as ciphertext INDEX at OFFSET
+  forget OFFSET LENGTH         mark keystream bytes as unknown again
+  show                         print every ciphertext decrypted so far
+  keystream                    print the recovered keystream as hex
+  help                         print this message
+  quit                         exit
+CRIB is everything after the preceding arguments, including spaces.";
+
+// How many placements `drag` prints.
+const TOP: usize = 10;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+const USAGE: &str = "usage: crib_drag [--hex | --base64] FILE...";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Hex,
+    Base64,
+}
+
+// The encoding given on the command line, if any, and the files to read.
+fn options(args: &[String]) -> Result<(Option<Encoding>, &[String])> {
+    let (encoding, paths) = match args.split_first() {
+        Some((flag, paths)) if flag == "--hex" => (Some(Encoding::Hex), paths),
+        Some((flag, paths)) if flag == "--base64" => (Some(Encoding::Base64), paths),
+        _ => (None, args),
+    };
+    match paths.iter().find(|path| path.starts_with("--")) {
+        Some(flag) => Err(format!("unexpected option {}", flag).into()),
+        None => Ok((encoding, paths)),
+    }
+}
+
+// Decodes every non-blank line of a file. A file without an explicit
+// encoding is taken as hex only if all of its lines are hex, since a short
+// base64 line can happen to look like hex too.
+fn decode(contents: &str, encoding: Option<Encoding>) -> Result<Vec<Vec<u8>>> {
+    let lines: Vec<&str> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let encoding = encoding.unwrap_or_else(|| {
+        let is_hex = |line: &&str| {
+            line.len().is_multiple_of(2) && line.bytes().all(|c| c.is_ascii_hexdigit())
+        };
+        if lines.iter().all(is_hex) {
+            Encoding::Hex
+        } else {
+            Encoding::Base64
+        }
+    });
+    lines
+        .iter()
+        .enumerate()
+        .map(|(number, line)| {
+            let decoded = match encoding {
+                Encoding::Hex => hex::decode(line).map_err(|e| e.to_string()),
+                Encoding::Base64 => base64::decode(line).map_err(|e| e.to_string()),
+            };
+            decoded.map_err(|e| format!("line {}: {}", number + 1, e).into())
+        })
+        .collect()
+}
+
+fn read_ciphertexts(paths: &[String], encoding: Option<Encoding>) -> Result<Vec<Vec<u8>>> {
+    let mut ciphertexts = Vec::new();
+    for path in paths {
+        let contents = std::fs::read_to_string(path)?;
+        ciphertexts.extend(decode(&contents, encoding).map_err(|e| format!("{}: {}", path, e))?);
+    }
+    Ok(ciphertexts)
+}
+
+// Splits off `n` whitespace-separated arguments and returns them with the
+// rest of the line, which is kept verbatim.
+fn arguments(line: &str, n: usize) -> Option<(Vec<&str>, &str)> {
+    let mut args = Vec::with_capacity(n);
+    let mut rest = line;
+    for _ in 0..n {
+        let trimmed = rest.trim_start();
+        let end = trimmed.find(' ').unwrap_or(trimmed.len());
+        if end == 0 {
+            return None;
+        }
+        args.push(&trimmed[..end]);
+        rest = trimmed.get((end + 1)..).unwrap_or("");
+    }
+    Some((args, rest))
+}
+
+fn show(dragger: &CribDragger, out: &mut impl Write) -> io::Result<()> {
+    for index in 0..dragger.ciphertexts().len() {
+        writeln!(out, "{:>3}: {}", index, dragger.render(index).unwrap())?;
+    }
+    Ok(())
+}
+
+fn run(dragger: &mut CribDragger, line: &str, out: &mut impl Write) -> Result<bool> {
+    let (command, rest) = match line.split_once(' ') {
+        Some((command, rest)) => (command, rest),
+        None => (line.trim(), ""),
+    };
+    match command {
+        "drag" if !rest.is_empty() => {
+            for placement in dragger.drag(rest).iter().take(TOP) {
+                writeln!(
+                    out,
+                    "{:>3} @ {:<4} score {:.4}",
+                    placement.index, placement.offset, placement.score
+                )?;
+                for (other, revealed) in placement.revealed.iter() {
+                    writeln!(
+                        out,
+                        "      {:>3}: {:?}",
+                        other,
+                        String::from_utf8_lossy(revealed)
+                    )?;
+                }
+            }
+        }
+        "place" => match arguments(rest, 2) {
+            Some((args, crib)) if !crib.is_empty() => {
+                dragger.place(args[0].parse()?, args[1].parse()?, crib)?;
+                show(dragger, out)?;
+            }
+            _ => writeln!(out, "usage: place INDEX OFFSET CRIB")?,
+        },
+        "forget" => match arguments(rest, 2) {
+            Some((args, _)) => {
+                dragger.forget(args[0].parse()?, args[1].parse()?);
+                show(dragger, out)?;
+            }
+            None => writeln!(out, "usage: forget OFFSET LENGTH")?,
+        },
+        "show" => show(dragger, out)?,
+        "keystream" => {
+            let keystream: String = dragger
+                .keystream()
+                .iter()
+                .map(|k| k.map_or("__".to_string(), |k| format!("{:02x}", k)))
+                .collect();
+            writeln!(out, "{}", keystream)?;
+        }
+        "quit" | "exit" => return Ok(false),
+        "" => {}
+        _ => writeln!(out, "{}", HELP)?,
+    }
+    Ok(true)
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (encoding, paths) = match options(&args) {
+        Ok((encoding, paths)) if !paths.is_empty() => (encoding, paths),
+        Ok(_) => {
+            eprintln!("{}\n{}", USAGE, HELP);
+            std::process::exit(2);
+        }
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let ciphertexts = read_ciphertexts(paths, encoding)?;
+    if ciphertexts.len() < 2 {
+        return Err("crib dragging needs at least two ciphertexts".into());
+    }
+    let mut dragger = CribDragger::new(ciphertexts);
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    show(&dragger, &mut stdout)?;
+    loop {
+        write!(stdout, "> ")?;
+        stdout.flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        match run(&mut dragger, line, &mut stdout) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(error) => writeln!(stdout, "error: {}", error)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXTS: [&[u8]; 3] = [
+        b"I have met them at close of day",
+        b"Coming with vivid faces",
+        b"From counter or desk among grey",
+    ];
+
+    fn dragger() -> CribDragger {
+        let keystream: Vec<u8> = (0_u8..32).map(|i| i.wrapping_mul(37) ^ 0x5a).collect();
+        CribDragger::new(
+            PLAINTEXTS
+                .iter()
+                .map(|plaintext| {
+                    xor::exor::exor(*plaintext, &keystream[..plaintext.len()]).unwrap()
+                })
+                .collect(),
+        )
+    }
+
+    fn run_lines(dragger: &mut CribDragger, lines: &[&str]) -> (bool, String) {
+        let mut out: Vec<u8> = Vec::new();
+        let mut running = true;
+        for line in lines {
+            running = run(dragger, line, &mut out).unwrap();
+        }
+        (running, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn arguments_keeps_the_rest_verbatim() {
+        assert_eq!(
+            Some((vec!["0", "12"], "of  day ")),
+            arguments(" 0  12 of  day ", 2)
+        );
+        assert_eq!(Some((vec!["0", "12"], "")), arguments("0 12", 2));
+        assert_eq!(None, arguments("0", 2));
+        assert_eq!(None, arguments("", 1));
+        assert_eq!(Some((vec![], " x")), arguments(" x", 0));
+    }
+
+    #[test]
+    fn options_picks_the_encoding() {
+        let args = |args: &[&str]| -> Vec<String> { args.iter().map(|a| a.to_string()).collect() };
+        let files = args(&["a", "b"]);
+        assert_eq!(None, options(&files).unwrap().0);
+        assert_eq!(&files[..], options(&files).unwrap().1);
+        let hex = args(&["--hex", "a"]);
+        assert_eq!(Some(Encoding::Hex), options(&hex).unwrap().0);
+        assert_eq!(&hex[1..], options(&hex).unwrap().1);
+        let base64 = args(&["--base64", "a"]);
+        assert_eq!(Some(Encoding::Base64), options(&base64).unwrap().0);
+        assert!(options(&args(&["--hex", "--base64", "a"])).is_err());
+        assert!(options(&args(&["--utf8", "a"])).is_err());
+    }
+
+    #[test]
+    fn decode_picks_the_encoding_per_file() {
+        // "deadbeef" is valid hex and valid base64.
+        assert_eq!(
+            vec![vec![0xde, 0xad, 0xbe, 0xef], vec![0x00, 0xff]],
+            decode("deadbeef\n\n  00ff  \n", None).unwrap()
+        );
+        assert_eq!(
+            vec![
+                base64::decode("deadbeef").unwrap(),
+                base64::decode("SGVsbG8=").unwrap()
+            ],
+            decode("deadbeef\nSGVsbG8=\n", None).unwrap()
+        );
+        assert_eq!(
+            vec![base64::decode("deadbeef").unwrap()],
+            decode("deadbeef", Some(Encoding::Base64)).unwrap()
+        );
+        assert_eq!(
+            vec![vec![0xde, 0xad, 0xbe, 0xef]],
+            decode("deadbeef", Some(Encoding::Hex)).unwrap()
+        );
+        assert!(decode("SGVsbG8=", Some(Encoding::Hex)).is_err());
+        assert!(decode("", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn run_places_and_forgets_cribs() {
+        let mut dragger = dragger();
+        let (running, out) = run_lines(&mut dragger, &["place 0 0 I have met"]);
+        assert!(running);
+        assert_eq!(
+            format!(
+                "  0: I have met{}\n  1: Coming wit{}\n  2: From count{}\n",
+                "_".repeat(21),
+                "_".repeat(13),
+                "_".repeat(21)
+            ),
+            out
+        );
+        let (_, out) = run_lines(&mut dragger, &["forget 2 20", "keystream"]);
+        let expected = format!("5a7f{}", "__".repeat(29));
+        assert_eq!(Some(expected.as_str()), out.lines().last());
+    }
+
+    #[test]
+    fn run_drags_cribs() {
+        let mut dragger = dragger();
+        let (_, out) = run_lines(&mut dragger, &["drag counter"]);
+        assert!(out.contains("  2 @ 5 "));
+        assert!(out.lines().filter(|line| line.contains(" @ ")).count() <= TOP);
+    }
+
+    #[test]
+    fn run_reports_usage_and_quits() {
+        let mut dragger = dragger();
+        let (running, out) = run_lines(&mut dragger, &["place 0 0", "forget 1", "", "what"]);
+        assert!(running);
+        assert_eq!(
+            format!(
+                "usage: place INDEX OFFSET CRIB\nusage: forget OFFSET LENGTH\n{}\n",
+                HELP
+            ),
+            out
+        );
+        assert!(run(&mut dragger, "place x 0 crib", &mut Vec::new()).is_err());
+        assert!(run(&mut dragger, "place 5 0 crib", &mut Vec::new()).is_err());
+        assert!(!run_lines(&mut dragger, &["quit"]).0);
+        assert!(!run_lines(&mut dragger, &["exit"]).0);
+    }
+}
@@ -22,6 +22,7 @@ pub mod set02;
 pub mod set03;
 pub mod set04;
 pub mod set05;
+pub mod set07;
 
 #[cfg(test)]
 mod tests {
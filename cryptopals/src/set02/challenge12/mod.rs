@@ -1,3 +1,5 @@
+pub use aes::block_cipher::BlockCipher;
+pub use aes::ecb::AesEcbBlockCipher;
 pub use aes::error::AesError;
 pub use aes::key::AesKey;
 pub use oracles::encryption_oracle::{
@@ -5,12 +7,18 @@ pub use oracles::encryption_oracle::{
     EncryptionResult, PaddingAlgorithm,
 };
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Oracle {
-    pub(crate) context: EncryptionContext,
+#[derive(Clone, Debug)]
+pub struct Oracle<C: BlockCipher = AesEcbBlockCipher> {
+    pub(crate) context: EncryptionContext<C>,
 }
 
-impl DeterministicEncryptionOracle for Oracle {}
+impl<C: BlockCipher> PartialEq for Oracle<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.context == other.context
+    }
+}
+
+impl<C: BlockCipher> DeterministicEncryptionOracle for Oracle<C> {}
 
 impl Default for Oracle {
     fn default() -> Self {
@@ -27,7 +35,7 @@ impl Default for Oracle {
     }
 }
 
-impl EncryptionOracle for Oracle {
+impl<C: BlockCipher> EncryptionOracle for Oracle<C> {
     fn encrypt<I: std::iter::FromIterator<u8>, T: ?Sized + AsRef<[u8]>>(
         &self,
         input: &T,
@@ -38,8 +46,14 @@ impl EncryptionOracle for Oracle {
 
 impl Oracle {
     pub fn new(key: &[u8], prefix: &[u8], suffix: &[u8]) -> Self {
+        Self::with_block_cipher(key, prefix, suffix)
+    }
+}
+
+impl<C: BlockCipher> Oracle<C> {
+    pub fn with_block_cipher(key: &[u8], prefix: &[u8], suffix: &[u8]) -> Self {
         Self {
-            context: EncryptionContext::new(
+            context: EncryptionContext::with_block_cipher(
                 key,
                 prefix,
                 suffix,
@@ -70,7 +84,7 @@ impl Oracle {
         let ciphertext: Vec<u8> = self.encrypt(&input)?;
         let prefix_blocks = self.count_prefix_blocks(block_size, byte0, byte1)?;
         let blocks: Vec<&[u8]> = ciphertext
-            .chunks(16)
+            .chunks(block_size)
             .skip(prefix_blocks + 1)
             .take(2)
             .collect();
@@ -213,6 +227,7 @@ impl Oracle {
 #[cfg(test)]
 mod tests {
     use super::{AesKey, Oracle};
+    use aes::toy_spn::ToySpnBlockCipher;
 
     #[test]
     fn static_encryption_oracle_decrypt_byte_at_a_time_aes_ecb_mode() {
@@ -247,6 +262,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn static_encryption_oracle_decrypt_byte_at_a_time_toy_spn_ecb_mode() {
+        let key = b"YELLOW SUBMARINE";
+        let suffix = b"Rollin' in my 5.0 with my rag-top down";
+        let oracle = Oracle::<ToySpnBlockCipher>::with_block_cipher(key, b"", suffix);
+        let block_size = oracle.detect_block_size(0_u8).unwrap();
+        assert_eq!(8, block_size);
+        assert!(oracle
+            .detect_uses_aes_ecb_mode(block_size, 0_u8, 1_u8)
+            .unwrap());
+        let (prefix_size, suffix_size) = oracle
+            .detect_prefix_size_and_suffix_size(block_size, 0_u8, 1_u8)
+            .unwrap();
+        assert_eq!((0, suffix.len()), (prefix_size, suffix_size));
+        assert_eq!(
+            &suffix[..],
+            &oracle
+                .decrypt_suffix(block_size, prefix_size, suffix_size)
+                .unwrap()[..]
+        );
+    }
+
     #[ignore]
     #[quickcheck]
     fn random_encryption_oracle_decrypt_byte_at_a_time_aes_ecb_mode(
@@ -0,0 +1,144 @@
+pub use aes::cbc::{decrypt as aes_cbc_decrypt, encrypt as aes_cbc_encrypt, AesCbcIv};
+pub use aes::key::AesKey;
+pub use oracles::padding_oracle::{
+    PaddingOracle, PaddingOracleAttack, PaddingOracleError, PaddingOracleResult,
+};
+pub use pkcs7;
+
+use rand::prelude::*;
+
+pub const PLAINTEXTS: [&str; 10] = [
+    "MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc=",
+    "MDAwMDAxV2l0aCB0aGUgYmFzcyBraWNrZWQgaW4gYW5kIHRoZSBWZWdhJ3MgYXJlIHB1bXBpbic=",
+    "MDAwMDAyUXVpY2sgdG8gdGhlIHBvaW50LCB0byB0aGUgcG9pbnQsIG5vIGZha2luZw==",
+    "MDAwMDAzQ29va2luZyBNQydzIGxpa2UgYSBwb3VuZCBvZiBiYWNvbg==",
+    "MDAwMDA0QnVybmluZyAnZW0sIGlmIHlvdSBhaW4ndCBxdWljayBhbmQgbmltYmxl",
+    "MDAwMDA1SSBnbyBjcmF6eSB3aGVuIEkgaGVhciBhIGN5bWJhbA==",
+    "MDAwMDA2QW5kIGEgaGlnaCBoYXQgd2l0aCBhIHNvdXBlZCB1cCB0ZW1wbw==",
+    "MDAwMDA3SSdtIG9uIGEgcm9sbCwgaXQncyB0aW1lIHRvIGdvIHNvbG8=",
+    "MDAwMDA4b2xsaW4nIGluIG15IGZpdmUgcG9pbnQgb2g=",
+    "MDAwMDA5aXRoIG15IHJhZy10b3AgZG93biBzbyBteSBoYWlyIGNhbiBibG93",
+];
+
+#[derive(Clone, Debug)]
+pub struct Oracle {
+    key: AesKey,
+}
+
+impl Default for Oracle {
+    fn default() -> Self {
+        Self::random()
+    }
+}
+
+impl PaddingOracle for Oracle {
+    fn check<IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        iv: &IV,
+        ciphertext: &T,
+    ) -> PaddingOracleResult<bool> {
+        let plaintext: Vec<u8> = aes_cbc_decrypt(self.key.as_slice(), iv, ciphertext)?;
+        Ok(pkcs7::unpad::<Vec<u8>, _>(&plaintext, 16).is_ok())
+    }
+}
+
+impl Oracle {
+    pub fn random() -> Self {
+        let mut csprng = thread_rng();
+        Self::new(csprng.gen())
+    }
+
+    pub fn new(key: AesKey) -> Self {
+        Self { key }
+    }
+
+    pub fn encrypt<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        plaintext: &T,
+    ) -> PaddingOracleResult<(Vec<u8>, Vec<u8>)> {
+        let mut csprng = thread_rng();
+        let iv: AesCbcIv = csprng.gen();
+        let plaintext: Vec<u8> = pkcs7::pad(plaintext, 16)?;
+        let ciphertext: Vec<u8> = aes_cbc_encrypt(self.key.as_slice(), iv.as_slice(), &plaintext)?;
+        Ok((iv.to_vec(), ciphertext))
+    }
+
+    pub fn encrypt_random_plaintext(&self) -> PaddingOracleResult<(Vec<u8>, Vec<u8>)> {
+        let mut csprng = thread_rng();
+        let plaintext: Vec<u8> = base64::decode(PLAINTEXTS.choose(&mut csprng).unwrap())?;
+        self.encrypt(&plaintext)
+    }
+
+    pub fn decrypt<IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        iv: &IV,
+        ciphertext: &T,
+    ) -> PaddingOracleResult<Vec<u8>> {
+        let mut plaintext: Vec<u8> = aes_cbc_decrypt(self.key.as_slice(), iv, ciphertext)?;
+        pkcs7::unpad_mut(&mut plaintext, 16)?;
+        Ok(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_cbc_padding_oracle() {
+        let oracle = Oracle::default();
+        for encoded in PLAINTEXTS.iter() {
+            let plaintext: Vec<u8> = base64::decode(encoded).unwrap();
+            let (iv, ciphertext) = oracle.encrypt(&plaintext).unwrap();
+            let mut attack = PaddingOracleAttack::new(&oracle);
+            let mut challenge: Vec<u8> = attack.decrypt(&iv, &ciphertext).unwrap();
+            pkcs7::unpad_mut(&mut challenge, 16).unwrap();
+            assert_eq!(plaintext, challenge);
+            // At most 256 guesses per byte, plus confirmations of the last byte.
+            assert!(attack.queries() <= ciphertext.len() * 256 + ciphertext.len() / 16 * 256);
+        }
+    }
+
+    #[test]
+    fn the_cbc_padding_oracle_with_ambiguous_last_byte() {
+        // The final block decrypts to `...\x02\x01`, so a guess producing
+        // `\x02\x02` is also accepted by the oracle.
+        let oracle = Oracle::default();
+        let plaintext: Vec<u8> = b"YELLOW SUBMARINE-ambiguous pad\x02".to_vec();
+        let (iv, ciphertext) = oracle.encrypt(&plaintext).unwrap();
+        let challenge: Vec<u8> = oracle.decrypt(&iv, &ciphertext).unwrap();
+        assert_eq!(plaintext, challenge);
+        let challenge: Vec<u8> = PaddingOracle::decrypt(&oracle, &iv, &ciphertext).unwrap();
+        assert_eq!(plaintext, challenge);
+    }
+
+    #[test]
+    fn the_cbc_padding_oracle_forgery() {
+        let oracle = Oracle::default();
+        let plaintext = b"comment1=cooking%20MCs;userdata=x;admin=true;comment2=%20like";
+        let (iv, ciphertext) = oracle.forge(&plaintext).unwrap();
+        let challenge: Vec<u8> = oracle.decrypt(&iv, &ciphertext).unwrap();
+        assert_eq!(&plaintext[..], &challenge[..]);
+    }
+
+    #[quickcheck]
+    fn the_cbc_padding_oracle_property(key: AesKey, index: usize) -> bool {
+        let oracle = Oracle::new(key);
+        let plaintext: Vec<u8> = base64::decode(PLAINTEXTS[index % PLAINTEXTS.len()]).unwrap();
+        let (iv, ciphertext) = oracle.encrypt(&plaintext).unwrap();
+        let challenge: Vec<u8> = PaddingOracle::decrypt(&oracle, &iv, &ciphertext).unwrap();
+        plaintext == challenge
+    }
+
+    #[quickcheck]
+    fn the_cbc_padding_oracle_forgery_property(key: AesKey, plaintext: Vec<u8>) -> bool {
+        let oracle = Oracle::new(key);
+        match oracle.forge(&plaintext) {
+            Ok((iv, ciphertext)) => plaintext == oracle.decrypt(&iv, &ciphertext).unwrap(),
+            Err(error) => {
+                plaintext.is_empty()
+                    && error.to_string() == PaddingOracleError::EmptyForgery.to_string()
+            }
+        }
+    }
+}
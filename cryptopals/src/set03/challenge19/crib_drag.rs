@@ -0,0 +1,296 @@
+// Crib dragging for ciphertexts that share a keystream
+//
+// XORing two ciphertexts encrypted under the same keystream cancels it out,
+// leaving the XOR of the two plaintexts. Sliding a guessed word (a crib)
+// along that reveals the other plaintext wherever the guess is in the right
+// place, so each placement is ranked by how much the revealed text looks like
+// English. Accepting a placement recovers that part of the keystream, which
+// then decrypts every ciphertext at those offsets.
+
+use xor::exor::exor;
+
+use crate::set01::challenge03::english_distribution;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CribDragError {
+    InvalidCiphertextIndex {
+        was: usize,
+        count: usize,
+    },
+    CribOutOfBounds {
+        offset: usize,
+        length: usize,
+        available: usize,
+    },
+}
+
+// This is important for other errors to wrap this one.
+impl std::error::Error for CribDragError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl std::fmt::Display for CribDragError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CribDragError::InvalidCiphertextIndex { was, count } => write!(
+                f,
+                "invalid ciphertext index {} (there are {} ciphertexts)",
+                was, count
+            ),
+            CribDragError::CribOutOfBounds {
+                offset,
+                length,
+                available,
+            } => write!(
+                f,
+                "crib of length {} at offset {} does not fit in {} bytes",
+                length, offset, available
+            ),
+        }
+    }
+}
+
+// A crib assumed to be in ciphertext `index` at `offset`, with the text it
+// reveals in each of the other ciphertexts that overlap it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CribPlacement {
+    pub score: f64,
+    pub index: usize,
+    pub offset: usize,
+    pub keystream: Vec<u8>,
+    pub revealed: Vec<(usize, Vec<u8>)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CribDragger {
+    ciphertexts: Vec<Vec<u8>>,
+    keystream: Vec<Option<u8>>,
+}
+
+impl CribDragger {
+    pub fn new(ciphertexts: Vec<Vec<u8>>) -> CribDragger {
+        let keysize = ciphertexts.iter().map(|c| c.len()).max().unwrap_or(0);
+        CribDragger {
+            ciphertexts,
+            keystream: vec![None; keysize],
+        }
+    }
+
+    pub fn ciphertexts(&self) -> &[Vec<u8>] {
+        &self.ciphertexts
+    }
+
+    // The recovered keystream so far, `None` where it is still unknown.
+    pub fn keystream(&self) -> &[Option<u8>] {
+        &self.keystream
+    }
+
+    // Every placement of `crib` in every ciphertext that only reveals
+    // human-readable text, best first.
+    pub fn drag<T: ?Sized + AsRef<[u8]>>(&self, crib: &T) -> Vec<CribPlacement> {
+        let mut placements: Vec<CribPlacement> = (0..self.ciphertexts.len())
+            .flat_map(|index| self.drag_in(index, crib))
+            .collect();
+        placements.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        placements
+    }
+
+    // Like `drag`, for placements in ciphertext `index` only.
+    pub fn drag_in<T: ?Sized + AsRef<[u8]>>(&self, index: usize, crib: &T) -> Vec<CribPlacement> {
+        let crib = crib.as_ref();
+        let length = match self.ciphertexts.get(index) {
+            Some(ciphertext) if !crib.is_empty() && ciphertext.len() >= crib.len() => {
+                ciphertext.len()
+            }
+            _ => return Vec::new(),
+        };
+        let mut placements: Vec<CribPlacement> = (0..=(length - crib.len()))
+            .filter_map(|offset| self.placement(index, offset, crib))
+            .collect();
+        placements.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        placements
+    }
+
+    fn placement(&self, index: usize, offset: usize, crib: &[u8]) -> Option<CribPlacement> {
+        let ciphertext = &self.ciphertexts[index][offset..(offset + crib.len())];
+        let mut revealed: Vec<(usize, Vec<u8>)> = Vec::new();
+        for (other, c) in self.ciphertexts.iter().enumerate() {
+            let n = std::cmp::min(crib.len(), c.len().saturating_sub(offset));
+            if other == index || n == 0 {
+                continue;
+            }
+            let plaintexts: Vec<u8> = exor(&ciphertext[..n], &c[offset..(offset + n)]).ok()?;
+            revealed.push((other, exor(&plaintexts, &crib[..n]).ok()?));
+        }
+        let text: Vec<u8> = revealed
+            .iter()
+            .flat_map(|(_, r)| r.iter().copied())
+            .collect();
+        let score = english_distribution::ascii_histogram(&text)?.score();
+        if revealed.is_empty() || score <= 0.0_f64 {
+            return None;
+        }
+        Some(CribPlacement {
+            score,
+            index,
+            offset,
+            keystream: exor(ciphertext, crib).ok()?,
+            revealed,
+        })
+    }
+
+    // Records the keystream implied by `crib` being in ciphertext `index` at
+    // `offset`, replacing whatever was known there before.
+    pub fn place<T: ?Sized + AsRef<[u8]>>(
+        &mut self,
+        index: usize,
+        offset: usize,
+        crib: &T,
+    ) -> Result<(), CribDragError> {
+        let crib = crib.as_ref();
+        let ciphertext =
+            self.ciphertexts
+                .get(index)
+                .ok_or(CribDragError::InvalidCiphertextIndex {
+                    was: index,
+                    count: self.ciphertexts.len(),
+                })?;
+        if offset + crib.len() > ciphertext.len() {
+            return Err(CribDragError::CribOutOfBounds {
+                offset,
+                length: crib.len(),
+                available: ciphertext.len(),
+            });
+        }
+        let keystream: Vec<u8> = exor(&ciphertext[offset..(offset + crib.len())], crib).unwrap();
+        for (known, byte) in self.keystream[offset..].iter_mut().zip(keystream) {
+            *known = Some(byte);
+        }
+        Ok(())
+    }
+
+    // Marks `length` keystream bytes from `offset` as unknown again.
+    pub fn forget(&mut self, offset: usize, length: usize) {
+        let end = std::cmp::min(self.keystream.len(), offset.saturating_add(length));
+        if offset < end {
+            self.keystream[offset..end].fill(None);
+        }
+    }
+
+    // Every ciphertext decrypted as far as the keystream is known.
+    pub fn decrypt(&self) -> Vec<Vec<Option<u8>>> {
+        self.ciphertexts
+            .iter()
+            .map(|ciphertext| {
+                ciphertext
+                    .iter()
+                    .zip(self.keystream.iter())
+                    .map(|(&c, k)| k.map(|k| c ^ k))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Ciphertext `index` decrypted for display: unknown bytes are shown as
+    // `_` and unprintable ones as `.`.
+    pub fn render(&self, index: usize) -> Option<String> {
+        let ciphertext = self.ciphertexts.get(index)?;
+        Some(
+            ciphertext
+                .iter()
+                .zip(self.keystream.iter())
+                .map(|(&c, k)| match k.map(|k| c ^ k) {
+                    None => '_',
+                    Some(byte) if byte == b' ' || byte.is_ascii_graphic() => byte as char,
+                    Some(_) => '.',
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::ctr::{AesCtrCipher, AesCtrIv, AesCtrMode};
+    use aes::key::AesKey;
+
+    const PLAINTEXTS: [&[u8]; 4] = [
+        b"I have met them at close of day",
+        b"Coming with vivid faces",
+        b"From counter or desk among grey",
+        b"Eighteenth-century houses.",
+    ];
+
+    fn dragger() -> CribDragger {
+        let key = AesKey::aes_128_key([0x2b; 16]);
+        let iv = AesCtrIv::aes_ctr_iv([0x00; 16]);
+        let cipher = AesCtrCipher::new(&key, &iv, AesCtrMode::CRYPTOPALS);
+        CribDragger::new(
+            PLAINTEXTS
+                .iter()
+                .map(|plaintext| cipher.encrypt(plaintext).unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn drag_finds_the_right_placement() {
+        let dragger = dragger();
+        let placements = dragger.drag("counter");
+        let placement = placements
+            .iter()
+            .find(|p| p.index == 2 && p.offset == 5)
+            .unwrap();
+        for (other, revealed) in placement.revealed.iter() {
+            assert_eq!(&PLAINTEXTS[*other][5..12], &revealed[..]);
+        }
+        assert!(placements
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn place_extends_the_keystream() -> Result<(), CribDragError> {
+        let mut dragger = dragger();
+        assert_eq!(Some("_".repeat(23)), dragger.render(1));
+        dragger.place(0, 0, "I have")?;
+        dragger.place(3, 6, "enth-century ")?;
+        let expected: Vec<Option<u8>> = PLAINTEXTS[1]
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| if i < 19 { Some(byte) } else { None })
+            .collect();
+        assert_eq!(expected, dragger.decrypt()[1]);
+        assert_eq!(
+            Some("Coming with vivid f____".to_string()),
+            dragger.render(1)
+        );
+        dragger.forget(2, 4);
+        assert_eq!(
+            Some("Co____ with vivid f____".to_string()),
+            dragger.render(1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn place_rejects_bad_placements() {
+        let mut dragger = dragger();
+        assert_eq!(
+            Err(CribDragError::InvalidCiphertextIndex { was: 4, count: 4 }),
+            dragger.place(4, 0, "I")
+        );
+        assert_eq!(
+            Err(CribDragError::CribOutOfBounds {
+                offset: 20,
+                length: 4,
+                available: 23
+            }),
+            dragger.place(1, 20, "aces")
+        );
+    }
+}
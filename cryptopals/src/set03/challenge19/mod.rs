@@ -1,41 +1,58 @@
 // pub use aes::ctr::{decrypt_nist_sp800_38a as aes_ctr_decrypt, encrypt_nist_sp800_38a as aes_ctr_encrypt};
 pub use aes::ctr::{decrypt as aes_ctr_decrypt, encrypt as aes_ctr_encrypt};
-pub use aes::ctr::{AesCtrCipher, AesCtrIv, AesCtrMode};
+pub use aes::ctr::{AesCtrCipher, AesCtrIv, AesCtrKeystream, AesCtrMode};
 pub use aes::error::AesError;
 pub use aes::key::AesKey;
+pub use xor::keystream::Keystream;
 
 pub use crate::set01::challenge06::RepeatingKeyXorSolver;
 
+pub mod crib_drag;
+
+// Recovers the keystream shared by messages that were all encrypted from the
+// start of the same keystream, as with a reused CTR nonce. `keystream` is
+// cloned for every message, so it must be positioned at the start.
 #[derive(Clone, Debug)]
-pub struct FixedNonceCtrSolver {
-    pub key: AesKey,
-    pub iv: AesCtrIv,
-    pub mode: AesCtrMode,
+pub struct FixedNonceSolver<K> {
+    pub keystream: K,
     pub extra: usize,
     pub plaintexts: Vec<Vec<u8>>,
     pub ciphertexts: Vec<Vec<u8>>,
 }
 
+pub type FixedNonceCtrSolver = FixedNonceSolver<AesCtrKeystream<'static>>;
+
 impl FixedNonceCtrSolver {
     pub fn new<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>>(
         key: &K,
         iv: &IV,
         mode: AesCtrMode,
     ) -> Result<FixedNonceCtrSolver, AesError> {
-        Ok(FixedNonceCtrSolver {
-            key: AesKey::try_copy_from_slice(key)?,
-            iv: AesCtrIv::try_copy_from_slice(iv)?,
-            mode,
+        let key = AesKey::try_copy_from_slice(key)?;
+        let iv = AesCtrIv::try_copy_from_slice(iv)?;
+        Ok(Self::from_keystream(AesCtrKeystream::new(&key, &iv, mode)?))
+    }
+}
+
+impl<K: Keystream + Clone> FixedNonceSolver<K> {
+    pub fn from_keystream(keystream: K) -> FixedNonceSolver<K> {
+        FixedNonceSolver {
+            keystream,
             extra: 0,
             plaintexts: Vec::new(),
             ciphertexts: Vec::new(),
-        })
+        }
+    }
+
+    fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let mut ciphertext = plaintext.to_vec();
+        self.keystream.clone().apply(&mut ciphertext).unwrap();
+        ciphertext
     }
 
     pub fn push<T: ?Sized + AsRef<[u8]>>(&mut self, plaintext: &T) {
-        let cipher = AesCtrCipher::new(&self.key, &self.iv, self.mode);
         let plaintext: Vec<u8> = plaintext.as_ref().to_vec();
-        let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+        let ciphertext: Vec<u8> = self.encrypt(&plaintext);
         self.plaintexts.push(plaintext);
         self.ciphertexts.push(ciphertext);
     }
@@ -106,12 +123,11 @@ impl FixedNonceCtrSolver {
     }
 }
 
-impl Extend<Vec<u8>> for FixedNonceCtrSolver {
+impl<K: Keystream + Clone> Extend<Vec<u8>> for FixedNonceSolver<K> {
     #[inline]
     fn extend<I: IntoIterator<Item = Vec<u8>>>(&mut self, iter: I) {
-        let cipher = AesCtrCipher::new(&self.key, &self.iv, self.mode);
         for plaintext in iter.into_iter() {
-            let ciphertext: Vec<u8> = cipher.encrypt(&plaintext).unwrap();
+            let ciphertext: Vec<u8> = self.encrypt(&plaintext);
             self.plaintexts.push(plaintext);
             self.ciphertexts.push(ciphertext);
         }
@@ -163,4 +179,19 @@ mod tests {
         let decrypted = solver.decrypt(&keystream);
         assert_eq!(solver.plaintexts, decrypted);
     }
+
+    #[ignore]
+    #[test]
+    fn break_fixed_seed_mt19937_keystream() {
+        use crate::set03::challenge24::MersenneTwister19937Keystream;
+        use rand::prelude::*;
+        let seed: u32 = thread_rng().gen();
+        let keystream = MersenneTwister19937Keystream::new(seed);
+        let mut solver = FixedNonceSolver::from_keystream(keystream);
+        solver.extend(TEST_VECTORS.iter().cloned());
+        solver.generate_extra();
+        let keystream = solver.keystream().unwrap();
+        let decrypted = solver.decrypt(&keystream);
+        assert_eq!(solver.plaintexts, decrypted);
+    }
 }
@@ -1,12 +1,14 @@
 pub use std::time::{Duration, SystemTime};
 
 pub use crate::set03::challenge21::*;
+pub use xor::keystream::{Keystream, KeystreamSeekError};
 
 #[derive(Clone, Debug)]
 pub struct MersenneTwister19937Keystream {
     mt: MersenneTwister19937,
     data: [u8; 4],
     offset: usize,
+    position: u64,
 }
 
 impl MersenneTwister19937Keystream {
@@ -73,10 +75,32 @@ impl From<MersenneTwister19937> for MersenneTwister19937Keystream {
             mt,
             data: [0_u8; 4],
             offset: 4,
+            position: 0,
         }
     }
 }
 
+// The generator cannot be rewound, so only seeking forward is supported.
+impl Keystream for MersenneTwister19937Keystream {
+    fn apply(&mut self, input: &mut [u8]) -> xor::Result<()> {
+        input.iter_mut().zip(self).for_each(|(c, k)| {
+            *c ^= k;
+        });
+        Ok(())
+    }
+
+    fn seek(&mut self, position: u64) -> xor::Result<()> {
+        if position < self.position {
+            return Err(KeystreamSeekError { position }.into());
+        }
+        let skip = position - self.position;
+        if skip > 0 {
+            self.nth((skip - 1) as usize);
+        }
+        Ok(())
+    }
+}
+
 impl Iterator for MersenneTwister19937Keystream {
     type Item = u8;
     fn next(&mut self) -> Option<Self::Item> {
@@ -86,6 +110,7 @@ impl Iterator for MersenneTwister19937Keystream {
         }
         let output = self.data[self.offset];
         self.offset += 1;
+        self.position += 1;
         Some(output)
     }
 }
@@ -95,6 +120,7 @@ pub struct MersenneTwister19937_64Keystream {
     mt: MersenneTwister19937_64,
     data: [u8; 8],
     offset: usize,
+    position: u64,
 }
 
 impl MersenneTwister19937_64Keystream {
@@ -161,10 +187,32 @@ impl From<MersenneTwister19937_64> for MersenneTwister19937_64Keystream {
             mt,
             data: [0_u8; 8],
             offset: 8,
+            position: 0,
         }
     }
 }
 
+// The generator cannot be rewound, so only seeking forward is supported.
+impl Keystream for MersenneTwister19937_64Keystream {
+    fn apply(&mut self, input: &mut [u8]) -> xor::Result<()> {
+        input.iter_mut().zip(self).for_each(|(c, k)| {
+            *c ^= k;
+        });
+        Ok(())
+    }
+
+    fn seek(&mut self, position: u64) -> xor::Result<()> {
+        if position < self.position {
+            return Err(KeystreamSeekError { position }.into());
+        }
+        let skip = position - self.position;
+        if skip > 0 {
+            self.nth((skip - 1) as usize);
+        }
+        Ok(())
+    }
+}
+
 impl Iterator for MersenneTwister19937_64Keystream {
     type Item = u8;
     fn next(&mut self) -> Option<Self::Item> {
@@ -174,6 +222,7 @@ impl Iterator for MersenneTwister19937_64Keystream {
         }
         let output = self.data[self.offset];
         self.offset += 1;
+        self.position += 1;
         Some(output)
     }
 }
@@ -414,4 +463,38 @@ mod tests {
         let challenge: Vec<u8> = keystream.decrypt(&ciphertext);
         plaintext == challenge
     }
+
+    #[quickcheck]
+    fn mt19937_keystream_seek_matches_skip(seed: u32, position: u16, length: u8) -> bool {
+        let expected: Vec<u8> = MersenneTwister19937Keystream::new(seed)
+            .skip(position as usize)
+            .take(length as usize)
+            .collect();
+        let mut keystream = MersenneTwister19937Keystream::new(seed);
+        let mut challenge = vec![0xff_u8; length as usize];
+        keystream.seek(position as u64).unwrap();
+        keystream.fill(&mut challenge).unwrap();
+        expected == challenge && keystream.seek(position as u64 + length as u64).is_ok()
+    }
+
+    #[quickcheck]
+    fn mt19937_64_keystream_seek_matches_skip(seed: u64, position: u16, length: u8) -> bool {
+        let expected: Vec<u8> = MersenneTwister19937_64Keystream::new(seed)
+            .skip(position as usize)
+            .take(length as usize)
+            .collect();
+        let mut keystream = MersenneTwister19937_64Keystream::new(seed);
+        let mut challenge = vec![0xff_u8; length as usize];
+        keystream.seek(position as u64).unwrap();
+        keystream.fill(&mut challenge).unwrap();
+        expected == challenge && keystream.seek(position as u64 + length as u64).is_ok()
+    }
+
+    #[test]
+    fn mt19937_keystream_cannot_seek_backwards() {
+        let mut keystream = MersenneTwister19937Keystream::new(5489);
+        keystream.fill(&mut [0_u8; 7]).unwrap();
+        assert!(keystream.seek(6).is_err());
+        assert!(keystream.seek(7).is_ok());
+    }
 }
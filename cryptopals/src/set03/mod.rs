@@ -1,3 +1,4 @@
+pub mod challenge17;
 pub mod challenge18;
 pub mod challenge19;
 pub mod challenge20;
@@ -11,15 +11,6 @@ impl Default for Md4Mac {
     }
 }
 
-fn constant_time_compare<A: ?Sized + AsRef<[u8]>, B: ?Sized + AsRef<[u8]>>(a: &A, b: &B) -> bool {
-    let a = a.as_ref();
-    let b = b.as_ref();
-    if a.len() != b.len() {
-        return false;
-    }
-    a.iter().zip(b.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
-}
-
 impl Md4Mac {
     pub fn random() -> Self {
         use rand::prelude::*;
@@ -47,7 +38,7 @@ impl Md4Mac {
         code: &C,
     ) -> bool {
         let challenge = self.authenticate(message);
-        constant_time_compare(&challenge, code)
+        xor::ct::ct_eq(&challenge, code)
     }
 
     pub fn get_key_size(&self) -> usize {
@@ -0,0 +1,311 @@
+pub use aes::cbc::AesCbcIv;
+pub use aes::key::AesKey;
+pub use mac::cbc_mac::{aes_cbc_mac, aes_cbc_mac_verify, CbcMacOutput};
+pub use xor::exor;
+
+use rand::prelude::*;
+
+pub type BankResult<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+// Account ids are always written with two digits, so every message starts
+// with a `from=NN&` field that sits entirely inside the first block.
+pub type AccountId = u8;
+
+pub const FIXED_IV: [u8; 16] = [0_u8; 16];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub from: AccountId,
+    pub to: AccountId,
+    pub amount: u64,
+}
+
+// The API server. It shares `key` with the legitimate web clients, which
+// only ever sign requests spending from the logged-in account; the server
+// trusts anything that carries a valid CBC-MAC.
+#[derive(Clone, Debug)]
+pub struct Server {
+    key: AesKey,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::random()
+    }
+}
+
+impl Server {
+    pub fn random() -> Self {
+        let mut csprng = thread_rng();
+        Self::new(csprng.gen())
+    }
+
+    pub fn new(key: AesKey) -> Self {
+        Self { key }
+    }
+
+    pub fn client(&self, account: AccountId) -> Client<'_> {
+        assert!(account < 100, "account ids have two digits");
+        Client {
+            server: self,
+            account,
+        }
+    }
+
+    // Part one: `message || IV || MAC`, where the client picks the IV.
+    pub fn process_transfer<T: ?Sized + AsRef<[u8]>>(&self, request: &T) -> BankResult<Transfer> {
+        let request = request.as_ref();
+        if request.len() < 32 {
+            return Err("request too short".into());
+        }
+        let (message, trailer) = request.split_at(request.len() - 32);
+        let (iv, tag) = trailer.split_at(16);
+        if !aes_cbc_mac_verify(self.key.as_slice(), iv, message, tag)? {
+            return Err("invalid MAC".into());
+        }
+        let fields = parse_fields(message);
+        Ok(Transfer {
+            from: parse_field(&fields, b"from")?,
+            to: parse_field(&fields, b"to")?,
+            amount: parse_field(&fields, b"amount")?,
+        })
+    }
+
+    // Part two: `message || MAC` under `FIXED_IV`. Malformed entries in the
+    // transaction list are skipped rather than rejected.
+    pub fn process_transaction_list<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        request: &T,
+    ) -> BankResult<Vec<Transfer>> {
+        let request = request.as_ref();
+        if request.len() < 16 {
+            return Err("request too short".into());
+        }
+        let (message, tag) = request.split_at(request.len() - 16);
+        if !aes_cbc_mac_verify(self.key.as_slice(), &FIXED_IV, message, tag)? {
+            return Err("invalid MAC".into());
+        }
+        let fields = parse_fields(message);
+        let from: AccountId = parse_field(&fields, b"from")?;
+        let tx_list = find_field(&fields, b"tx_list").ok_or("missing field 'tx_list'")?;
+        Ok(tx_list
+            .split(|&c| c == b';')
+            .filter_map(|entry| {
+                let mut parts = entry.splitn(2, |&c| c == b':');
+                let to = parse_number(parts.next()?)?;
+                let amount = parse_number(parts.next()?)?;
+                Some(Transfer { from, to, amount })
+            })
+            .collect())
+    }
+
+    fn sign(&self, iv: &[u8], message: &[u8]) -> Vec<u8> {
+        aes_cbc_mac(self.key.as_slice(), iv, message)
+            .unwrap()
+            .bytes()
+            .to_vec()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Client<'s> {
+    server: &'s Server,
+    account: AccountId,
+}
+
+impl<'s> Client<'s> {
+    pub fn account(&self) -> AccountId {
+        self.account
+    }
+
+    // Signs `from=#{account}&to=#{to}&amount=#{amount}` under a random IV.
+    pub fn transfer(&self, to: AccountId, amount: u64) -> Vec<u8> {
+        let message = format!("from={:02}&to={:02}&amount={}", self.account, to, amount);
+        let mut iv = [0_u8; 16];
+        thread_rng().fill_bytes(&mut iv);
+        let tag = self.server.sign(&iv, message.as_bytes());
+        [message.as_bytes(), &iv[..], &tag[..]].concat()
+    }
+
+    // Signs `from=#{account}&tx_list=#{to:amount(;to:amount)*}`.
+    pub fn transaction_list(&self, transactions: &[(AccountId, u64)]) -> Vec<u8> {
+        let tx_list: Vec<String> = transactions
+            .iter()
+            .map(|(to, amount)| format!("{:02}:{}", to, amount))
+            .collect();
+        let message = format!("from={:02}&tx_list={}", self.account, tx_list.join(";"));
+        let tag = self.server.sign(&FIXED_IV, message.as_bytes());
+        [message.as_bytes(), &tag[..]].concat()
+    }
+}
+
+// Rewrites the `from` field of a transfer the attacker signed for their own
+// account. Only the first block changes, so XOR-ing the same difference into
+// the IV leaves the CBC-MAC untouched.
+pub fn forge_transfer_with_iv<T: ?Sized + AsRef<[u8]>>(
+    request: &T,
+    victim: AccountId,
+) -> BankResult<Vec<u8>> {
+    let request = request.as_ref();
+    if request.len() < 48 {
+        return Err("request too short".into());
+    }
+    let message_len = request.len() - 32;
+    let mut forged = request.to_vec();
+    let field = format!("from={:02}", victim);
+    let (message, trailer) = forged.split_at_mut(message_len);
+    let (iv, _) = trailer.split_at_mut(16);
+    for (i, &byte) in field.as_bytes().iter().enumerate() {
+        iv[i] ^= message[i] ^ byte;
+        message[i] = byte;
+    }
+    Ok(forged)
+}
+
+// Length extension under a fixed IV: `captured` is a transaction list signed
+// for the victim and `own` one signed for the attacker. Appending
+// `own[0] ^ MAC(captured)` and the rest of `own` to the padded victim
+// message chains into the same state as `own`, so its MAC carries over.
+//
+// The first entry of `own` after its first block is fused with the glue
+// block and lost, so the attacker should lead with a throwaway transaction.
+// Returns `None` when the glue block contains `&`, which would end the
+// `tx_list` field early; another capture is then needed.
+pub fn forge_transaction_list<C: ?Sized + AsRef<[u8]>, O: ?Sized + AsRef<[u8]>>(
+    captured: &C,
+    own: &O,
+) -> BankResult<Option<Vec<u8>>> {
+    let captured = captured.as_ref();
+    let own = own.as_ref();
+    if captured.len() < 16 || own.len() < 32 {
+        return Err("request too short".into());
+    }
+    let (victim_message, victim_tag) = captured.split_at(captured.len() - 16);
+    let (own_message, own_tag) = own.split_at(own.len() - 16);
+    let glue: Vec<u8> = exor::exor(&own_message[..16], &victim_tag)?;
+    if glue.contains(&b'&') {
+        return Ok(None);
+    }
+    let mut forged = victim_message.to_vec();
+    let pad = 16 - forged.len() % 16;
+    forged.resize(forged.len() + pad, pad as u8);
+    forged.extend_from_slice(&glue);
+    forged.extend_from_slice(&own_message[16..]);
+    forged.extend_from_slice(own_tag);
+    Ok(Some(forged))
+}
+
+fn parse_fields(message: &[u8]) -> Vec<(&[u8], &[u8])> {
+    message
+        .split(|&c| c == b'&')
+        .filter_map(|field| {
+            let mut parts = field.splitn(2, |&c| c == b'=');
+            Some((parts.next()?, parts.next()?))
+        })
+        .collect()
+}
+
+fn find_field<'m>(fields: &[(&[u8], &'m [u8])], name: &[u8]) -> Option<&'m [u8]> {
+    fields
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| *value)
+}
+
+fn parse_field<T: std::str::FromStr>(fields: &[(&[u8], &[u8])], name: &[u8]) -> BankResult<T> {
+    let value = find_field(fields, name)
+        .ok_or_else(|| format!("missing field '{}'", String::from_utf8_lossy(name)))?;
+    parse_number(value)
+        .ok_or_else(|| format!("invalid field '{}'", String::from_utf8_lossy(name)).into())
+}
+
+fn parse_number<T: std::str::FromStr>(value: &[u8]) -> Option<T> {
+    if value.is_empty() || !value.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VICTIM: AccountId = 17;
+    const ATTACKER: AccountId = 42;
+
+    #[test]
+    fn forge_a_transfer_with_an_attacker_controlled_iv() {
+        let server = Server::default();
+        let attacker = server.client(ATTACKER);
+        let request = attacker.transfer(ATTACKER, 1_000_000);
+        assert_eq!(
+            Transfer {
+                from: ATTACKER,
+                to: ATTACKER,
+                amount: 1_000_000
+            },
+            server.process_transfer(&request).unwrap()
+        );
+        // Editing the message alone breaks the MAC.
+        let mut tampered = request.clone();
+        tampered[5..7].copy_from_slice(b"17");
+        assert!(server.process_transfer(&tampered).is_err());
+        let forged = forge_transfer_with_iv(&request, VICTIM).unwrap();
+        assert_eq!(
+            Transfer {
+                from: VICTIM,
+                to: ATTACKER,
+                amount: 1_000_000
+            },
+            server.process_transfer(&forged).unwrap()
+        );
+    }
+
+    #[quickcheck]
+    fn forge_any_transfer_with_an_attacker_controlled_iv(
+        key: AesKey,
+        victim: AccountId,
+        attacker: AccountId,
+        amount: u64,
+    ) -> bool {
+        let (victim, attacker) = (victim % 100, attacker % 100);
+        let server = Server::new(key);
+        let request = server.client(attacker).transfer(attacker, amount);
+        let forged = forge_transfer_with_iv(&request, victim).unwrap();
+        server.process_transfer(&forged).unwrap()
+            == Transfer {
+                from: victim,
+                to: attacker,
+                amount,
+            }
+    }
+
+    #[test]
+    fn forge_a_transaction_list_with_length_extension() {
+        let server = Server::default();
+        let victim = server.client(VICTIM);
+        let attacker = server.client(ATTACKER);
+        let own = attacker.transaction_list(&[(ATTACKER, 1), (ATTACKER, 1_000_000)]);
+        // Each captured request has a fresh amount and so a fresh MAC.
+        let forged = (1..)
+            .find_map(|amount| {
+                let captured = victim.transaction_list(&[(23, 10), (31, amount)]);
+                forge_transaction_list(&captured, &own).unwrap()
+            })
+            .unwrap();
+        let transfers = server.process_transaction_list(&forged).unwrap();
+        assert_eq!(
+            Transfer {
+                from: VICTIM,
+                to: 23,
+                amount: 10
+            },
+            transfers[0]
+        );
+        assert!(transfers.contains(&Transfer {
+            from: VICTIM,
+            to: ATTACKER,
+            amount: 1_000_000
+        }));
+    }
+}
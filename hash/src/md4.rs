@@ -1,6 +1,7 @@
 // See: https://tools.ietf.org/html/rfc1320
 
 pub use crate::fixed_hash::{FixedHashContext, FixedHashOutput};
+use zeroize::Zeroize;
 
 #[derive(Clone)]
 struct Md4Buffer([u8; 64]);
@@ -32,6 +33,17 @@ pub struct Md4Context {
     corrupted: bool,
 }
 
+// Leaves the context all zero, which has to be `reset` before it is used again.
+impl Zeroize for Md4Context {
+    fn zeroize(&mut self) {
+        self.state.zeroize();
+        self.count.zeroize();
+        self.buffer.0.zeroize();
+        self.computed.zeroize();
+        self.corrupted.zeroize();
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Md4Error {
     InputTooLong,
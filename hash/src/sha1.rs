@@ -1,6 +1,7 @@
 // See: https://tools.ietf.org/html/rfc3174
 
 pub use crate::fixed_hash::{FixedHashContext, FixedHashOutput};
+use zeroize::Zeroize;
 
 #[derive(Clone)]
 struct Sha1MessageBlock([u8; 64]);
@@ -34,6 +35,19 @@ pub struct Sha1Context {
     corrupted: bool,
 }
 
+// Leaves the context all zero, which has to be `reset` before it is used again.
+impl Zeroize for Sha1Context {
+    fn zeroize(&mut self) {
+        self.intermediate_hash.zeroize();
+        self.length_low.zeroize();
+        self.length_high.zeroize();
+        self.message_block_index.zeroize();
+        self.message_block.0.zeroize();
+        self.computed.zeroize();
+        self.corrupted.zeroize();
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Sha1Error {
     InputTooLong,
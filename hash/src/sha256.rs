@@ -1,5 +1,6 @@
 pub use crate::fixed_hash::{FixedHashContext, FixedHashOutput};
 use sha2impl::{Digest, Sha256};
+use zeroize::Zeroize;
 
 #[derive(Clone, Debug, Default)]
 pub struct Sha256Context {
@@ -8,6 +9,20 @@ pub struct Sha256Context {
     corrupted: bool,
 }
 
+// `Sha256` cannot wipe itself, so its state is replaced by a fresh one with a
+// volatile write that the optimizer cannot drop. The context is left as if
+// it had just been reset.
+impl Zeroize for Sha256Context {
+    fn zeroize(&mut self) {
+        // Safe because the pointer comes from a reference, and the value it
+        // replaces owns no heap memory that would leak.
+        unsafe { std::ptr::write_volatile(&mut self.internal, Sha256::new()) };
+        std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+        self.computed.zeroize();
+        self.corrupted.zeroize();
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Sha256Error {
     InputTooLong,
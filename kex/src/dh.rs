@@ -2,6 +2,7 @@ pub use num_bigint_dig::BigUint;
 use num_bigint_dig::{RandBigInt, RandPrime, ToBigUint};
 pub use num_traits::Num;
 use rand::prelude::*;
+use zeroize::Zeroize;
 
 pub use num_integer;
 pub use num_traits;
@@ -52,13 +53,30 @@ impl DiffieHellmanBase {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+// `secret` is wiped on drop and redacted from `Debug`.
+#[derive(Clone, PartialEq)]
 pub struct DiffieHellmanSecretKey {
     pub base: DiffieHellmanBase,
     pub secret: BigUint,
     pub public: BigUint,
 }
 
+impl std::fmt::Debug for DiffieHellmanSecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("DiffieHellmanSecretKey")
+            .field("base", &self.base)
+            .field("secret", &format_args!(".."))
+            .field("public", &self.public)
+            .finish()
+    }
+}
+
+impl Drop for DiffieHellmanSecretKey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
 impl DiffieHellmanSecretKey {
     pub fn new(base: DiffieHellmanBase, secret: BigUint) -> Self {
         let secret = secret % &base.p;
@@ -107,7 +125,7 @@ impl DiffieHellmanPublicKey {
 
 impl From<DiffieHellmanSecretKey> for DiffieHellmanPublicKey {
     fn from(secret: DiffieHellmanSecretKey) -> DiffieHellmanPublicKey {
-        DiffieHellmanPublicKey::new(secret.base, secret.public)
+        DiffieHellmanPublicKey::new(secret.base.clone(), secret.public.clone())
     }
 }
 
@@ -147,4 +165,14 @@ mod tests {
             TestResult::from_bool(a_shared_secret == b_shared_secret)
         }
     }
+
+    #[test]
+    fn debug_redacts_secret() {
+        let base = DiffieHellmanBase::new(BigUint::from(23_u32), BigUint::from(5_u32));
+        let secret_key = DiffieHellmanSecretKey::new(base, BigUint::from(19_u32));
+        let debug = format!("{:?}", secret_key);
+        assert!(debug.contains("secret: .."));
+        assert!(!debug.contains(&format!("{:?}", secret_key.secret)));
+        assert!(debug.contains(&format!("public: {:?}", secret_key.public)));
+    }
 }
@@ -2,6 +2,7 @@ pub use num_bigint_dig::BigUint;
 use num_bigint_dig::{RandBigInt, RandPrime, ToBigUint};
 pub use num_traits::Num;
 use rand::prelude::*;
+use zeroize::Zeroize;
 
 pub use num_integer;
 pub use num_traits;
@@ -95,13 +96,30 @@ impl SecureRemotePasswordBase {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+// `secret_a` is wiped on drop and redacted from `Debug`.
+#[derive(Clone, PartialEq)]
 pub struct SecureRemotePasswordUnsaltedSecret {
     pub base: SecureRemotePasswordBase,
     pub secret_a: BigUint,
     pub public_a: BigUint,
 }
 
+impl std::fmt::Debug for SecureRemotePasswordUnsaltedSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SecureRemotePasswordUnsaltedSecret")
+            .field("base", &self.base)
+            .field("secret_a", &format_args!(".."))
+            .field("public_a", &self.public_a)
+            .finish()
+    }
+}
+
+impl Drop for SecureRemotePasswordUnsaltedSecret {
+    fn drop(&mut self) {
+        self.secret_a.zeroize();
+    }
+}
+
 impl SecureRemotePasswordUnsaltedSecret {
     pub fn new(base: SecureRemotePasswordBase, secret_a: BigUint) -> Self {
         let secret_a = secret_a % &base.prime_n;
@@ -177,11 +195,13 @@ impl SecureRemotePasswordUnsaltedPublic {
 
 impl From<SecureRemotePasswordUnsaltedSecret> for SecureRemotePasswordUnsaltedPublic {
     fn from(secret: SecureRemotePasswordUnsaltedSecret) -> SecureRemotePasswordUnsaltedPublic {
-        SecureRemotePasswordUnsaltedPublic::new(secret.base, secret.public_a)
+        SecureRemotePasswordUnsaltedPublic::new(secret.base.clone(), secret.public_a.clone())
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+// The password verifier `secret_v` and `secret_b` are wiped on drop and
+// redacted from `Debug`.
+#[derive(Clone, PartialEq)]
 pub struct SecureRemotePasswordSaltedSecret {
     pub base: SecureRemotePasswordBase,
     pub salt: BigUint,
@@ -190,6 +210,25 @@ pub struct SecureRemotePasswordSaltedSecret {
     pub public_b: BigUint,
 }
 
+impl std::fmt::Debug for SecureRemotePasswordSaltedSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SecureRemotePasswordSaltedSecret")
+            .field("base", &self.base)
+            .field("salt", &self.salt)
+            .field("secret_v", &format_args!(".."))
+            .field("secret_b", &format_args!(".."))
+            .field("public_b", &self.public_b)
+            .finish()
+    }
+}
+
+impl Drop for SecureRemotePasswordSaltedSecret {
+    fn drop(&mut self) {
+        self.secret_v.zeroize();
+        self.secret_b.zeroize();
+    }
+}
+
 impl SecureRemotePasswordSaltedSecret {
     pub fn new(
         base: SecureRemotePasswordBase,
@@ -273,7 +312,11 @@ impl SecureRemotePasswordSaltedPublic {
 
 impl From<SecureRemotePasswordSaltedSecret> for SecureRemotePasswordSaltedPublic {
     fn from(secret: SecureRemotePasswordSaltedSecret) -> SecureRemotePasswordSaltedPublic {
-        SecureRemotePasswordSaltedPublic::new(secret.base, secret.salt, secret.public_b)
+        SecureRemotePasswordSaltedPublic::new(
+            secret.base.clone(),
+            secret.salt.clone(),
+            secret.public_b.clone(),
+        )
     }
 }
 
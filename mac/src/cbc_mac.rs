@@ -0,0 +1,237 @@
+// CBC-MAC: the final block of the CBC encryption of the PKCS#7 padded
+// message. Unlike CMAC this is only secure for fixed-length messages under a
+// fixed IV; letting the IV or the message length vary enables forgeries (see
+// cryptopals challenge 49).
+
+use aes::cbc::AesCbcIv;
+use aes::ecb::AesEcbBlockCipher;
+use aes::error::AesError;
+use aes::key::AesKey;
+use hash::fixed_hash::{FixedHashContext, FixedHashOutput};
+use xor::ct::ct_eq;
+use zeroize::Zeroize;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CbcMacOutput([u8; 16]);
+
+impl CbcMacOutput {
+    pub fn bytes(&self) -> [u8; 16] {
+        self.0
+    }
+}
+
+impl FixedHashOutput for CbcMacOutput {
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+#[derive(Clone)]
+pub struct CbcMacContext {
+    block_cipher: AesEcbBlockCipher,
+    iv: [u8; 16],
+    state: [u8; 16],
+    buffer: [u8; 16],
+    buffer_len: usize,
+}
+
+// The chaining state and buffered message are wiped on drop and left out of
+// `Debug`; the IV is public.
+impl std::fmt::Debug for CbcMacContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("CbcMacContext")
+            .field("block_cipher", &self.block_cipher)
+            .field("iv", &self.iv)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for CbcMacContext {
+    fn drop(&mut self) {
+        self.state.zeroize();
+        self.buffer.zeroize();
+    }
+}
+
+// `init()` keys the context with an all-zero AES-128 key and a zero IV; use
+// `new()` or `set_key()` for a real key. `reset()` keeps the key and IV.
+impl FixedHashContext for CbcMacContext {
+    type Error = AesError;
+    type Output = CbcMacOutput;
+
+    fn init() -> Self {
+        Self::with_key(
+            &AesKey::aes_128_key([0_u8; 16]),
+            &AesCbcIv::aes_cbc_iv([0_u8; 16]),
+        )
+    }
+
+    fn update<T: ?Sized + AsRef<[u8]>>(&mut self, input: &T) -> Result<(), Self::Error> {
+        for &byte in input.as_ref() {
+            self.buffer[self.buffer_len] = byte;
+            self.buffer_len += 1;
+            if self.buffer_len == 16 {
+                self.process_buffer();
+            }
+        }
+        Ok(())
+    }
+
+    fn output(&mut self) -> Result<Self::Output, Self::Error> {
+        let mut last_block = self.buffer;
+        let pad = (16 - self.buffer_len) as u8;
+        last_block[self.buffer_len..].fill(pad);
+        last_block
+            .iter_mut()
+            .zip(self.state.iter())
+            .for_each(|(b, s)| *b ^= s);
+        self.block_cipher.encrypt_blocks_mut(&mut last_block);
+        Ok(CbcMacOutput(last_block))
+    }
+
+    fn reset(&mut self) {
+        self.state = self.iv;
+        self.buffer = [0_u8; 16];
+        self.buffer_len = 0;
+    }
+
+    fn block_size() -> usize {
+        16
+    }
+
+    fn hash_size() -> usize {
+        16
+    }
+}
+
+impl CbcMacContext {
+    pub fn new<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>>(
+        key: &K,
+        iv: &IV,
+    ) -> Result<Self, AesError> {
+        Ok(Self::with_key(
+            &AesKey::try_copy_from_slice(key)?,
+            &AesCbcIv::try_copy_from_slice(iv)?,
+        ))
+    }
+
+    pub fn with_key(key: &AesKey, iv: &AesCbcIv) -> Self {
+        let mut iv_bytes = [0_u8; 16];
+        iv_bytes.copy_from_slice(iv.as_slice());
+        Self {
+            block_cipher: AesEcbBlockCipher::new(key),
+            iv: iv_bytes,
+            state: iv_bytes,
+            buffer: [0_u8; 16],
+            buffer_len: 0,
+        }
+    }
+
+    pub fn set_key<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>>(
+        &mut self,
+        key: &K,
+        iv: &IV,
+    ) -> Result<(), AesError> {
+        *self = Self::new(key, iv)?;
+        Ok(())
+    }
+
+    // Compares the tag for the input so far against `tag` in constant time.
+    pub fn verify<T: ?Sized + AsRef<[u8]>>(&mut self, tag: &T) -> Result<bool, AesError> {
+        let tag = tag.as_ref();
+        let output = self.output()?;
+        if tag.len() != 16 {
+            return Ok(false);
+        }
+        Ok(ct_eq(&output.0, tag))
+    }
+
+    fn process_buffer(&mut self) {
+        self.state
+            .iter_mut()
+            .zip(self.buffer.iter())
+            .for_each(|(s, b)| *s ^= b);
+        self.block_cipher.encrypt_blocks_mut(&mut self.state);
+        self.buffer_len = 0;
+    }
+}
+
+pub fn aes_cbc_mac<K: ?Sized + AsRef<[u8]>, IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+    key: &K,
+    iv: &IV,
+    input: &T,
+) -> Result<CbcMacOutput, AesError> {
+    let mut ctx = CbcMacContext::new(key, iv)?;
+    ctx.update(input)?;
+    ctx.output()
+}
+
+pub fn aes_cbc_mac_verify<
+    K: ?Sized + AsRef<[u8]>,
+    IV: ?Sized + AsRef<[u8]>,
+    T: ?Sized + AsRef<[u8]>,
+    M: ?Sized + AsRef<[u8]>,
+>(
+    key: &K,
+    iv: &IV,
+    input: &T,
+    tag: &M,
+) -> Result<bool, AesError> {
+    let mut ctx = CbcMacContext::new(key, iv)?;
+    ctx.update(input)?;
+    ctx.verify(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cbc_mac::*;
+    use aes::cbc::AesCbcCipher;
+
+    fn padded<T: ?Sized + AsRef<[u8]>>(input: &T) -> Vec<u8> {
+        let mut padded = input.as_ref().to_vec();
+        let pad = 16 - padded.len() % 16;
+        padded.resize(padded.len() + pad, pad as u8);
+        padded
+    }
+
+    #[quickcheck]
+    fn matches_last_cbc_block(key: AesKey, iv: AesCbcIv, input: Vec<u8>) -> bool {
+        let ciphertext: Vec<u8> = AesCbcCipher::new(&key, &iv)
+            .encrypt(&padded(&input))
+            .unwrap();
+        let mut ctx = CbcMacContext::with_key(&key, &iv);
+        ctx.update(&input).unwrap();
+        ctx.output().unwrap().as_slice() == &ciphertext[ciphertext.len() - 16..]
+    }
+
+    #[quickcheck]
+    fn chunked_update_matches_single_update(
+        key: AesKey,
+        iv: AesCbcIv,
+        input: Vec<u8>,
+        split: usize,
+    ) -> bool {
+        let split = split % (input.len() + 1);
+        let mut ctx = CbcMacContext::with_key(&key, &iv);
+        ctx.update(&input[..split]).unwrap();
+        ctx.update(&input[split..]).unwrap();
+        let output = ctx.output().unwrap();
+        ctx.reset();
+        ctx.update(&input).unwrap();
+        output == ctx.output().unwrap()
+            && aes_cbc_mac_verify(key.as_slice(), iv.as_slice(), &input, &output.bytes()).unwrap()
+    }
+
+    #[test]
+    fn tag_depends_on_iv() {
+        let key = AesKey::aes_128_key(*b"YELLOW SUBMARINE");
+        let a = aes_cbc_mac(key.as_slice(), &[0_u8; 16], b"hello").unwrap();
+        let b = aes_cbc_mac(key.as_slice(), &[1_u8; 16], b"hello").unwrap();
+        assert_ne!(a, b);
+        assert!(!aes_cbc_mac_verify(key.as_slice(), &[0_u8; 16], b"hello", &b.bytes()).unwrap());
+    }
+}
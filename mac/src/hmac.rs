@@ -5,14 +5,29 @@ use hash::md4::{Md4Context, Md4Output};
 use hash::sha1::{Sha1Context, Sha1Output};
 use hash::sha256::{Sha256Context, Sha256Output};
 use xor::sxor::sxor;
+use zeroize::Zeroize;
 
 #[derive(Clone)]
-pub struct HmacContext<H: FixedHashContext> {
+pub struct HmacContext<H: FixedHashContext + Zeroize> {
     outer_context: H,
     inner_context: H,
 }
 
-impl<H: FixedHashContext> FixedHashContext for HmacContext<H> {
+// Both contexts carry chaining state keyed by the padded key.
+impl<H: FixedHashContext + Zeroize> Zeroize for HmacContext<H> {
+    fn zeroize(&mut self) {
+        self.outer_context.zeroize();
+        self.inner_context.zeroize();
+    }
+}
+
+impl<H: FixedHashContext + Zeroize> Drop for HmacContext<H> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<H: FixedHashContext + Zeroize> FixedHashContext for HmacContext<H> {
     type Error = H::Error;
     type Output = H::Output;
 
@@ -47,7 +62,7 @@ impl<H: FixedHashContext> FixedHashContext for HmacContext<H> {
     }
 }
 
-impl<H: FixedHashContext> HmacContext<H> {
+impl<H: FixedHashContext + Zeroize> HmacContext<H> {
     pub fn new<K: ?Sized + AsRef<[u8]>>(key: &K) -> Result<Self, H::Error> {
         let mut context = Self::init();
         context.set_key(key)?;
@@ -69,13 +84,17 @@ impl<H: FixedHashContext> HmacContext<H> {
             pad_key.resize(block_size, 0);
         }
 
-        let pad_key_outer: Vec<u8> = sxor(&pad_key, 0x5c);
-        let pad_key_inner: Vec<u8> = sxor(&pad_key, 0x36);
-
-        self.outer_context.update(&pad_key_outer)?;
-        self.inner_context.update(&pad_key_inner)?;
-
-        Ok(())
+        let mut pad_key_outer: Vec<u8> = sxor(&pad_key, 0x5c);
+        let mut pad_key_inner: Vec<u8> = sxor(&pad_key, 0x36);
+        pad_key.zeroize();
+
+        let result = self
+            .outer_context
+            .update(&pad_key_outer)
+            .and_then(|()| self.inner_context.update(&pad_key_inner));
+        pad_key_outer.zeroize();
+        pad_key_inner.zeroize();
+        result
     }
 }
 
@@ -109,6 +128,32 @@ pub fn hmac_sha256<K: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
 
 #[cfg(test)]
 mod tests {
+    use hash::fixed_hash::FixedHashContext;
+    use hash::md4::Md4Context;
+    use hash::sha1::Sha1Context;
+    use hash::sha256::Sha256Context;
+    use zeroize::Zeroize;
+
+    #[quickcheck]
+    fn zeroize_clears_state(key: Vec<u8>, input: Vec<u8>) -> bool {
+        let mut md4 = crate::hmac::HmacMd4Context::new(&key).unwrap();
+        md4.update(&input).unwrap();
+        md4.zeroize();
+        let mut sha1 = crate::hmac::HmacSha1Context::new(&key).unwrap();
+        sha1.update(&input).unwrap();
+        sha1.zeroize();
+        let mut sha256 = crate::hmac::HmacSha256Context::new(&key).unwrap();
+        sha256.update(&input).unwrap();
+        sha256.zeroize();
+        let empty = Sha256Context::init().output().unwrap();
+        md4.inner_context == Md4Context::default()
+            && md4.outer_context == Md4Context::default()
+            && sha1.inner_context == Sha1Context::default()
+            && sha1.outer_context == Sha1Context::default()
+            && sha256.inner_context.output().unwrap() == empty
+            && sha256.outer_context.output().unwrap() == empty
+    }
+
     #[test]
     fn crate_hmac_sha1_matches_extern_hmac_sha1() {
         let mut key: Vec<u8> = Vec::new();
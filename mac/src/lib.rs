@@ -14,4 +14,6 @@ extern crate quickcheck;
 #[macro_use(quickcheck)]
 extern crate quickcheck_macros;
 
+pub mod cbc_mac;
+pub mod cmac;
 pub mod hmac;
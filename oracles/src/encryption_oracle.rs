@@ -1,8 +1,11 @@
-use aes::cbc::encrypt as aes_cbc_encrypt;
-use aes::ctr::encrypt as aes_ctr_encrypt;
-use aes::ecb::encrypt as aes_ecb_encrypt;
-use pkcs7;
+use aes::block_cipher::BlockCipher;
+use aes::cbc::CbcCipher;
+use aes::ctr::{AesCtrMode, CtrCipher};
+use aes::ecb::{AesEcbBlockCipher, EcbCipher};
+use aes::envelope::{self, EnvelopeMode, EnvelopePadding};
+use pkcs7::padding::Padding;
 use rand::prelude::*;
+use std::marker::PhantomData;
 
 pub type EncryptionResult<T> =
     std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
@@ -39,29 +42,80 @@ impl EncryptionAlgorithm {
 pub enum PaddingAlgorithm {
     Nothing,
     Pkcs7,
+    AnsiX923,
+    Iso10126,
+    Iso7816,
+    Zero,
+    Sslv3,
 }
 
 impl PaddingAlgorithm {
+    pub fn padding(&self) -> Option<&'static dyn Padding> {
+        self.envelope_padding().padding()
+    }
+
     pub fn padding_size(&self, block_size: u8, unpadded_size: usize) -> Option<usize> {
+        let remainder = unpadded_size % block_size as usize;
         match self {
             Self::Nothing => None,
-            Self::Pkcs7 => Some(block_size as usize - (unpadded_size % block_size as usize)),
+            Self::Zero => Some((block_size as usize - remainder) % block_size as usize),
+            _ => Some(block_size as usize - remainder),
+        }
+    }
+
+    pub fn envelope_padding(&self) -> EnvelopePadding {
+        match self {
+            Self::Nothing => EnvelopePadding::Nothing,
+            Self::Pkcs7 => EnvelopePadding::Pkcs7,
+            Self::AnsiX923 => EnvelopePadding::AnsiX923,
+            Self::Iso10126 => EnvelopePadding::Iso10126,
+            Self::Iso7816 => EnvelopePadding::Iso7816,
+            Self::Zero => EnvelopePadding::Zero,
+            Self::Sslv3 => EnvelopePadding::Sslv3,
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct EncryptionContext {
+// The `Aes*` encryption algorithms name the mode; the block cipher itself is
+// `C`, which is AES unless the context was built with `with_block_cipher`.
+#[derive(Clone)]
+pub struct EncryptionContext<C: BlockCipher = AesEcbBlockCipher> {
     pub key: Vec<u8>,
     pub iv: Option<Vec<u8>>,
     pub prefix: Vec<u8>,
     pub suffix: Vec<u8>,
     pub encryption_algorithm: EncryptionAlgorithm,
     pub padding_algorithm: PaddingAlgorithm,
+    block_cipher: PhantomData<C>,
 }
 
-impl EncryptionContext {
-    pub fn new(
+// Written out by hand so that `C` itself needs neither `Debug` nor `PartialEq`.
+impl<C: BlockCipher> std::fmt::Debug for EncryptionContext<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("EncryptionContext")
+            .field("key", &self.key)
+            .field("iv", &self.iv)
+            .field("prefix", &self.prefix)
+            .field("suffix", &self.suffix)
+            .field("encryption_algorithm", &self.encryption_algorithm)
+            .field("padding_algorithm", &self.padding_algorithm)
+            .finish()
+    }
+}
+
+impl<C: BlockCipher> PartialEq for EncryptionContext<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+            && self.iv == other.iv
+            && self.prefix == other.prefix
+            && self.suffix == other.suffix
+            && self.encryption_algorithm == other.encryption_algorithm
+            && self.padding_algorithm == other.padding_algorithm
+    }
+}
+
+impl<C: BlockCipher> EncryptionContext<C> {
+    pub fn with_block_cipher(
         key: &[u8],
         prefix: &[u8],
         suffix: &[u8],
@@ -75,8 +129,70 @@ impl EncryptionContext {
             suffix: suffix.to_vec(),
             encryption_algorithm,
             padding_algorithm,
+            block_cipher: PhantomData,
+        }
+    }
+
+    pub fn block_size(&self) -> Option<u8> {
+        match self.encryption_algorithm {
+            EncryptionAlgorithm::AesCtr => None,
+            EncryptionAlgorithm::AesCbc | EncryptionAlgorithm::AesEcb => {
+                Some(C::block_size() as u8)
+            }
+        }
+    }
+
+    pub fn padded_size(&self, input_size: usize) -> Option<usize> {
+        let unpadded_size = self.unpadded_size(input_size);
+        self.padding_size(unpadded_size)
+            .and_then(|padding_size| Some(padding_size + unpadded_size))
+    }
+
+    pub fn padding_size(&self, input_size: usize) -> Option<usize> {
+        let unpadded_size = self.unpadded_size(input_size);
+        self.block_size().and_then(|block_size| {
+            self.padding_algorithm
+                .padding_size(block_size, unpadded_size)
+        })
+    }
+
+    pub fn unpadded_size(&self, input_size: usize) -> usize {
+        self.prefix.len() + input_size + self.suffix.len()
+    }
+
+    // `prefix || input || suffix` with room left for the padding.
+    fn plaintext(&self, input: &[u8]) -> Vec<u8> {
+        let padded_size = self.padded_size(input.len());
+        let unpadded_size = self.unpadded_size(input.len());
+        let mut plaintext = Vec::with_capacity(padded_size.unwrap_or(unpadded_size));
+        plaintext.extend_from_slice(&self.prefix);
+        plaintext.extend_from_slice(input);
+        plaintext.extend_from_slice(&self.suffix);
+        plaintext
+    }
+
+    fn iv(&self) -> Vec<u8> {
+        if let Some(iv) = self.iv.as_ref() {
+            iv.to_vec()
+        } else {
+            let mut csprng = thread_rng();
+            let mut iv: Vec<u8> = vec![0_u8; C::block_size()];
+            csprng.fill_bytes(&mut iv);
+            iv
         }
     }
+}
+
+impl EncryptionContext {
+    pub fn new(
+        key: &[u8],
+        prefix: &[u8],
+        suffix: &[u8],
+        encryption_algorithm: EncryptionAlgorithm,
+        padding_algorithm: PaddingAlgorithm,
+    ) -> Self {
+        Self::with_block_cipher(key, prefix, suffix, encryption_algorithm, padding_algorithm)
+    }
 
     pub fn random(
         encryption_algorithm: EncryptionAlgorithm,
@@ -121,6 +237,43 @@ impl EncryptionContext {
         )
     }
 
+    // Like `encrypt`, but wrapped in an `aes::envelope` that records the mode,
+    // padding and IV, so that the result can actually be decrypted.
+    pub fn encrypt_envelope<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        input: &T,
+    ) -> EncryptionResult<Vec<u8>> {
+        let plaintext = self.plaintext(input.as_ref());
+        let padding = match self.block_size() {
+            Some(_) => self.padding_algorithm.envelope_padding(),
+            None => EnvelopePadding::Nothing,
+        };
+        let (mode, iv) = match self.encryption_algorithm {
+            EncryptionAlgorithm::AesCbc => (EnvelopeMode::Cbc, self.iv()),
+            EncryptionAlgorithm::AesCtr => (EnvelopeMode::Ctr(AesCtrMode::CRYPTOPALS), self.iv()),
+            EncryptionAlgorithm::AesEcb => (EnvelopeMode::Ecb, Vec::new()),
+        };
+        Ok(envelope::encrypt(
+            &self.key, mode, padding, &iv, &plaintext,
+        )?)
+    }
+
+    // Opens an envelope from `encrypt_envelope` using whatever mode its header
+    // names, and strips this context's prefix and suffix from the plaintext.
+    pub fn decrypt_envelope<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        envelope: &T,
+    ) -> EncryptionResult<Vec<u8>> {
+        let plaintext = envelope::decrypt(&self.key, envelope)?;
+        if plaintext.len() < self.unpadded_size(0)
+            || !plaintext.starts_with(&self.prefix)
+            || !plaintext.ends_with(&self.suffix)
+        {
+            return Err("plaintext does not match the prefix and suffix".into());
+        }
+        Ok(plaintext[self.prefix.len()..plaintext.len() - self.suffix.len()].to_vec())
+    }
+
     fn generate_key(encryption_algorithm: &EncryptionAlgorithm) -> Vec<u8> {
         let mut csprng = thread_rng();
         match encryption_algorithm {
@@ -145,85 +298,110 @@ impl EncryptionContext {
             },
         }
     }
-
-    pub fn block_size(&self) -> Option<u8> {
-        self.encryption_algorithm.block_size()
-    }
-
-    pub fn padded_size(&self, input_size: usize) -> Option<usize> {
-        let unpadded_size = self.unpadded_size(input_size);
-        self.padding_size(unpadded_size)
-            .and_then(|padding_size| Some(padding_size + unpadded_size))
-    }
-
-    pub fn padding_size(&self, input_size: usize) -> Option<usize> {
-        let unpadded_size = self.unpadded_size(input_size);
-        self.block_size().and_then(|block_size| {
-            self.padding_algorithm
-                .padding_size(block_size, unpadded_size)
-        })
-    }
-
-    pub fn unpadded_size(&self, input_size: usize) -> usize {
-        self.prefix.len() + input_size + self.suffix.len()
-    }
 }
 
 pub trait DeterministicEncryptionOracle: EncryptionOracle {}
 
-impl EncryptionOracle for EncryptionContext {
+impl<C: BlockCipher> EncryptionOracle for EncryptionContext<C> {
     fn encrypt<I: std::iter::FromIterator<u8>, T: ?Sized + AsRef<[u8]>>(
         &self,
         input: &T,
     ) -> EncryptionResult<I> {
-        let input = input.as_ref();
-        let padded_size = self.padded_size(input.len());
-        let unpadded_size = self.unpadded_size(input.len());
-        let plaintext_size = padded_size.unwrap_or(unpadded_size);
-
-        let mut plaintext = Vec::with_capacity(plaintext_size);
-        plaintext.extend_from_slice(&self.prefix);
-        plaintext.extend_from_slice(input);
-        plaintext.extend_from_slice(&self.suffix);
+        let mut plaintext = self.plaintext(input.as_ref());
 
-        if let Some(block_size) = self.block_size() {
-            match self.padding_algorithm {
-                PaddingAlgorithm::Nothing => (),
-                PaddingAlgorithm::Pkcs7 => pkcs7::pad_mut(&mut plaintext, block_size)?,
-            };
+        if let (Some(block_size), Some(padding)) =
+            (self.block_size(), self.padding_algorithm.padding())
+        {
+            padding.pad_mut(&mut plaintext, block_size)?;
         }
 
-        match self.encryption_algorithm {
+        let key = C::key_from_slice(&self.key)?;
+        let ciphertext = match self.encryption_algorithm {
             EncryptionAlgorithm::AesCbc => {
-                let iv = if let Some(iv) = self.iv.as_ref() {
-                    // println!("about to encrypt: {:?}", String::from_utf8(plaintext.clone()));
-                    iv.to_vec()
-                } else {
-                    let mut csprng = thread_rng();
-                    let mut iv: Vec<u8> = vec![0_u8; 16];
-                    csprng.fill_bytes(&mut iv);
-                    iv
-                };
-                let ciphertext = aes_cbc_encrypt(&self.key, &iv, &plaintext)?;
-                Ok(ciphertext.into_iter().collect())
+                let iv = self.iv();
+                CbcCipher::<C>::try_new(&key, &iv)?.encrypt(&plaintext)?
             }
             EncryptionAlgorithm::AesCtr => {
-                let iv = if let Some(iv) = self.iv.as_ref() {
-                    // println!("about to encrypt: {:?}", String::from_utf8(plaintext.clone()));
-                    iv.to_vec()
-                } else {
-                    let mut csprng = thread_rng();
-                    let mut iv: Vec<u8> = vec![0_u8; 16];
-                    csprng.fill_bytes(&mut iv);
-                    iv
-                };
-                let ciphertext = aes_ctr_encrypt(&self.key, &iv, &plaintext)?;
-                Ok(ciphertext.into_iter().collect())
+                let iv = self.iv();
+                CtrCipher::<C>::try_new(&key, &iv, AesCtrMode::CRYPTOPALS)?.encrypt(&plaintext)?
+            }
+            EncryptionAlgorithm::AesEcb => EcbCipher::<C>::new(&key).encrypt(&plaintext)?,
+        };
+        Ok(ciphertext.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALGORITHMS: [EncryptionAlgorithm; 3] = [
+        EncryptionAlgorithm::AesCbc,
+        EncryptionAlgorithm::AesCtr,
+        EncryptionAlgorithm::AesEcb,
+    ];
+
+    #[quickcheck]
+    fn envelope_round_trip(input: Vec<u8>, preset_iv: bool) -> bool {
+        ALGORITHMS.iter().all(|&encryption_algorithm| {
+            let mut context =
+                EncryptionContext::random(encryption_algorithm, PaddingAlgorithm::Pkcs7);
+            if preset_iv {
+                context.iv = Some(vec![0x42_u8; 16]);
             }
-            EncryptionAlgorithm::AesEcb => {
-                let ciphertext = aes_ecb_encrypt(&self.key, &plaintext)?;
-                Ok(ciphertext.into_iter().collect())
+            let envelope = context.encrypt_envelope(&input).unwrap();
+            context.decrypt_envelope(&envelope).unwrap() == input
+        })
+    }
+
+    #[quickcheck]
+    fn envelope_round_trip_with_every_padding(input: Vec<u8>) -> bool {
+        // Zero padding is left out: it cannot restore a trailing zero byte.
+        let paddings = [
+            PaddingAlgorithm::Pkcs7,
+            PaddingAlgorithm::AnsiX923,
+            PaddingAlgorithm::Iso10126,
+            PaddingAlgorithm::Iso7816,
+            PaddingAlgorithm::Sslv3,
+        ];
+        ALGORITHMS.iter().all(|&encryption_algorithm| {
+            paddings.iter().all(|&padding_algorithm| {
+                let context = EncryptionContext::random(encryption_algorithm, padding_algorithm);
+                let envelope = context.encrypt_envelope(&input).unwrap();
+                context.decrypt_envelope(&envelope).unwrap() == input
+            })
+        })
+    }
+
+    #[quickcheck]
+    fn envelope_round_trip_without_padding(input: Vec<u8>) -> bool {
+        ALGORITHMS.iter().all(|&encryption_algorithm| {
+            let context =
+                EncryptionContext::random(encryption_algorithm, PaddingAlgorithm::Nothing);
+            match context.encrypt_envelope(&input) {
+                Ok(envelope) => context.decrypt_envelope(&envelope).unwrap() == input,
+                // Only block modes need a whole number of blocks.
+                Err(_) => {
+                    context.block_size().is_some() && context.unpadded_size(input.len()) % 16 != 0
+                }
             }
-        }
+        })
+    }
+
+    #[test]
+    fn envelope_records_the_random_iv() {
+        let context =
+            EncryptionContext::random(EncryptionAlgorithm::AesCbc, PaddingAlgorithm::Pkcs7);
+        let first = context.encrypt_envelope(b"attack at dawn").unwrap();
+        let second = context.encrypt_envelope(b"attack at dawn").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(
+            b"attack at dawn".to_vec(),
+            context.decrypt_envelope(&first).unwrap()
+        );
+        assert_eq!(
+            b"attack at dawn".to_vec(),
+            context.decrypt_envelope(&second).unwrap()
+        );
     }
 }
@@ -1,4 +1,11 @@
+#[cfg(test)]
+extern crate quickcheck;
+#[cfg(test)]
+#[macro_use(quickcheck)]
+extern crate quickcheck_macros;
+
 pub mod encryption_oracle;
+pub mod padding_oracle;
 pub mod timing_leak_oracle;
 
 #[cfg(test)]
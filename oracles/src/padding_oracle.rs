@@ -0,0 +1,420 @@
+use rand::prelude::*;
+use xor::exor::exor;
+
+use crate::encryption_oracle::PaddingAlgorithm;
+
+pub type PaddingOracleResult<T> =
+    std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+#[derive(Debug, Clone)]
+pub enum PaddingOracleError {
+    InvalidBlockSize { was: usize, expected: usize },
+    InvalidCiphertextSize { was: usize, block_size: usize },
+    NoValidPadding { offset: usize },
+    UnsupportedPadding(PaddingAlgorithm),
+    EmptyForgery,
+}
+
+// This is important for other errors to wrap this one.
+impl std::error::Error for PaddingOracleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl std::fmt::Display for PaddingOracleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PaddingOracleError::InvalidBlockSize { was, expected } => {
+                write!(f, "invalid block size '{}' (expected '{}')", was, expected)
+            }
+            PaddingOracleError::InvalidCiphertextSize { was, block_size } => write!(
+                f,
+                "invalid ciphertext size '{}' (expected non-zero multiple of '{}')",
+                was, block_size
+            ),
+            PaddingOracleError::NoValidPadding { offset } => write!(
+                f,
+                "no candidate produced valid padding at offset {} (broken oracle)",
+                offset
+            ),
+            PaddingOracleError::UnsupportedPadding(padding_algorithm) => write!(
+                f,
+                "padding {:?} does not leak every byte through the oracle",
+                padding_algorithm
+            ),
+            PaddingOracleError::EmptyForgery => write!(
+                f,
+                "cannot forge an empty plaintext (it pads to no blocks at all)"
+            ),
+        }
+    }
+}
+
+pub trait PaddingOracle {
+    // Returns `true` if `ciphertext` decrypts under `iv` to a correctly padded plaintext.
+    fn check<IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        iv: &IV,
+        ciphertext: &T,
+    ) -> PaddingOracleResult<bool>;
+
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    // The padding that `check` validates.
+    fn padding_algorithm(&self) -> PaddingAlgorithm {
+        PaddingAlgorithm::Pkcs7
+    }
+
+    fn decrypt<IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &self,
+        iv: &IV,
+        ciphertext: &T,
+    ) -> PaddingOracleResult<Vec<u8>> {
+        let mut attack = PaddingOracleAttack::new(self);
+        let mut plaintext = attack.decrypt(iv, ciphertext)?;
+        if let Some(padding) = self.padding_algorithm().padding() {
+            padding.unpad_mut(&mut plaintext, self.block_size() as u8)?;
+        }
+        Ok(plaintext)
+    }
+
+    fn forge<T: ?Sized + AsRef<[u8]>>(
+        &self,
+        plaintext: &T,
+    ) -> PaddingOracleResult<(Vec<u8>, Vec<u8>)> {
+        let mut attack = PaddingOracleAttack::new(self);
+        attack.forge(plaintext)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PaddingOracleAttack<'o, O: ?Sized> {
+    oracle: &'o O,
+    block_size: usize,
+    padding_algorithm: PaddingAlgorithm,
+    queries: usize,
+}
+
+impl<'o, O: ?Sized + PaddingOracle> PaddingOracleAttack<'o, O> {
+    pub fn new(oracle: &'o O) -> Self {
+        Self {
+            oracle,
+            block_size: oracle.block_size(),
+            padding_algorithm: oracle.padding_algorithm(),
+            queries: 0,
+        }
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn padding_algorithm(&self) -> PaddingAlgorithm {
+        self.padding_algorithm
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    // Recovers the padded plaintext for `ciphertext` one byte at a time.
+    pub fn decrypt<IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+        &mut self,
+        iv: &IV,
+        ciphertext: &T,
+    ) -> PaddingOracleResult<Vec<u8>> {
+        let iv = iv.as_ref();
+        let ciphertext = ciphertext.as_ref();
+        if iv.len() != self.block_size {
+            return Err(PaddingOracleError::InvalidBlockSize {
+                was: iv.len(),
+                expected: self.block_size,
+            }
+            .into());
+        }
+        if ciphertext.is_empty() || ciphertext.len() % self.block_size != 0 {
+            return Err(PaddingOracleError::InvalidCiphertextSize {
+                was: ciphertext.len(),
+                block_size: self.block_size,
+            }
+            .into());
+        }
+        let mut plaintext: Vec<u8> = Vec::with_capacity(ciphertext.len());
+        let mut previous_block = iv;
+        for block in ciphertext.chunks(self.block_size) {
+            let intermediate_block = self.intermediate_block(previous_block, block)?;
+            let plaintext_block: Vec<u8> = exor(&intermediate_block, &previous_block)?;
+            plaintext.extend(plaintext_block);
+            previous_block = block;
+        }
+        Ok(plaintext)
+    }
+
+    // CBC-R: walks backwards from a random final block, choosing each
+    // preceding block so that it decrypts to the wanted plaintext block.
+    // Returns the forged `(iv, ciphertext)` pair. Fails with `EmptyForgery`
+    // if the padded plaintext is empty (as PKCS#7 leaves an empty plaintext),
+    // since there is then no block to forge.
+    pub fn forge<T: ?Sized + AsRef<[u8]>>(
+        &mut self,
+        plaintext: &T,
+    ) -> PaddingOracleResult<(Vec<u8>, Vec<u8>)> {
+        let padded: Vec<u8> = match self.padding_algorithm.padding() {
+            Some(padding) => padding.pad(plaintext.as_ref(), self.block_size as u8)?,
+            None => plaintext.as_ref().to_vec(),
+        };
+        if padded.is_empty() {
+            return Err(PaddingOracleError::EmptyForgery.into());
+        }
+        let zero_block: Vec<u8> = vec![0_u8; self.block_size];
+        let mut block: Vec<u8> = vec![0_u8; self.block_size];
+        thread_rng().fill_bytes(&mut block);
+        let mut blocks: Vec<Vec<u8>> = vec![block.clone()];
+        for plaintext_block in padded.chunks(self.block_size).rev() {
+            let intermediate_block = self.intermediate_block(&zero_block, &block)?;
+            block = exor(&intermediate_block, &plaintext_block)?;
+            blocks.push(block.clone());
+        }
+        let iv = blocks.pop().unwrap();
+        blocks.reverse();
+        Ok((iv, blocks.concat()))
+    }
+
+    // Recovers the block cipher decryption of `block` (before it is XOR'd
+    // with `previous_block`) by tampering with `previous_block`.
+    pub fn intermediate_block<P: ?Sized + AsRef<[u8]>, B: ?Sized + AsRef<[u8]>>(
+        &mut self,
+        previous_block: &P,
+        block: &B,
+    ) -> PaddingOracleResult<Vec<u8>> {
+        let previous_block = previous_block.as_ref();
+        let block = block.as_ref();
+        for challenge in [previous_block, block] {
+            if challenge.len() != self.block_size {
+                return Err(PaddingOracleError::InvalidBlockSize {
+                    was: challenge.len(),
+                    expected: self.block_size,
+                }
+                .into());
+            }
+        }
+        let mut forged_block: Vec<u8> = previous_block.to_vec();
+        let mut intermediate_block: Vec<u8> = vec![0_u8; self.block_size];
+        let mut iv: Vec<u8> = vec![0_u8; self.block_size];
+        for offset in (0..self.block_size).rev() {
+            let length = self.block_size - offset;
+            let tail = self.valid_tail(length)?;
+            forged_block[(offset + 1)..]
+                .iter_mut()
+                .zip(intermediate_block[(offset + 1)..].iter())
+                .zip(tail[1..].iter())
+                .for_each(|((f, &i), &t)| *f = i ^ t);
+            let mut found: Option<u8> = None;
+            for candidate in 0_u8..=255_u8 {
+                forged_block[offset] = candidate;
+                if !self.query(&iv, &forged_block, block)? {
+                    continue;
+                }
+                // The candidate may have produced a different valid padding
+                // that reaches further back: `\x02\x02` instead of `\x01`
+                // for PKCS#7, or a marker before a zero byte for ISO/IEC
+                // 7816-4. Disturbing the preceding byte (in the dummy IV for
+                // the first byte) only keeps the intended padding valid.
+                if self.is_ambiguous(length) {
+                    *preceding_byte(&mut iv, &mut forged_block, offset) ^= 0xff;
+                    let confirmed = self.query(&iv, &forged_block, block)?;
+                    *preceding_byte(&mut iv, &mut forged_block, offset) ^= 0xff;
+                    if !confirmed {
+                        continue;
+                    }
+                }
+                found = Some(candidate);
+                break;
+            }
+            match found {
+                Some(candidate) => intermediate_block[offset] = candidate ^ tail[0],
+                None => return Err(PaddingOracleError::NoValidPadding { offset }.into()),
+            }
+        }
+        Ok(intermediate_block)
+    }
+
+    // The plaintext that a valid padding of `length` bytes must end with.
+    // Schemes that only check the length byte (or nothing at all) leak too
+    // little to recover whole blocks.
+    fn valid_tail(&self, length: usize) -> PaddingOracleResult<Vec<u8>> {
+        let mut tail = vec![0_u8; length];
+        match self.padding_algorithm {
+            PaddingAlgorithm::Pkcs7 => tail.fill(length as u8),
+            PaddingAlgorithm::AnsiX923 => tail[length - 1] = length as u8,
+            PaddingAlgorithm::Iso7816 => tail[0] = 0x80,
+            padding_algorithm => {
+                return Err(PaddingOracleError::UnsupportedPadding(padding_algorithm).into())
+            }
+        }
+        Ok(tail)
+    }
+
+    fn is_ambiguous(&self, length: usize) -> bool {
+        match self.padding_algorithm {
+            PaddingAlgorithm::Iso7816 => true,
+            _ => length == 1,
+        }
+    }
+
+    fn query(&mut self, iv: &[u8], forged_block: &[u8], block: &[u8]) -> PaddingOracleResult<bool> {
+        // The forged block is sent as ciphertext behind a dummy IV so that a
+        // full block of padding still leaves a non-empty message behind.
+        let ciphertext: Vec<u8> = [forged_block, block].concat();
+        self.queries += 1;
+        self.oracle.check(iv, &ciphertext)
+    }
+}
+
+// The byte that precedes `offset` of the forged block in the decrypted query.
+fn preceding_byte<'a>(iv: &'a mut [u8], forged_block: &'a mut [u8], offset: usize) -> &'a mut u8 {
+    match offset {
+        0 => iv.last_mut().unwrap(),
+        _ => &mut forged_block[offset - 1],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cbc::{decrypt as aes_cbc_decrypt, encrypt as aes_cbc_encrypt};
+
+    const SUPPORTED: [PaddingAlgorithm; 3] = [
+        PaddingAlgorithm::Pkcs7,
+        PaddingAlgorithm::AnsiX923,
+        PaddingAlgorithm::Iso7816,
+    ];
+
+    struct Oracle {
+        key: [u8; 16],
+        padding_algorithm: PaddingAlgorithm,
+    }
+
+    impl PaddingOracle for Oracle {
+        fn check<IV: ?Sized + AsRef<[u8]>, T: ?Sized + AsRef<[u8]>>(
+            &self,
+            iv: &IV,
+            ciphertext: &T,
+        ) -> PaddingOracleResult<bool> {
+            let plaintext: Vec<u8> = aes_cbc_decrypt(&self.key, iv, ciphertext)?;
+            let padding = self.padding_algorithm.padding().unwrap();
+            Ok(padding.unpad(&plaintext, 16).is_ok())
+        }
+
+        fn padding_algorithm(&self) -> PaddingAlgorithm {
+            self.padding_algorithm
+        }
+    }
+
+    impl Oracle {
+        fn new(key: [u8; 16], padding_algorithm: PaddingAlgorithm) -> Self {
+            Self {
+                key,
+                padding_algorithm,
+            }
+        }
+
+        fn encrypt(&self, plaintext: &[u8]) -> (Vec<u8>, Vec<u8>) {
+            let mut iv = vec![0_u8; 16];
+            thread_rng().fill_bytes(&mut iv);
+            let padding = self.padding_algorithm.padding().unwrap();
+            let padded = padding.pad(plaintext, 16).unwrap();
+            let ciphertext = aes_cbc_encrypt(&self.key, &iv, &padded).unwrap();
+            (iv, ciphertext)
+        }
+    }
+
+    // Padded lengths of one, two and three blocks, with the padding taking
+    // up a single byte, part of a block or a whole block.
+    fn plaintexts() -> Vec<Vec<u8>> {
+        [15, 16, 21, 47]
+            .iter()
+            .map(|&length| {
+                let mut plaintext = vec![0_u8; length];
+                thread_rng().fill_bytes(&mut plaintext);
+                plaintext
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decrypt_under_every_supported_padding() {
+        for padding_algorithm in SUPPORTED {
+            let oracle = Oracle::new(thread_rng().gen(), padding_algorithm);
+            for plaintext in plaintexts() {
+                let (iv, ciphertext) = oracle.encrypt(&plaintext);
+                assert_eq!(plaintext, oracle.decrypt(&iv, &ciphertext).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn forge_under_every_supported_padding() {
+        for padding_algorithm in SUPPORTED {
+            let oracle = Oracle::new(thread_rng().gen(), padding_algorithm);
+            for plaintext in plaintexts() {
+                let (iv, ciphertext) = oracle.forge(&plaintext).unwrap();
+                let padding = padding_algorithm.padding().unwrap();
+                let challenge: Vec<u8> = aes_cbc_decrypt(&oracle.key, &iv, &ciphertext).unwrap();
+                assert_eq!(plaintext, padding.unpad(&challenge, 16).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn forge_empty_plaintext() {
+        // PKCS#7 pads an empty plaintext to nothing, so there is no block to
+        // forge; the other schemes pad it to a whole block.
+        let oracle = Oracle::new(thread_rng().gen(), PaddingAlgorithm::Pkcs7);
+        let error = oracle.forge(b"").unwrap_err();
+        assert_eq!(
+            PaddingOracleError::EmptyForgery.to_string(),
+            error.to_string()
+        );
+        for padding_algorithm in [PaddingAlgorithm::AnsiX923, PaddingAlgorithm::Iso7816] {
+            let oracle = Oracle::new(thread_rng().gen(), padding_algorithm);
+            let (iv, ciphertext) = oracle.forge(b"").unwrap();
+            let padding = padding_algorithm.padding().unwrap();
+            let challenge: Vec<u8> = aes_cbc_decrypt(&oracle.key, &iv, &ciphertext).unwrap();
+            assert_eq!(16, ciphertext.len());
+            assert!(padding.unpad(&challenge, 16).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn decrypt_with_marker_before_a_zero_byte() {
+        // The final block ends with `\x80\x00\x80\x00\x00\x00`, so guesses
+        // that produce a zero byte fall back on an earlier marker and are
+        // also accepted by the oracle.
+        let oracle = Oracle::new(*b"YELLOW SUBMARINE", PaddingAlgorithm::Iso7816);
+        let plaintext = b"YELLOW SUBMARINE-ambiguous\x80\x00".to_vec();
+        let (iv, ciphertext) = oracle.encrypt(&plaintext);
+        assert_eq!(plaintext, oracle.decrypt(&iv, &ciphertext).unwrap());
+    }
+
+    #[test]
+    fn length_only_paddings_are_unsupported() {
+        for padding_algorithm in [
+            PaddingAlgorithm::Iso10126,
+            PaddingAlgorithm::Sslv3,
+            PaddingAlgorithm::Zero,
+        ] {
+            let oracle = Oracle::new([0_u8; 16], padding_algorithm);
+            let (iv, ciphertext) = oracle.encrypt(b"attack at dawn");
+            let error = oracle.decrypt(&iv, &ciphertext).unwrap_err();
+            assert_eq!(
+                PaddingOracleError::UnsupportedPadding(padding_algorithm).to_string(),
+                error.to_string()
+            );
+        }
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Pkcs7PadError {
     ZeroBlockSize,
 }
@@ -21,7 +21,7 @@ impl std::fmt::Display for Pkcs7PadError {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Pkcs7UnpadError {
     ZeroBlockSize,
     InvalidBlockLength {
@@ -73,3 +73,124 @@ impl std::fmt::Display for Pkcs7UnpadError {
         }
     }
 }
+
+// Lets streaming adapters report unpadding failures through `std::io`.
+impl From<Pkcs7UnpadError> for std::io::Error {
+    fn from(error: Pkcs7UnpadError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    }
+}
+
+// The errors shared by every `padding::Padding` scheme.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PadError {
+    ZeroBlockSize,
+}
+
+impl std::error::Error for PadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        None
+    }
+}
+
+impl std::fmt::Display for PadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PadError::ZeroBlockSize => {
+                write!(f, "block_size must be a non-zero u8 (between 1 and 255)")
+            }
+        }
+    }
+}
+
+impl From<Pkcs7PadError> for PadError {
+    fn from(error: Pkcs7PadError) -> Self {
+        match error {
+            Pkcs7PadError::ZeroBlockSize => PadError::ZeroBlockSize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnpadError {
+    ZeroBlockSize,
+    InvalidBlockLength {
+        was: usize,
+        expected: u8,
+    },
+    MissingPadding,
+    InvalidPaddingLength {
+        was: u8,
+        offset: usize,
+    },
+    InvalidPaddingByte {
+        was: u8,
+        offset: usize,
+        expected: u8,
+    },
+    MissingPaddingMarker {
+        offset: usize,
+    },
+}
+
+impl std::error::Error for UnpadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        None
+    }
+}
+
+impl std::fmt::Display for UnpadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UnpadError::ZeroBlockSize => {
+                write!(f, "block_size must be a non-zero u8 (between 1 and 255)")
+            }
+            UnpadError::InvalidBlockLength { was, expected } => write!(
+                f,
+                "invalid block length '{}' (expected multiple of '{}')",
+                was, expected
+            ),
+            UnpadError::MissingPadding => write!(f, "input is empty, so it has no padding"),
+            UnpadError::InvalidPaddingLength { was, offset } => {
+                write!(f, "invalid padding length '{}' at offset {}", was, offset)
+            }
+            UnpadError::InvalidPaddingByte {
+                was,
+                offset,
+                expected,
+            } => write!(
+                f,
+                "invalid padding byte '{}' at offset {} (expected '{}')",
+                was, offset, expected
+            ),
+            UnpadError::MissingPaddingMarker { offset } => write!(
+                f,
+                "no padding marker in the final block starting at offset {}",
+                offset
+            ),
+        }
+    }
+}
+
+impl From<Pkcs7UnpadError> for UnpadError {
+    fn from(error: Pkcs7UnpadError) -> Self {
+        match error {
+            Pkcs7UnpadError::ZeroBlockSize => UnpadError::ZeroBlockSize,
+            Pkcs7UnpadError::InvalidBlockLength { was, expected } => {
+                UnpadError::InvalidBlockLength { was, expected }
+            }
+            Pkcs7UnpadError::InvalidPaddingLength { was, offset } => {
+                UnpadError::InvalidPaddingLength { was, offset }
+            }
+            Pkcs7UnpadError::InvalidPaddingByte {
+                was,
+                offset,
+                expected,
+            } => UnpadError::InvalidPaddingByte {
+                was,
+                offset,
+                expected,
+            },
+        }
+    }
+}
@@ -13,6 +13,8 @@ extern crate quickcheck;
 extern crate quickcheck_macros;
 
 pub mod errors;
+pub mod padding;
+pub mod stream;
 
 use errors::{Pkcs7PadError, Pkcs7UnpadError};
 
@@ -163,6 +165,38 @@ pub fn unpad_mut<U: ?Sized + AsMut<Vec<u8>>>(input: &mut U, block_size: u8) -> U
     input.pkcs7_unpad_mut(block_size)
 }
 
+// Unpads without revealing, through timing or the error, why invalid padding
+// was rejected. The last `block_size` bytes are always all inspected; only
+// the input length and block size are treated as public.
+pub fn pkcs7_unpad_ct<U: ?Sized + AsRef<[u8]>>(input: &U, block_size: u8) -> Option<&[u8]> {
+    use xor::ct::{ct_eq_mask, ct_is_zero, ct_lt};
+    let head = input.as_ref();
+    if block_size == 0 || head.len() % block_size as usize != 0 {
+        return None;
+    }
+    if head.is_empty() {
+        return Some(head);
+    }
+    let hlen = head.len();
+    let pad = head[hlen - 1];
+    let mut good = !ct_is_zero(pad) & !ct_lt(block_size, pad);
+    if hlen == block_size as usize {
+        good &= ct_lt(pad, block_size);
+    }
+    for (i, &c) in head[(hlen - block_size as usize)..]
+        .iter()
+        .rev()
+        .enumerate()
+    {
+        good &= !ct_lt(i as u8, pad) | ct_eq_mask(c, pad);
+    }
+    if std::hint::black_box(good) == 0xff {
+        Some(&head[..(hlen - pad as usize)])
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{Pkcs7, Pkcs7Mut};
@@ -222,4 +256,60 @@ mod tests {
         ys.pkcs7_unpad_mut(block_size)?;
         Ok(ys == xs)
     }
+
+    #[quickcheck]
+    fn prop_unpad_ct_matches_unpad(xs: Vec<u8>, block_size: u8, padded: bool) -> bool {
+        let xs: Vec<u8> = match (padded, xs.pkcs7_pad(block_size)) {
+            (true, Ok(ys)) => ys,
+            _ => xs,
+        };
+        let expected: crate::UnpadResult<Vec<u8>> = xs.pkcs7_unpad(block_size);
+        match (super::pkcs7_unpad_ct(&xs, block_size), expected) {
+            (Some(challenge), Ok(expected)) => challenge == &expected[..],
+            (None, Err(_)) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn pkcs7_unpad_ct_sanity_check() {
+        fn unpad(xs: &[u8], block_size: u8) -> Option<&[u8]> {
+            super::pkcs7_unpad_ct(xs, block_size)
+        }
+        assert_eq!(
+            Some(&b"YELLOW SUBMARINE"[..]),
+            unpad(b"YELLOW SUBMARINE\x04\x04\x04\x04", 20)
+        );
+        assert_eq!(None, unpad(b"YELLOW SUBMARINE\x04\x04\x05\x04", 20));
+        assert_eq!(None, unpad(b"YELLOW SUBMARINE\x04\x04\x04\x00", 20));
+        assert_eq!(None, unpad(b"YELLOW SUBMARINE\x04\x04\x04\x15", 20));
+        assert_eq!(None, unpad(&[4u8; 4], 4));
+        assert_eq!(None, unpad(b"YELLOW SUBMARINE\x04\x04\x04", 20));
+    }
+
+    // Valid and invalid padding must take the same time. Run with
+    // `cargo test --release -p pkcs7 -- --ignored`.
+    #[test]
+    #[ignore]
+    fn pkcs7_unpad_ct_has_no_timing_leak() {
+        let valid: Vec<Vec<u8>> = (1..=16u8)
+            .map(|pad| {
+                let head = &b"YELLOW SUBMARINE"[..];
+                [head, &head[(pad as usize)..], &vec![pad; pad as usize]].concat()
+            })
+            .collect();
+        let invalid: Vec<Vec<u8>> = valid
+            .iter()
+            .map(|xs| {
+                let mut xs = xs.clone();
+                let last = xs.len() - 1;
+                xs[last] ^= 0x80;
+                xs
+            })
+            .collect();
+        let t = xor::dudect::t_statistic(&valid, &invalid, 100_000, |xs| {
+            super::pkcs7_unpad_ct(xs, 16).is_some()
+        });
+        assert!(t.abs() < xor::dudect::LEAK_THRESHOLD, "t = {}", t);
+    }
 }
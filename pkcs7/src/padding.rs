@@ -0,0 +1,324 @@
+// Block cipher padding schemes behind one object-safe trait, so that callers
+// can pick a scheme at runtime.
+//
+// The ANSI X9.23, ISO 10126, ISO/IEC 7816-4 and SSLv3 schemes always add
+// between 1 and `block_size` bytes, so that unpadding is unambiguous.
+// `Pkcs7Padding` does too, except that an empty input stays empty, as with
+// the `Pkcs7` traits. Zero padding only fills up the final block and strips
+// every trailing zero, including any that belonged to the message.
+
+use rand::prelude::*;
+
+use crate::errors::{PadError, UnpadError};
+use crate::Pkcs7Mut;
+
+pub trait Padding {
+    fn pad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), PadError>;
+    fn unpad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), UnpadError>;
+
+    fn pad(&self, input: &[u8], block_size: u8) -> Result<Vec<u8>, PadError> {
+        let mut output = input.to_vec();
+        self.pad_mut(&mut output, block_size)?;
+        Ok(output)
+    }
+
+    fn unpad(&self, input: &[u8], block_size: u8) -> Result<Vec<u8>, UnpadError> {
+        let mut output = input.to_vec();
+        self.unpad_mut(&mut output, block_size)?;
+        Ok(output)
+    }
+}
+
+// Number of padding bytes needed to reach the next block boundary, where an
+// already aligned input gets a whole block.
+fn padding_length(length: usize, block_size: u8) -> Result<usize, PadError> {
+    if block_size == 0 {
+        return Err(PadError::ZeroBlockSize);
+    }
+    Ok(block_size as usize - length % block_size as usize)
+}
+
+// Checks that `buffer` is a non-empty whole number of blocks and returns the
+// final byte.
+fn last_byte(buffer: &[u8], block_size: u8) -> Result<u8, UnpadError> {
+    if block_size == 0 {
+        return Err(UnpadError::ZeroBlockSize);
+    }
+    if !buffer.len().is_multiple_of(block_size as usize) {
+        return Err(UnpadError::InvalidBlockLength {
+            was: buffer.len(),
+            expected: block_size,
+        });
+    }
+    buffer.last().copied().ok_or(UnpadError::MissingPadding)
+}
+
+// Checks a trailing length byte counting `length` padding bytes in total.
+fn check_length(buffer: &[u8], block_size: u8, length: usize) -> Result<(), UnpadError> {
+    if length == 0 || length > block_size as usize || length > buffer.len() {
+        return Err(UnpadError::InvalidPaddingLength {
+            was: buffer[buffer.len() - 1],
+            offset: buffer.len() - 1,
+        });
+    }
+    Ok(())
+}
+
+fn random_bytes(buffer: &mut Vec<u8>, count: usize) {
+    let start = buffer.len();
+    buffer.resize(start + count, 0);
+    thread_rng().fill_bytes(&mut buffer[start..]);
+}
+
+// PKCS#7, as implemented by the `Pkcs7` traits (an empty input stays empty).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pkcs7Padding;
+
+impl Padding for Pkcs7Padding {
+    fn pad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), PadError> {
+        Ok(buffer.pkcs7_pad_mut(block_size)?)
+    }
+
+    fn unpad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), UnpadError> {
+        Ok(buffer.pkcs7_unpad_mut(block_size)?)
+    }
+}
+
+// ANSI X9.23: zero bytes followed by the padding length, `00 00 03`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AnsiX923Padding;
+
+impl Padding for AnsiX923Padding {
+    fn pad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), PadError> {
+        let length = padding_length(buffer.len(), block_size)?;
+        buffer.resize(buffer.len() + length - 1, 0);
+        buffer.push(length as u8);
+        Ok(())
+    }
+
+    fn unpad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), UnpadError> {
+        let length = last_byte(buffer, block_size)? as usize;
+        check_length(buffer, block_size, length)?;
+        let start = buffer.len() - length;
+        if let Some(position) = buffer[start..buffer.len() - 1].iter().position(|&c| c != 0) {
+            return Err(UnpadError::InvalidPaddingByte {
+                was: buffer[start + position],
+                offset: start + position,
+                expected: 0,
+            });
+        }
+        buffer.truncate(start);
+        Ok(())
+    }
+}
+
+// ISO 10126: random bytes followed by the padding length. Only the length is
+// checked when unpadding.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Iso10126Padding;
+
+impl Padding for Iso10126Padding {
+    fn pad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), PadError> {
+        let length = padding_length(buffer.len(), block_size)?;
+        random_bytes(buffer, length - 1);
+        buffer.push(length as u8);
+        Ok(())
+    }
+
+    fn unpad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), UnpadError> {
+        let length = last_byte(buffer, block_size)? as usize;
+        check_length(buffer, block_size, length)?;
+        buffer.truncate(buffer.len() - length);
+        Ok(())
+    }
+}
+
+// ISO/IEC 7816-4: a `0x80` marker followed by zero bytes, `80 00 00`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Iso7816Padding;
+
+impl Padding for Iso7816Padding {
+    fn pad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), PadError> {
+        let length = padding_length(buffer.len(), block_size)?;
+        buffer.push(0x80);
+        buffer.resize(buffer.len() + length - 1, 0);
+        Ok(())
+    }
+
+    fn unpad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), UnpadError> {
+        last_byte(buffer, block_size)?;
+        let start = buffer.len() - block_size as usize;
+        match buffer[start..].iter().rposition(|&c| c != 0) {
+            Some(position) if buffer[start + position] == 0x80 => {
+                buffer.truncate(start + position);
+                Ok(())
+            }
+            Some(position) => Err(UnpadError::InvalidPaddingByte {
+                was: buffer[start + position],
+                offset: start + position,
+                expected: 0x80,
+            }),
+            None => Err(UnpadError::MissingPaddingMarker { offset: start }),
+        }
+    }
+}
+
+// Zero bytes up to the block boundary; aligned input is left alone.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ZeroPadding;
+
+impl Padding for ZeroPadding {
+    fn pad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), PadError> {
+        let length = padding_length(buffer.len(), block_size)? % block_size as usize;
+        buffer.resize(buffer.len() + length, 0);
+        Ok(())
+    }
+
+    fn unpad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), UnpadError> {
+        if buffer.is_empty() {
+            return if block_size == 0 {
+                Err(UnpadError::ZeroBlockSize)
+            } else {
+                Ok(())
+            };
+        }
+        last_byte(buffer, block_size)?;
+        let length = buffer.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+        buffer.truncate(length);
+        Ok(())
+    }
+}
+
+// SSLv3 CBC padding: arbitrary bytes followed by their count, which unlike
+// ISO 10126 leaves out the length byte itself and must be less than the block
+// size. Only the length is checked when unpadding, which is what POODLE
+// exploits.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sslv3Padding;
+
+impl Padding for Sslv3Padding {
+    fn pad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), PadError> {
+        let length = padding_length(buffer.len(), block_size)?;
+        random_bytes(buffer, length - 1);
+        buffer.push((length - 1) as u8);
+        Ok(())
+    }
+
+    fn unpad_mut(&self, buffer: &mut Vec<u8>, block_size: u8) -> Result<(), UnpadError> {
+        let length = last_byte(buffer, block_size)? as usize + 1;
+        check_length(buffer, block_size, length)?;
+        buffer.truncate(buffer.len() - length);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::TestResult;
+
+    const SCHEMES: [&dyn Padding; 5] = [
+        &Pkcs7Padding,
+        &AnsiX923Padding,
+        &Iso10126Padding,
+        &Iso7816Padding,
+        &Sslv3Padding,
+    ];
+
+    #[test]
+    fn padding_sanity_check() {
+        let input = b"YELLOW SUBMARINE";
+        let padded = AnsiX923Padding.pad(input, 20).unwrap();
+        assert_eq!(&b"YELLOW SUBMARINE\x00\x00\x00\x04"[..], &padded[..]);
+        let padded = Iso7816Padding.pad(input, 20).unwrap();
+        assert_eq!(&b"YELLOW SUBMARINE\x80\x00\x00\x00"[..], &padded[..]);
+        let padded = ZeroPadding.pad(input, 20).unwrap();
+        assert_eq!(&b"YELLOW SUBMARINE\x00\x00\x00\x00"[..], &padded[..]);
+        assert_eq!(&input[..], &ZeroPadding.pad(input, 16).unwrap()[..]);
+        let padded = Iso10126Padding.pad(input, 20).unwrap();
+        assert_eq!((20, 4), (padded.len(), padded[19]));
+        let padded = Sslv3Padding.pad(input, 20).unwrap();
+        assert_eq!((20, 3), (padded.len(), padded[19]));
+        let padded = Sslv3Padding.pad(input, 16).unwrap();
+        assert_eq!((32, 15), (padded.len(), padded[31]));
+    }
+
+    #[test]
+    fn unpadding_errors() {
+        assert_eq!(
+            Err(UnpadError::InvalidPaddingByte {
+                was: 1,
+                offset: 17,
+                expected: 0
+            }),
+            AnsiX923Padding.unpad(b"YELLOW SUBMARINE\x00\x01\x00\x04", 20)
+        );
+        assert_eq!(
+            Err(UnpadError::InvalidPaddingLength {
+                was: 21,
+                offset: 19
+            }),
+            Iso10126Padding.unpad(b"YELLOW SUBMARINE\x00\x01\x00\x15", 20)
+        );
+        assert_eq!(
+            Err(UnpadError::InvalidPaddingByte {
+                was: 0x45,
+                offset: 15,
+                expected: 0x80
+            }),
+            Iso7816Padding.unpad(b"YELLOW SUBMARINE\x00\x00\x00\x00", 20)
+        );
+        assert_eq!(
+            Err(UnpadError::MissingPaddingMarker { offset: 0 }),
+            Iso7816Padding.unpad(&[0_u8; 16], 16)
+        );
+        assert_eq!(
+            Err(UnpadError::InvalidPaddingLength {
+                was: 16,
+                offset: 15
+            }),
+            Sslv3Padding.unpad(&[16_u8; 16], 16)
+        );
+        assert_eq!(
+            Err(UnpadError::InvalidBlockLength {
+                was: 15,
+                expected: 16
+            }),
+            ZeroPadding.unpad(&[1_u8; 15], 16)
+        );
+        assert_eq!(
+            Err(UnpadError::MissingPadding),
+            AnsiX923Padding.unpad(b"", 16)
+        );
+        assert_eq!(Err(UnpadError::ZeroBlockSize), Sslv3Padding.unpad(b"", 0));
+    }
+
+    #[quickcheck]
+    fn pad_and_unpad_is_identity(xs: Vec<u8>, block_size: u8) -> TestResult {
+        if block_size == 0 {
+            return TestResult::from_bool(
+                SCHEMES
+                    .iter()
+                    .all(|scheme| scheme.pad(&xs, block_size).is_err()),
+            );
+        }
+        TestResult::from_bool(SCHEMES.iter().all(|scheme| {
+            let padded = scheme.pad(&xs, block_size).unwrap();
+            padded.len().is_multiple_of(block_size as usize)
+                && scheme.unpad(&padded, block_size).unwrap() == xs
+        }))
+    }
+
+    #[quickcheck]
+    fn zero_padding_strips_trailing_zeros(xs: Vec<u8>, block_size: u8) -> TestResult {
+        if block_size == 0 {
+            return TestResult::discard();
+        }
+        let padded = ZeroPadding.pad(&xs, block_size).unwrap();
+        let unpadded = ZeroPadding.unpad(&padded, block_size).unwrap();
+        let trimmed = xs.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+        TestResult::from_bool(
+            padded.len().is_multiple_of(block_size as usize) && unpadded == xs[..trimmed],
+        )
+    }
+}
@@ -0,0 +1,260 @@
+use std::io::{self, Read, Write};
+
+use crate::errors::{Pkcs7PadError, Pkcs7UnpadError};
+use crate::{PadResult, UnpadResult};
+
+// Wraps a reader so that PKCS#7 padding follows the inner stream's bytes once
+// it reaches EOF. As with `Pkcs7::pkcs7_pad`, an empty stream stays empty.
+pub struct Pkcs7PadReader<R> {
+    inner: R,
+    block_size: u8,
+    length: usize,
+    padding: Option<(u8, u8)>,
+}
+
+impl<R> Pkcs7PadReader<R> {
+    pub fn new(inner: R, block_size: u8) -> PadResult<Pkcs7PadReader<R>> {
+        if block_size == 0 {
+            return Err(Pkcs7PadError::ZeroBlockSize);
+        }
+        Ok(Pkcs7PadReader {
+            inner,
+            block_size,
+            length: 0,
+            padding: None,
+        })
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Pkcs7PadReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let (pad, remaining) = match self.padding {
+            Some(padding) => padding,
+            None => {
+                let n = self.inner.read(buf)?;
+                if n > 0 {
+                    self.length += n;
+                    return Ok(n);
+                }
+                let pad = match self.length {
+                    0 => 0,
+                    length => self.block_size - (length % self.block_size as usize) as u8,
+                };
+                (pad, pad)
+            }
+        };
+        let n = std::cmp::min(buf.len(), remaining as usize);
+        buf[..n].fill(pad);
+        self.padding = Some((pad, remaining - n as u8));
+        Ok(n)
+    }
+}
+
+// Wraps a writer so that PKCS#7 padding is stripped from the bytes written
+// through it. The last block is held back until `finish`, which checks the
+// padding and returns the inner writer; dropping the adapter without calling
+// `finish` discards that block. Padding errors are reported as
+// `io::ErrorKind::InvalidData` wrapping a `Pkcs7UnpadError`.
+pub struct Pkcs7UnpadWriter<W> {
+    inner: W,
+    block_size: u8,
+    length: usize,
+    block: Vec<u8>,
+}
+
+impl<W> Pkcs7UnpadWriter<W> {
+    pub fn new(inner: W, block_size: u8) -> UnpadResult<Pkcs7UnpadWriter<W>> {
+        if block_size == 0 {
+            return Err(Pkcs7UnpadError::ZeroBlockSize);
+        }
+        Ok(Pkcs7UnpadWriter {
+            inner,
+            block_size,
+            length: 0,
+            block: Vec::with_capacity(block_size as usize),
+        })
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+}
+
+impl<W: Write> Pkcs7UnpadWriter<W> {
+    pub fn finish(mut self) -> io::Result<W> {
+        let block_size = self.block_size as usize;
+        let hlen = self.length;
+        if hlen == 0 {
+            return Ok(self.inner);
+        }
+        if !hlen.is_multiple_of(block_size) {
+            return Err(Pkcs7UnpadError::InvalidBlockLength {
+                was: hlen,
+                expected: self.block_size,
+            }
+            .into());
+        }
+        let pad = self.block[block_size - 1];
+        let plen = pad as usize;
+        if plen < 1 || plen > block_size || hlen <= plen {
+            return Err(Pkcs7UnpadError::InvalidPaddingLength {
+                was: pad,
+                offset: hlen - 1,
+            }
+            .into());
+        }
+        let head = &self.block[(block_size - plen)..];
+        if let Some(position) = head.iter().position(|&c| c != pad) {
+            return Err(Pkcs7UnpadError::InvalidPaddingByte {
+                was: head[position],
+                offset: position + hlen - plen,
+                expected: pad,
+            }
+            .into());
+        }
+        self.inner.write_all(&self.block[..(block_size - plen)])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Pkcs7UnpadWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let block_size = self.block_size as usize;
+        let total = self.block.len() + buf.len();
+        if total > block_size {
+            // Everything except the final `block_size` bytes is known not to
+            // be padding.
+            let emit = total - block_size;
+            let from_block = std::cmp::min(emit, self.block.len());
+            self.inner.write_all(&self.block[..from_block])?;
+            self.block.drain(..from_block);
+            let from_buf = emit - from_block;
+            self.inner.write_all(&buf[..from_buf])?;
+            self.block.extend_from_slice(&buf[from_buf..]);
+        } else {
+            self.block.extend_from_slice(buf);
+        }
+        self.length += buf.len();
+        Ok(buf.len())
+    }
+
+    // Only flushes the inner writer, the held back block is not written.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor, Read, Write};
+
+    use super::{Pkcs7PadReader, Pkcs7UnpadWriter};
+    use crate::errors::Pkcs7UnpadError;
+    use crate::Pkcs7;
+
+    // Hands out at most `chunk` bytes per read.
+    struct ChunkedReader<R> {
+        inner: R,
+        chunk: usize,
+    }
+
+    impl<R: Read> Read for ChunkedReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = std::cmp::min(buf.len(), self.chunk);
+            self.inner.read(&mut buf[..len])
+        }
+    }
+
+    fn unpad_error(error: io::Error) -> Option<Pkcs7UnpadError> {
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        error
+            .into_inner()?
+            .downcast::<Pkcs7UnpadError>()
+            .ok()
+            .map(|error| *error)
+    }
+
+    #[quickcheck]
+    fn pad_reader_matches_pkcs7_pad(xs: Vec<u8>, block_size: u8, chunk: u8) -> bool {
+        if block_size == 0 {
+            return Pkcs7PadReader::new(&xs[..], block_size).is_err();
+        }
+        let expected: Vec<u8> = xs.pkcs7_pad(block_size).unwrap();
+        let inner = ChunkedReader {
+            inner: &xs[..],
+            chunk: chunk as usize + 1,
+        };
+        let mut reader = Pkcs7PadReader::new(inner, block_size).unwrap();
+        let mut challenge = Vec::new();
+        reader.read_to_end(&mut challenge).unwrap();
+        expected == challenge
+    }
+
+    #[quickcheck]
+    fn pad_reader_and_unpad_writer_is_identity(xs: Vec<u8>, block_size: u8, chunk: u8) -> bool {
+        if block_size == 0 {
+            return Pkcs7UnpadWriter::new(Vec::<u8>::new(), block_size).is_err();
+        }
+        let mut reader = Pkcs7PadReader::new(Cursor::new(&xs), block_size).unwrap();
+        let mut writer = Pkcs7UnpadWriter::new(Vec::new(), block_size).unwrap();
+        let mut buf = vec![0_u8; chunk as usize + 1];
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            writer.write_all(&buf[..n]).unwrap();
+        }
+        xs == writer.finish().unwrap()
+    }
+
+    #[quickcheck]
+    fn unpad_writer_matches_pkcs7_unpad(xs: Vec<u8>, block_size: u8) -> bool {
+        if block_size == 0 {
+            return true;
+        }
+        let expected: crate::UnpadResult<Vec<u8>> = xs.pkcs7_unpad(block_size);
+        let mut writer = Pkcs7UnpadWriter::new(Vec::new(), block_size).unwrap();
+        for chunk in xs.chunks(3) {
+            writer.write_all(chunk).unwrap();
+        }
+        match (writer.finish(), expected) {
+            (Ok(challenge), Ok(expected)) => challenge == expected,
+            (Err(challenge), Err(expected)) => unpad_error(challenge) == Some(expected),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn unpad_writer_sanity_check() -> io::Result<()> {
+        let mut writer = Pkcs7UnpadWriter::new(Vec::new(), 20).unwrap();
+        writer.write_all(b"YELLOW SUB")?;
+        writer.write_all(b"MARINE\x04\x04")?;
+        writer.write_all(b"\x04\x04")?;
+        assert_eq!(&b"YELLOW SUBMARINE"[..], &writer.finish()?[..]);
+
+        let mut writer = Pkcs7UnpadWriter::new(Vec::new(), 20).unwrap();
+        writer.write_all(b"YELLOW SUBMARINE\x04\x04\x05\x04")?;
+        assert_eq!(
+            Some(Pkcs7UnpadError::InvalidPaddingByte {
+                was: 5,
+                offset: 18,
+                expected: 4
+            }),
+            unpad_error(writer.finish().unwrap_err())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use xor::kernel::{self, scalar};
+
+const SIZES: [usize; 3] = [16, 1024, 64 * 1024];
+
+fn bench_fixed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fixed");
+    for size in SIZES {
+        let input = vec![0x5a_u8; size];
+        let other = vec![0xc3_u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("scalar", size), &input, |b, input| {
+            let mut buffer = input.clone();
+            b.iter(|| scalar::xor_in_place(black_box(&mut buffer), black_box(&other)))
+        });
+        group.bench_with_input(BenchmarkId::new("kernel", size), &input, |b, input| {
+            let mut buffer = input.clone();
+            b.iter(|| kernel::xor_in_place(black_box(&mut buffer), black_box(&other)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_repeating(c: &mut Criterion) {
+    let mut group = c.benchmark_group("repeating");
+    for key_size in [3, 29] {
+        let key: Vec<u8> = (0..key_size as u8).collect();
+        for size in SIZES {
+            let input = vec![0x5a_u8; size];
+            group.throughput(Throughput::Bytes(size as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("scalar/key-{}", key_size), size),
+                &input,
+                |b, input| {
+                    let mut buffer = input.clone();
+                    b.iter(|| scalar::xor_repeating_in_place(black_box(&mut buffer), &key))
+                },
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("kernel/key-{}", key_size), size),
+                &input,
+                |b, input| {
+                    let mut buffer = input.clone();
+                    b.iter(|| kernel::xor_repeating_in_place(black_box(&mut buffer), &key))
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_single_byte(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single-byte");
+    for size in SIZES {
+        let input = vec![0x5a_u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("scalar", size), &input, |b, input| {
+            let mut buffer = input.clone();
+            b.iter(|| scalar::xor_byte_in_place(black_box(&mut buffer), black_box(0x20)))
+        });
+        group.bench_with_input(BenchmarkId::new("kernel", size), &input, |b, input| {
+            let mut buffer = input.clone();
+            b.iter(|| kernel::xor_byte_in_place(black_box(&mut buffer), black_box(0x20)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_fixed, bench_repeating, bench_single_byte);
+criterion_main!(benches);
@@ -0,0 +1,77 @@
+// Constant-time byte operations
+//
+// Conditions are carried as masks, `0xff` for true and `0x00` for false, so
+// that they can be combined with `&` and `|` instead of branches. Lengths
+// are treated as public. `black_box` keeps the optimizer from turning the
+// final mask back into an early exit.
+
+use std::hint::black_box;
+
+// `0xff` when `x == 0`, otherwise `0x00`.
+pub fn ct_is_zero(x: u8) -> u8 {
+    ((x as u16).wrapping_sub(1) >> 8) as u8
+}
+
+// `0xff` when `a == b`, otherwise `0x00`.
+pub fn ct_eq_mask(a: u8, b: u8) -> u8 {
+    ct_is_zero(a ^ b)
+}
+
+// `0xff` when `a < b`, otherwise `0x00`.
+pub fn ct_lt(a: u8, b: u8) -> u8 {
+    ((a as u16).wrapping_sub(b as u16) >> 8) as u8
+}
+
+// `a` when `mask` is `0xff` and `b` when it is `0x00`.
+pub fn ct_select(mask: u8, a: u8, b: u8) -> u8 {
+    (a & mask) | (b & !mask)
+}
+
+// Compares every byte regardless of where the first difference is. Inputs of
+// different lengths are never equal.
+pub fn ct_eq<A: ?Sized + AsRef<[u8]>, B: ?Sized + AsRef<[u8]>>(a: &A, b: &B) -> bool {
+    let a = a.as_ref();
+    let b = b.as_ref();
+    if a.len() != b.len() {
+        return false;
+    }
+    let difference = a.iter().zip(b.iter()).fold(0, |acc, (a, b)| acc | (a ^ b));
+    black_box(ct_is_zero(black_box(difference))) == 0xff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dudect;
+
+    #[quickcheck]
+    fn masks_match_comparisons(a: u8, b: u8) -> bool {
+        let mask = |condition: bool| if condition { 0xff } else { 0x00 };
+        ct_is_zero(a) == mask(a == 0)
+            && ct_eq_mask(a, b) == mask(a == b)
+            && ct_lt(a, b) == mask(a < b)
+            && ct_select(ct_lt(a, b), a, b) == a.min(b)
+    }
+
+    #[quickcheck]
+    fn ct_eq_matches_eq(a: Vec<u8>, b: Vec<u8>) -> bool {
+        ct_eq(&a, &b) == (a == b) && ct_eq(&a, &a)
+    }
+
+    // Run with `cargo test --release -p xor -- --ignored`.
+    #[test]
+    #[ignore]
+    fn ct_eq_has_no_timing_leak() {
+        let secret = vec![0x5a_u8; 512];
+        let equal = vec![secret.clone(); 64];
+        let early: Vec<Vec<u8>> = (0..64)
+            .map(|i| {
+                let mut input = secret.clone();
+                input[0] ^= 1 + i as u8;
+                input
+            })
+            .collect();
+        let t = dudect::t_statistic(&equal, &early, 100_000, |input| ct_eq(&secret, input));
+        assert!(t.abs() < dudect::LEAK_THRESHOLD, "t = {}", t);
+    }
+}
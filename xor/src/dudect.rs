@@ -0,0 +1,138 @@
+// A dudect-style timing leak check.
+//
+// See: https://eprint.iacr.org/2016/1123.pdf
+//
+// The function under test is timed on inputs from two classes, picked in a
+// pseudo-random order. Measurements above a percentile are cropped, then
+// Welch's t-test tells whether the two timing distributions differ. This is
+// a local sanity check: measurements are noisy, so run it in release mode on
+// an otherwise idle machine.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+// dudect treats |t| above 10 as a definite leak.
+pub const LEAK_THRESHOLD: f64 = 10.0;
+
+// Measurements above this percentile are treated as interruptions.
+const CROP_PERCENTILE: f64 = 0.9;
+
+// Online mean and variance (Welford's algorithm).
+#[derive(Clone, Copy, Debug, Default)]
+struct Moments {
+    n: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Moments {
+    fn push(&mut self, x: f64) {
+        self.n += 1.0;
+        let delta = x - self.mean;
+        self.mean += delta / self.n;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        if self.n < 2.0 {
+            0.0
+        } else {
+            self.m2 / (self.n - 1.0)
+        }
+    }
+}
+
+// Welch's t statistic between the measurements of the two classes.
+pub fn welch_t(a: &[f64], b: &[f64]) -> f64 {
+    let mut moments = [Moments::default(); 2];
+    a.iter().for_each(|&x| moments[0].push(x));
+    b.iter().for_each(|&x| moments[1].push(x));
+    let [a, b] = moments;
+    let standard_error = (a.variance() / a.n + b.variance() / b.n).sqrt();
+    if standard_error == 0.0 {
+        0.0
+    } else {
+        (a.mean - b.mean) / standard_error
+    }
+}
+
+// Times `f` `measurements` times on inputs cycled from `class_a` and
+// `class_b` and returns Welch's t statistic for the two classes.
+pub fn t_statistic<T, R, F: FnMut(&T) -> R>(
+    class_a: &[T],
+    class_b: &[T],
+    measurements: usize,
+    mut f: F,
+) -> f64 {
+    assert!(!class_a.is_empty() && !class_b.is_empty());
+    // Warm up caches and the branch predictor before measuring.
+    for input in class_a
+        .iter()
+        .chain(class_b.iter())
+        .cycle()
+        .take(measurements / 10)
+    {
+        black_box(f(black_box(input)));
+    }
+    // xorshift64, so that class order does not line up with anything
+    // periodic in the machine.
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut timings: Vec<(bool, f64)> = Vec::with_capacity(measurements);
+    for i in 0..measurements {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let in_a = state & 1 == 0;
+        let input = if in_a {
+            &class_a[i % class_a.len()]
+        } else {
+            &class_b[i % class_b.len()]
+        };
+        let start = Instant::now();
+        black_box(f(black_box(input)));
+        timings.push((in_a, start.elapsed().as_nanos() as f64));
+    }
+    let mut sorted: Vec<f64> = timings.iter().map(|&(_, t)| t).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let cutoff = sorted[((sorted.len() - 1) as f64 * CROP_PERCENTILE) as usize];
+    let cropped = timings.iter().filter(|&&(_, t)| t <= cutoff);
+    let a: Vec<f64> = cropped
+        .clone()
+        .filter(|&&(c, _)| c)
+        .map(|&(_, t)| t)
+        .collect();
+    let b: Vec<f64> = cropped.filter(|&&(c, _)| !c).map(|&(_, t)| t).collect();
+    welch_t(&a, &b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn welch_t_sanity_check() {
+        assert_eq!(0.0, welch_t(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0]));
+        let t = welch_t(&[10.0, 11.0, 12.0, 11.0], &[1.0, 2.0, 3.0, 2.0]);
+        assert!(t > LEAK_THRESHOLD);
+        assert_eq!(
+            -t,
+            welch_t(&[1.0, 2.0, 3.0, 2.0], &[10.0, 11.0, 12.0, 11.0])
+        );
+    }
+
+    // Makes sure the harness can see an early exit at all. Run with
+    // `cargo test --release -p xor -- --ignored`.
+    #[test]
+    #[ignore]
+    fn detects_early_exit_comparison() {
+        let secret = vec![0x5a_u8; 4096];
+        let equal = vec![secret.clone()];
+        let mut early = secret.clone();
+        early[0] ^= 1;
+        let early = vec![early];
+        let t = t_statistic(&equal, &early, 100_000, |input: &Vec<u8>| {
+            secret.iter().zip(input.iter()).all(|(a, b)| a == b)
+        });
+        assert!(t.abs() > LEAK_THRESHOLD, "t = {}", t);
+    }
+}
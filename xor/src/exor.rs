@@ -33,7 +33,9 @@ impl<T: Sized + AsRef<[u8]>, U: ?Sized + AsRef<[u8]>> ExclusiveFixedXor<T> for U
             return Err(ExclusiveFixedXorError.into());
             // return Err("exor may only be performed on same-length inputs".into());
         }
-        Ok(a.iter().zip(b.iter()).map(|(&c, &d)| c ^ d).collect())
+        let mut output = a.to_vec();
+        crate::kernel::xor_in_place(&mut output, b);
+        Ok(output.into_iter().collect())
     }
 }
 
@@ -45,9 +47,7 @@ impl<T: Sized + AsRef<[u8]>, U: ?Sized + AsMut<[u8]>> ExclusiveFixedXorMut<T> fo
             return Err(ExclusiveFixedXorError.into());
             // return Err("exor may only be performed on same-length inputs".into());
         }
-        a.iter_mut().zip(b.iter()).for_each(|(c, &d)| {
-            *c ^= d;
-        });
+        crate::kernel::xor_in_place(a, b);
         Ok(())
     }
 }
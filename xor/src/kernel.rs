@@ -0,0 +1,194 @@
+// Word-wide XOR kernels
+//
+// The `exor`, `rxor` and `sxor` traits are built on these. Input is XORed 16
+// bytes at a time as `u128` words, then 8 bytes at a time as a `u64`, then a
+// byte at a time for the tail. On x86_64 CPUs with AVX2 the bulk is done 32
+// bytes at a time instead. `scalar` keeps the byte-at-a-time versions as a
+// reference.
+
+// Repeating patterns are expanded to at least this many bytes so that short
+// keys still go through the wide path.
+const PATTERN_SIZE: usize = 256;
+
+// XORs `b` into `a`. Panics if the lengths differ.
+pub fn xor_in_place(a: &mut [u8], b: &[u8]) {
+    assert_eq!(a.len(), b.len(), "xor_in_place needs same-length inputs");
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::is_x86_feature_detected!("avx2") {
+            // Safe because AVX2 support was just checked.
+            unsafe { avx2::xor_in_place(a, b) };
+            return;
+        }
+    }
+    portable::xor_in_place(a, b);
+}
+
+// XORs `key` repeatedly into `a`, starting from the first byte of `key`. An
+// empty key leaves `a` unchanged.
+pub fn xor_repeating_in_place(a: &mut [u8], key: &[u8]) {
+    if key.is_empty() {
+        return;
+    }
+    if key.len() >= PATTERN_SIZE {
+        for chunk in a.chunks_mut(key.len()) {
+            let len = chunk.len();
+            xor_in_place(chunk, &key[..len]);
+        }
+        return;
+    }
+    let repeats = PATTERN_SIZE.div_ceil(key.len());
+    let pattern = key.repeat(repeats);
+    for chunk in a.chunks_mut(pattern.len()) {
+        let len = chunk.len();
+        xor_in_place(chunk, &pattern[..len]);
+    }
+}
+
+// XORs `byte` into every byte of `a`, using `byte` broadcast to a `u128`.
+pub fn xor_byte_in_place(a: &mut [u8], byte: u8) {
+    let word = u128::from_ne_bytes([byte; 16]);
+    let mut a_words = a.chunks_exact_mut(16);
+    for c in &mut a_words {
+        let mixed = u128::from_ne_bytes((&*c).try_into().unwrap()) ^ word;
+        c.copy_from_slice(&mixed.to_ne_bytes());
+    }
+    a_words.into_remainder().iter_mut().for_each(|c| {
+        *c ^= byte;
+    });
+}
+
+mod portable {
+    pub(super) fn xor_in_place(a: &mut [u8], b: &[u8]) {
+        let mut a_words = a.chunks_exact_mut(16);
+        let mut b_words = b.chunks_exact(16);
+        for (c, d) in (&mut a_words).zip(&mut b_words) {
+            let word = u128::from_ne_bytes(c.try_into().unwrap())
+                ^ u128::from_ne_bytes(d.try_into().unwrap());
+            c.copy_from_slice(&word.to_ne_bytes());
+        }
+        let mut a_words = a_words.into_remainder().chunks_exact_mut(8);
+        let mut b_words = b_words.remainder().chunks_exact(8);
+        for (c, d) in (&mut a_words).zip(&mut b_words) {
+            let word = u64::from_ne_bytes(c.try_into().unwrap())
+                ^ u64::from_ne_bytes(d.try_into().unwrap());
+            c.copy_from_slice(&word.to_ne_bytes());
+        }
+        let a = a_words.into_remainder();
+        let b = b_words.remainder();
+        a.iter_mut().zip(b.iter()).for_each(|(c, &d)| {
+            *c ^= d;
+        });
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::{__m256i, _mm256_loadu_si256, _mm256_storeu_si256, _mm256_xor_si256};
+
+    // The caller must make sure the CPU supports AVX2 and that `a` and `b`
+    // have the same length.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn xor_in_place(a: &mut [u8], b: &[u8]) {
+        let blocks = a.len() / 32;
+        for i in 0..blocks {
+            let c = a.as_mut_ptr().add(i * 32) as *mut __m256i;
+            let d = b.as_ptr().add(i * 32) as *const __m256i;
+            _mm256_storeu_si256(
+                c,
+                _mm256_xor_si256(_mm256_loadu_si256(c), _mm256_loadu_si256(d)),
+            );
+        }
+        super::portable::xor_in_place(&mut a[(blocks * 32)..], &b[(blocks * 32)..]);
+    }
+}
+
+// Byte-at-a-time reference versions of the kernels.
+pub mod scalar {
+    pub fn xor_in_place(a: &mut [u8], b: &[u8]) {
+        assert_eq!(a.len(), b.len(), "xor_in_place needs same-length inputs");
+        a.iter_mut().zip(b.iter()).for_each(|(c, &d)| {
+            *c ^= d;
+        });
+    }
+
+    pub fn xor_repeating_in_place(a: &mut [u8], key: &[u8]) {
+        if !key.is_empty() {
+            a.iter_mut().zip(key.iter().cycle()).for_each(|(c, &d)| {
+                *c ^= d;
+            });
+        }
+    }
+
+    pub fn xor_byte_in_place(a: &mut [u8], byte: u8) {
+        a.iter_mut().for_each(|c| {
+            *c ^= byte;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{portable, scalar};
+
+    // Pairs `xs` with a same-length slice of `ys` (cycled), starting at
+    // `offset` so that unaligned inputs are covered too.
+    fn operands(xs: &[u8], ys: &[u8], offset: u8) -> (Vec<u8>, Vec<u8>) {
+        let offset = (offset as usize) % (xs.len() + 1);
+        let a = xs[offset..].to_vec();
+        let b = if ys.is_empty() {
+            vec![0x5a; a.len()]
+        } else {
+            ys.iter().cycle().take(a.len()).copied().collect()
+        };
+        (a, b)
+    }
+
+    #[quickcheck]
+    fn xor_in_place_matches_scalar(xs: Vec<u8>, ys: Vec<u8>, offset: u8) -> bool {
+        let (a, b) = operands(&xs, &ys, offset);
+        let mut expected = a.clone();
+        scalar::xor_in_place(&mut expected, &b);
+        let mut challenge = a.clone();
+        super::xor_in_place(&mut challenge, &b);
+        let mut fallback = a;
+        portable::xor_in_place(&mut fallback, &b);
+        expected == challenge && expected == fallback
+    }
+
+    #[quickcheck]
+    fn xor_repeating_in_place_matches_scalar(xs: Vec<u8>, key: Vec<u8>) -> bool {
+        let mut expected = xs.clone();
+        scalar::xor_repeating_in_place(&mut expected, &key);
+        let mut challenge = xs;
+        super::xor_repeating_in_place(&mut challenge, &key);
+        expected == challenge
+    }
+
+    #[quickcheck]
+    fn xor_byte_in_place_matches_scalar(xs: Vec<u8>, byte: u8) -> bool {
+        let mut expected = xs.clone();
+        scalar::xor_byte_in_place(&mut expected, byte);
+        let mut challenge = xs;
+        super::xor_byte_in_place(&mut challenge, byte);
+        expected == challenge
+    }
+
+    #[test]
+    fn long_inputs_match_scalar() {
+        let a: Vec<u8> = (0..4133_u32).map(|i| (i * 7 + 3) as u8).collect();
+        let b: Vec<u8> = (0..4133_u32).map(|i| (i * 13 + 1) as u8).collect();
+        for key in [&b[..1], &b[..29], &b[..300]] {
+            let mut expected = a.clone();
+            scalar::xor_repeating_in_place(&mut expected, key);
+            let mut challenge = a.clone();
+            super::xor_repeating_in_place(&mut challenge, key);
+            assert_eq!(expected, challenge);
+        }
+        let mut expected = a.clone();
+        scalar::xor_in_place(&mut expected, &b);
+        let mut challenge = a;
+        super::xor_in_place(&mut challenge, &b);
+        assert_eq!(expected, challenge);
+    }
+}
@@ -0,0 +1,115 @@
+// Keystreams
+//
+// A stream cipher encrypts and decrypts by XORing the same keystream into its
+// input. Implementations only need `apply`; `fill` recovers the raw keystream
+// bytes, and `seek` is available for keystreams that support random access.
+// A keystream of limited length fails rather than wrapping around once it
+// runs out.
+
+#[derive(Debug, Clone)]
+pub struct KeystreamSeekError {
+    pub position: u64,
+}
+
+// This is important for other errors to wrap this one.
+impl std::error::Error for KeystreamSeekError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl std::fmt::Display for KeystreamSeekError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "keystream cannot seek to position {}", self.position)
+    }
+}
+
+pub trait Keystream {
+    // XORs the next `input.len()` keystream bytes into `input`, or fails
+    // without touching it if there are not that many left.
+    fn apply(&mut self, input: &mut [u8]) -> crate::Result<()>;
+
+    // Overwrites `output` with the next `output.len()` keystream bytes.
+    fn fill(&mut self, output: &mut [u8]) -> crate::Result<()> {
+        output.fill(0);
+        self.apply(output)
+    }
+
+    // Moves to an absolute byte position of the keystream. Fails with
+    // `KeystreamSeekError` unless the keystream supports it.
+    fn seek(&mut self, position: u64) -> crate::Result<()> {
+        Err(KeystreamSeekError { position }.into())
+    }
+}
+
+impl<K: ?Sized + Keystream> Keystream for &mut K {
+    fn apply(&mut self, input: &mut [u8]) -> crate::Result<()> {
+        (**self).apply(input)
+    }
+
+    fn fill(&mut self, output: &mut [u8]) -> crate::Result<()> {
+        (**self).fill(output)
+    }
+
+    fn seek(&mut self, position: u64) -> crate::Result<()> {
+        (**self).seek(position)
+    }
+}
+
+impl<K: ?Sized + Keystream> Keystream for Box<K> {
+    fn apply(&mut self, input: &mut [u8]) -> crate::Result<()> {
+        (**self).apply(input)
+    }
+
+    fn fill(&mut self, output: &mut [u8]) -> crate::Result<()> {
+        (**self).fill(output)
+    }
+
+    fn seek(&mut self, position: u64) -> crate::Result<()> {
+        (**self).seek(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Keystream;
+
+    // Keystream byte `i` is `i as u8`; cannot seek.
+    struct Counter(u8);
+
+    impl Keystream for Counter {
+        fn apply(&mut self, input: &mut [u8]) -> crate::Result<()> {
+            for c in input.iter_mut() {
+                *c ^= self.0;
+                self.0 = self.0.wrapping_add(1);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn fill_sanity_check() {
+        let mut keystream = Counter(0);
+        let mut output = [0xff_u8; 4];
+        keystream.fill(&mut output).unwrap();
+        assert_eq!([0, 1, 2, 3], output);
+        let mut boxed: Box<dyn Keystream> = Box::new(keystream);
+        boxed.fill(&mut output).unwrap();
+        assert_eq!([4, 5, 6, 7], output);
+    }
+
+    #[test]
+    fn seek_is_unsupported_by_default() {
+        let mut keystream = Counter(0);
+        assert!(keystream.seek(0).is_err());
+    }
+
+    #[quickcheck]
+    fn apply_twice_is_identity(xs: Vec<u8>) -> bool {
+        let mut ys = xs.clone();
+        Counter(0).apply(&mut ys).unwrap();
+        Counter(0).apply(&mut ys).unwrap();
+        ys == xs
+    }
+}
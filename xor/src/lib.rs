@@ -14,12 +14,17 @@ extern crate quickcheck_macros;
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
+pub mod ct;
+pub mod dudect;
 pub mod exor;
+pub mod kernel;
+pub mod keystream;
 pub mod rxor;
 pub mod sxor;
 
 pub mod prelude {
     pub use crate::exor::*;
+    pub use crate::keystream::*;
     pub use crate::rxor::*;
     pub use crate::sxor::*;
 }
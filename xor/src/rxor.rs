@@ -12,16 +12,9 @@ impl<T: Sized + AsRef<[u8]>, U: ?Sized + AsRef<[u8]>> RepeatingKeyXor<T> for U {
     fn rxor<I: std::iter::FromIterator<u8>>(&self, t: &T) -> I {
         let a = self.as_ref();
         let b = t.as_ref();
-        if b.is_empty() {
-            a.iter().copied().collect()
-        } else {
-            a.chunks(b.len())
-                .flat_map(|chunk| {
-                    let len = chunk.len();
-                    chunk.iter().zip(b[..len].iter()).map(|(&c, &d)| c ^ d)
-                })
-                .collect()
-        }
+        let mut output = a.to_vec();
+        crate::kernel::xor_repeating_in_place(&mut output, b);
+        output.into_iter().collect()
     }
 }
 
@@ -29,14 +22,7 @@ impl<T: Sized + AsRef<[u8]>, U: ?Sized + AsMut<[u8]>> RepeatingKeyXorMut<T> for
     fn rxor_mut(&mut self, t: &T) {
         let a = self.as_mut();
         let b = t.as_ref();
-        if !b.is_empty() {
-            a.chunks_mut(b.len()).for_each(|chunk| {
-                let len = chunk.len();
-                chunk.iter_mut().zip(b[..len].iter()).for_each(|(c, &d)| {
-                    *c ^= d;
-                });
-            });
-        }
+        crate::kernel::xor_repeating_in_place(a, b);
     }
 }
 
@@ -11,16 +11,16 @@ pub trait SingleByteXorMut {
 impl<U: ?Sized + AsRef<[u8]>> SingleByteXor for U {
     fn sxor<I: std::iter::FromIterator<u8>>(&self, b: u8) -> I {
         let a = self.as_ref();
-        a.iter().map(|&c| c ^ b).collect()
+        let mut output = a.to_vec();
+        crate::kernel::xor_byte_in_place(&mut output, b);
+        output.into_iter().collect()
     }
 }
 
 impl<U: ?Sized + AsMut<[u8]>> SingleByteXorMut for U {
     fn sxor_mut(&mut self, b: u8) {
         let a = self.as_mut();
-        a.iter_mut().for_each(|c| {
-            *c ^= b;
-        });
+        crate::kernel::xor_byte_in_place(a, b);
     }
 }
 